@@ -0,0 +1,129 @@
+use std::marker::PhantomData;
+
+use poem::{Error, Request, RequestBody, Result, http::StatusCode};
+use poem_openapi::{
+    ApiExtractor, ApiExtractorType, ExtractParamOptions, SecurityScheme, auth::Bearer,
+    registry::Registry,
+};
+use service::{ApiKeyDatabase, ApiKeyScope, api_key_scopes};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{rate_limit::KeyRateLimit, session::CurrentUser};
+
+/// Scope an endpoint requires from api keys.
+pub trait RequiredScope: Send + Sync {
+    const SCOPE: ApiKeyScope;
+}
+
+pub struct Read;
+pub struct Write;
+pub struct Stats;
+
+impl RequiredScope for Read {
+    const SCOPE: ApiKeyScope = ApiKeyScope::Read;
+}
+
+impl RequiredScope for Write {
+    const SCOPE: ApiKeyScope = ApiKeyScope::Write;
+}
+
+impl RequiredScope for Stats {
+    const SCOPE: ApiKeyScope = ApiKeyScope::Stats;
+}
+
+/// Api key created on the account, sent as `Authorization: Bearer qrk_<prefix>_<secret>`.
+#[derive(SecurityScheme)]
+#[oai(
+    rename = "ApiKey",
+    ty = "bearer",
+    bearer_format = "qrk_<prefix>_<secret>",
+    checker = "check_api_key"
+)]
+struct ApiKeyBearer(entity::api_key::Model);
+
+async fn check_api_key(req: &Request, bearer: Bearer) -> Result<entity::api_key::Model> {
+    let Some(api_keys) = req.data::<ApiKeyDatabase>() else {
+        return Err(Error::from_status(StatusCode::INTERNAL_SERVER_ERROR));
+    };
+
+    match api_keys.authenticate(&bearer.token).await {
+        Ok(Some(api_key)) => {
+            if let Some(limit) = req.data::<KeyRateLimit>() {
+                limit.charge(api_key.id)?;
+            }
+
+            Ok(api_key)
+        }
+        Ok(None) => Err(Error::from_string(
+            "The api key is invalid or expired.",
+            StatusCode::UNAUTHORIZED,
+        )),
+        Err(why) => {
+            error!("Failed to look up api key, {why}");
+            Err(Error::from_string(
+                "Could not check the api key, because of an internal error.",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+/// User of the api key sent with the request, requests without an `Authorization` header
+/// stay anonymous while invalid keys or keys without the scope `S` are rejected.
+pub struct ApiKeyAuth<S> {
+    pub user_id: Option<Uuid>,
+    scope: PhantomData<S>,
+}
+
+impl<S> ApiKeyAuth<S> {
+    /// Falls back to the user of the session cookie.
+    pub fn or(self, user: CurrentUser) -> CurrentUser {
+        CurrentUser(self.user_id.or(user.0))
+    }
+}
+
+impl<'a, S: RequiredScope> ApiExtractor<'a> for ApiKeyAuth<S> {
+    const TYPES: &'static [ApiExtractorType] = &[ApiExtractorType::SecurityScheme];
+
+    type ParamType = ();
+    type ParamRawType = ();
+
+    fn register(registry: &mut Registry) {
+        ApiKeyBearer::register(registry);
+    }
+
+    fn security_schemes() -> Vec<&'static str> {
+        ApiKeyBearer::security_schemes()
+    }
+
+    fn has_security_fallback() -> bool {
+        true
+    }
+
+    async fn from_request(
+        request: &'a Request,
+        body: &mut RequestBody,
+        param_opts: ExtractParamOptions<Self::ParamType>,
+    ) -> Result<Self> {
+        if !request.headers().contains_key("Authorization") {
+            return Ok(Self {
+                user_id: None,
+                scope: PhantomData,
+            });
+        }
+
+        let ApiKeyBearer(api_key) = ApiKeyBearer::from_request(request, body, param_opts).await?;
+        if !api_key_scopes(&api_key).contains(&S::SCOPE) {
+            return Err(Error::from_string(
+                format!("The api key lacks the {} scope.", S::SCOPE),
+                StatusCode::FORBIDDEN,
+            ));
+        }
+
+        Ok(Self {
+            user_id: Some(api_key.user_id),
+            scope: PhantomData,
+        })
+    }
+}
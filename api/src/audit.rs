@@ -0,0 +1,23 @@
+use poem::{FromRequest, Request, RequestBody, Result};
+use service::{AuditContext, IpHasher};
+
+use crate::{config::AppConfig, rate_limit::client_ip};
+
+/// Audit context of the request, identifying the client by the hash of its ip.
+pub struct Audit(pub AuditContext);
+
+impl<'a> FromRequest<'a> for Audit {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let (Some(hasher), Some(config)) = (req.data::<IpHasher>(), req.data::<AppConfig>()) else {
+            return Ok(Self(AuditContext::default()));
+        };
+
+        let client_ip = client_ip(
+            req.remote_addr().as_socket_addr().map(|x| x.ip()),
+            req.header("X-Forwarded-For"),
+            &config.trusted_proxies,
+        );
+
+        Ok(Self(hasher.context(client_ip)))
+    }
+}
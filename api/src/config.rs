@@ -1,20 +1,187 @@
 use std::env;
 
+use ipnet::IpNet;
+use service::{LinkPolicyMode, OidcConfig, SmtpConfig, SmtpSecurity};
+use url::Url;
+
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub database_url: String,
     pub server_url: String,
     pub image_base_path: String,
     pub domain_name: String, // New field
+    pub cookie_secret: Option<String>,
+    pub unlock_ttl_minutes: i64,
+    pub country_header: String,
+    pub trash_retention_days: i64,
+    pub purge_interval_minutes: u64,
+    pub link_policy_file: Option<String>,
+    pub link_policy_mode: LinkPolicyMode,
+    pub link_check_interval_minutes: u64,
+    pub link_check_timeout_seconds: u64,
+    pub preview_title_timeout_seconds: u64,
+    pub recovery_secret: Option<String>,
+    pub recovery_token_minutes: i64,
+    pub mail_file: String,
+    pub smtp: Option<SmtpConfig>,
+    pub session_ttl_days: i64,
+    pub oidc: Option<OidcConfig>,
+    pub rate_limits: RateLimits,
+    pub trusted_proxies: Vec<IpNet>,
+    pub admin_emails: Vec<String>,
+    pub audit_ip_secret: Option<String>,
+}
+
+/// Requests per minute and client for each kind of endpoint, `0` disables the limit.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimits {
+    pub create: u32,
+    pub mutate: u32,
+    pub image: u32,
+    pub redirect: u32,
 }
 
 impl AppConfig {
     pub fn from_env() -> Self {
+        let domain_name = env::var("DOMAIN_NAME").unwrap_or_else(|_| "localhost".to_string());
+
         Self {
             database_url: env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
             server_url: env::var("SERVER_URL").unwrap_or_else(|_| "0.0.0.0:3000".to_string()),
             image_base_path: env::var("IMAGE_BASE_PATH").unwrap_or_else(|_| "./images".to_string()),
-            domain_name: env::var("DOMAIN_NAME").unwrap_or_else(|_| "localhost".to_string()), // New field
+            domain_name: domain_name.clone(), // New field
+            cookie_secret: env::var("COOKIE_SECRET").ok(),
+            unlock_ttl_minutes: env::var("UNLOCK_TTL_MINUTES")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(60),
+            country_header: env::var("COUNTRY_HEADER")
+                .unwrap_or_else(|_| "CF-IPCountry".to_string()),
+            trash_retention_days: env::var("TRASH_RETENTION_DAYS")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(30),
+            purge_interval_minutes: env::var("PURGE_INTERVAL_MINUTES")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(60),
+            link_policy_file: env::var("LINK_POLICY_FILE").ok(),
+            link_policy_mode: match env::var("LINK_POLICY_MODE").as_deref() {
+                Ok("allowlist") => LinkPolicyMode::Allowlist,
+                _ => LinkPolicyMode::Blocklist,
+            },
+            link_check_interval_minutes: env::var("LINK_CHECK_INTERVAL_MINUTES")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(360),
+            link_check_timeout_seconds: env::var("LINK_CHECK_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(10),
+            preview_title_timeout_seconds: env::var("PREVIEW_TITLE_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(3),
+            recovery_secret: env::var("RECOVERY_SECRET").ok(),
+            recovery_token_minutes: env::var("RECOVERY_TOKEN_MINUTES")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(30),
+            mail_file: env::var("MAIL_FILE").unwrap_or_else(|_| "./mails.log".to_string()),
+            smtp: smtp_from_env(),
+            session_ttl_days: env::var("SESSION_TTL_DAYS")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(14),
+            oidc: oidc_from_env(&domain_name),
+            rate_limits: RateLimits {
+                create: per_minute_from_env("RATE_LIMIT_CREATE", 10),
+                mutate: per_minute_from_env("RATE_LIMIT_MUTATE", 60),
+                image: per_minute_from_env("RATE_LIMIT_IMAGE", 120),
+                redirect: per_minute_from_env("RATE_LIMIT_REDIRECT", 600),
+            },
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(|x| {
+                    x.parse::<IpNet>()
+                        .or_else(|_| x.parse::<std::net::IpAddr>().map(IpNet::from))
+                        .expect("TRUSTED_PROXIES must be a comma separated list of ips or cidrs")
+                })
+                .collect(),
+            admin_emails: env::var("ADMIN_EMAILS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|x| x.trim().to_lowercase())
+                .filter(|x| !x.is_empty())
+                .collect(),
+            audit_ip_secret: env::var("AUDIT_IP_SECRET").ok(),
         }
     }
-}
\ No newline at end of file
+}
+
+fn per_minute_from_env(key: &str, default: u32) -> u32 {
+    env::var(key)
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Single sign-on is only enabled when `OIDC_ISSUER` is set.
+fn oidc_from_env(domain_name: &str) -> Option<OidcConfig> {
+    let issuer = env::var("OIDC_ISSUER").ok()?;
+
+    let mut scopes: Vec<String> = env::var("OIDC_SCOPES")
+        .unwrap_or_else(|_| "openid email profile".to_string())
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    if !scopes.iter().any(|x| x == "openid") {
+        scopes.insert(0, "openid".to_string());
+    }
+
+    Some(OidcConfig {
+        issuer,
+        client_id: env::var("OIDC_CLIENT_ID").expect("OIDC_CLIENT_ID must be set for OIDC_ISSUER"),
+        client_secret: env::var("OIDC_CLIENT_SECRET")
+            .expect("OIDC_CLIENT_SECRET must be set for OIDC_ISSUER"),
+        scopes,
+        redirect_url: env::var("OIDC_REDIRECT_URL")
+            .unwrap_or_else(|_| format!("http://{domain_name}/api/account/oidc/callback"))
+            .parse::<Url>()
+            .expect("OIDC_REDIRECT_URL must be a valid url"),
+    })
+}
+
+/// Mails are only sent with smtp when `MAIL_TRANSPORT` is set to `smtp`, otherwise they are
+/// written to `MAIL_FILE`.
+fn smtp_from_env() -> Option<SmtpConfig> {
+    if env::var("MAIL_TRANSPORT").as_deref() != Ok("smtp") {
+        return None;
+    }
+
+    let host = env::var("SMTP_HOST").expect("SMTP_HOST must be set for MAIL_TRANSPORT=smtp");
+    let security = match env::var("SMTP_SECURITY").as_deref() {
+        Ok("none") => SmtpSecurity::None,
+        Ok("tls") => SmtpSecurity::Tls,
+        _ => SmtpSecurity::StartTls,
+    };
+
+    Some(SmtpConfig {
+        from: env::var("MAIL_FROM").unwrap_or_else(|_| format!("qr@{host}")),
+        port: env::var("SMTP_PORT")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(match security {
+                SmtpSecurity::None => 25,
+                SmtpSecurity::StartTls => 587,
+                SmtpSecurity::Tls => 465,
+            }),
+        host,
+        security,
+        username: env::var("SMTP_USERNAME").ok(),
+        password: env::var("SMTP_PASSWORD").ok(),
+    })
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use service::{HttpLinkProbe, LinkHealthDatabase, LinkPolicy, LinkProbe, QrCodeDatabase};
+use tracing::{error, info, warn};
+
+use crate::config::AppConfig;
+
+/// Periodically hard deletes qr codes whose trash retention period has run out.
+pub fn spawn_trash_purge(database: QrCodeDatabase, config: &AppConfig) {
+    let retention = chrono::Duration::days(config.trash_retention_days);
+    let interval = Duration::from_secs(config.purge_interval_minutes.max(1) * 60);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match database.purge_deleted(retention).await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {purged} deleted qr codes"),
+                Err(why) => error!("Failed to purge deleted qr codes, {why}"),
+            }
+        }
+    });
+}
+
+/// Picks up edits of the link policy file, redirects only use the rules loaded in memory.
+pub fn spawn_link_policy_reload(link_policy: LinkPolicy) {
+    if !link_policy.has_file() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(5));
+
+        loop {
+            ticker.tick().await;
+            link_policy.reload_if_changed().await;
+        }
+    });
+}
+
+/// Periodically checks the destination links of all qr codes, disabled with an interval of 0.
+pub fn spawn_link_checker(database: LinkHealthDatabase, config: &AppConfig) {
+    if config.link_check_interval_minutes == 0 {
+        return;
+    }
+
+    let probe = match HttpLinkProbe::new(Duration::from_secs(config.link_check_timeout_seconds)) {
+        Ok(probe) => probe,
+        Err(why) => {
+            warn!("Link health checks are disabled, the http client failed to start, {why}");
+            return;
+        }
+    };
+    let interval = Duration::from_secs(config.link_check_interval_minutes * 60);
+
+    tokio::spawn(run_link_checker(database, probe, interval));
+}
+
+async fn run_link_checker<P: LinkProbe>(
+    database: LinkHealthDatabase,
+    probe: P,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        match database.check_all(&probe).await {
+            Ok(checked) => info!("Checked the links of {checked} qr codes"),
+            Err(why) => error!("Failed to check the links of the qr codes, {why}"),
+        }
+    }
+}
@@ -1,16 +1,43 @@
+// The endpoint type nests one layer per `.data()` and middleware.
+#![recursion_limit = "256"]
+
+mod api_key;
+mod audit;
 mod config;
+mod jobs;
 mod pages;
+mod rate_limit;
+mod request_log;
 mod services;
+mod session;
+#[cfg(test)]
+mod testing;
 
 use migration::sea_orm::Database;
-use poem::{EndpointExt, Route, Server, get, listener::TcpListener, middleware::Tracing};
+use poem::{
+    EndpointExt, Route, Server, get, listener::TcpListener, middleware::CookieJarManager,
+    web::cookie::CookieKey,
+};
 use poem_openapi::OpenApiService;
-use service::{QrCodeDatabase, QrCodeGenerator};
+use service::{
+    AliasDatabase, ApiKeyDatabase, AttemptThrottle, AuditDatabase, ConfiguredMailer,
+    DestinationResolver, DestinationSigner, FileMailer, IpHasher, LinkHealthDatabase, LinkPolicy,
+    OidcClient, OrganizationDatabase, PassphraseRecovery, QrCodeDatabase, QrCodeGenerator,
+    RevisionDatabase, ScheduleDatabase, SmtpMailer, TargetingDatabase, TitleFetcher, UserDatabase,
+    VariantDatabase,
+};
+use tracing::warn;
 
 use crate::{
     config::AppConfig,
     pages::*,
-    services::{HealthApi, ImageApi, QrCodeApi, RedirectApi, VersionApi},
+    rate_limit::RateLimiting,
+    request_log::RedactedTracing,
+    services::{
+        AccountApi, AliasApi, ApiKeyApi, AuditApi, HealthApi, HistoryApi, ImageApi, LinkHealthApi,
+        OrganizationApi, QrCodeApi, RecoveryApi, RedirectApi, ScheduleApi, SsoApi, StatsApi,
+        TargetingApi, VariantApi, VersionApi, short_redirect,
+    },
 };
 
 pub static PACKAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -18,50 +45,173 @@ pub static PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
 
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
-    dotenvy::dotenv();
+    let _ = dotenvy::dotenv();
 
     if std::env::var_os("RUST_LOG").is_none() {
-        unsafe { std::env::set_var("RUST_LOG", "poem=debug") };
+        unsafe { std::env::set_var("RUST_LOG", "poem=debug,api=info,security=info") };
     }
     tracing_subscriber::fmt::init();
 
     let app_config = AppConfig::from_env();
 
-    let conn = Database::connect(&app_config.database_url)
-        .await
-        .unwrap();
+    let conn = Database::connect(&app_config.database_url).await.unwrap();
 
     let qr_code_database = QrCodeDatabase {
         db_conn: conn.clone(),
     };
+    let schedule_database = ScheduleDatabase {
+        db_conn: conn.clone(),
+    };
+    let targeting_database = TargetingDatabase {
+        db_conn: conn.clone(),
+    };
+    let variant_database = VariantDatabase {
+        db_conn: conn.clone(),
+    };
+    let alias_database = AliasDatabase {
+        db_conn: conn.clone(),
+    };
+    let revision_database = RevisionDatabase {
+        db_conn: conn.clone(),
+    };
+    let user_database = UserDatabase {
+        db_conn: conn.clone(),
+    };
+    let api_key_database = ApiKeyDatabase {
+        db_conn: conn.clone(),
+    };
+    let organization_database = OrganizationDatabase {
+        db_conn: conn.clone(),
+    };
+    let link_health_database = LinkHealthDatabase {
+        db_conn: conn.clone(),
+    };
+    let audit_database = AuditDatabase {
+        db_conn: conn.clone(),
+    };
+    let link_policy = LinkPolicy::new(
+        app_config.link_policy_mode,
+        app_config.link_policy_file.clone().map(Into::into),
+    )
+    .expect("LINK_POLICY_FILE must be readable");
+    let destination_resolver = DestinationResolver {
+        schedule: schedule_database.clone(),
+        targeting: targeting_database.clone(),
+        variants: variant_database.clone(),
+        link_policy: link_policy.clone(),
+        signer: DestinationSigner::new(app_config.cookie_secret.as_deref().map(str::as_bytes)),
+    };
+    let title_fetcher = TitleFetcher::new(std::time::Duration::from_secs(
+        app_config.preview_title_timeout_seconds,
+    ))
+    .expect("the http client for preview titles must build");
     let qr_generator = QrCodeGenerator {
         db_conn: conn.clone(),
-        image_base_path: app_config.image_base_path.into(),
+        image_base_path: app_config.image_base_path.clone().into(),
         server_url: format!("http://{}", app_config.domain_name),
     };
+    if app_config.recovery_secret.is_none() {
+        warn!("RECOVERY_SECRET is not set, recovery tokens will stop working on restart");
+    }
+    let passphrase_recovery = PassphraseRecovery::new(
+        conn.clone(),
+        app_config.recovery_secret.as_deref().map(str::as_bytes),
+        chrono::Duration::minutes(app_config.recovery_token_minutes),
+        format!("http://{}", app_config.domain_name),
+    );
+    if app_config.audit_ip_secret.is_none() {
+        warn!(
+            "AUDIT_IP_SECRET is not set, client ip hashes in the audit log will change on restart"
+        );
+    }
+    let ip_hasher = IpHasher::new(app_config.audit_ip_secret.as_deref().map(str::as_bytes));
+    let mailer = match app_config.smtp.clone() {
+        Some(smtp) => ConfiguredMailer::Smtp(SmtpMailer::new(smtp)),
+        None => ConfiguredMailer::File(FileMailer::new(&app_config.mail_file)),
+    };
+
+    let oidc_client = app_config
+        .oidc
+        .clone()
+        .map(|config| OidcClient::new(config).expect("the oidc http client must build"));
+
+    jobs::spawn_trash_purge(qr_code_database.clone(), &app_config);
+    jobs::spawn_link_checker(link_health_database.clone(), &app_config);
+    jobs::spawn_link_policy_reload(link_policy.clone());
+
+    let cookie_key = match &app_config.cookie_secret {
+        Some(secret) => CookieKey::derive_from(secret.as_bytes()),
+        None => {
+            warn!("COOKIE_SECRET is not set, unlocked qr codes will be locked again on restart");
+            CookieKey::generate()
+        }
+    };
 
     let api_service = OpenApiService::new(
-        (HealthApi, RedirectApi, QrCodeApi, VersionApi, ImageApi),
+        (
+            HealthApi,
+            RedirectApi,
+            QrCodeApi,
+            VersionApi,
+            ImageApi,
+            ScheduleApi,
+            TargetingApi,
+            VariantApi,
+            StatsApi,
+            AliasApi,
+            HistoryApi,
+            LinkHealthApi,
+            RecoveryApi,
+            AuditApi,
+            // Tuples only implement `OpenApi` up to 16 apis, so the account ones are grouped.
+            (AccountApi, SsoApi, ApiKeyApi, OrganizationApi),
+        ),
         "qrcode",
         "1.0",
     )
     .server("/api");
     let ui = api_service.swagger_ui();
 
-    Server::new(TcpListener::bind(&app_config.server_url))
+    Server::new(TcpListener::bind(app_config.server_url.clone()))
         .run(
             Route::new()
                 .at("/", get(index_ui))
                 .at("/new", get(new_ui))
                 .at("/edit", get(edit_ui))
                 .at("/delete", get(delete_ui))
+                .at("/account", get(account_ui))
                 .at("/impressum", get(legal_notice_ui))
                 .at("/privacy", get(privacy_ui))
+                .at("/r/:slug", get(short_redirect))
                 .nest("/api", api_service)
                 .nest("/docs", ui)
-                .with(Tracing)
+                .with(RateLimiting::new(
+                    app_config.rate_limits,
+                    &app_config.trusted_proxies,
+                ))
+                .with(RedactedTracing)
+                .with(CookieJarManager::with_key(cookie_key))
                 .data(qr_generator)
-                .data(qr_code_database),
+                .data(qr_code_database)
+                .data(schedule_database)
+                .data(targeting_database)
+                .data(variant_database)
+                .data(alias_database)
+                .data(revision_database)
+                .data(link_health_database)
+                .data(audit_database)
+                .data(ip_hasher)
+                .data(destination_resolver)
+                .data(title_fetcher)
+                .data(link_policy)
+                .data(passphrase_recovery)
+                .data(user_database)
+                .data(api_key_database)
+                .data(organization_database)
+                .data(oidc_client)
+                .data(mailer)
+                .data(AttemptThrottle::default())
+                .data(app_config),
         )
         .await
 }
@@ -1,5 +1,10 @@
 use askama::Template;
-use poem::{handler, web::Html};
+use poem::{
+    handler,
+    web::{Data, Html},
+};
+
+use crate::config::AppConfig;
 
 #[derive(Debug, Template)]
 #[template(path = "index.html")]
@@ -74,6 +79,26 @@ pub fn delete_ui() -> Html<String> {
     Html(delete)
 }
 
+#[derive(Debug, Template)]
+#[template(path = "account.html")]
+struct AccountTemplate<'a> {
+    current: &'a str,
+    year: i32,
+    sso: bool,
+}
+
+#[handler]
+pub fn account_ui(Data(config): Data<&AppConfig>) -> Html<String> {
+    let account = AccountTemplate {
+        year: 2025,
+        current: "account",
+        sso: config.oidc.is_some(),
+    }
+    .render()
+    .unwrap();
+    Html(account)
+}
+
 #[derive(Debug, Template)]
 #[template(path = "privacy.html")]
 struct PrivacyTemplate<'a> {
@@ -108,4 +133,64 @@ pub fn legal_notice_ui() -> Html<String> {
     .render()
     .unwrap();
     Html(delete)
-}
\ No newline at end of file
+}
+#[derive(Debug, Template)]
+#[template(path = "unlock.html")]
+struct UnlockTemplate<'a> {
+    current: &'a str,
+    year: i32,
+    query: &'a str,
+    error: Option<&'a str>,
+}
+
+pub fn unlock_page(query: &str, error: Option<&str>) -> String {
+    UnlockTemplate {
+        year: 2025,
+        current: "unlock",
+        query,
+        error,
+    }
+    .render()
+    .unwrap()
+}
+
+#[derive(Debug, Template)]
+#[template(path = "unavailable.html")]
+struct UnavailableTemplate<'a> {
+    current: &'a str,
+    year: i32,
+}
+
+pub fn unavailable_page() -> String {
+    UnavailableTemplate {
+        year: 2025,
+        current: "unavailable",
+    }
+    .render()
+    .unwrap()
+}
+
+#[derive(Debug, Template)]
+#[template(path = "preview.html")]
+struct PreviewTemplate<'a> {
+    current: &'a str,
+    year: i32,
+    url: &'a str,
+    domain: &'a str,
+    title: Option<&'a str>,
+    continue_url: &'a str,
+}
+
+/// Shows where a code leads, `continue_url` counts the scan and redirects.
+pub fn preview_page(url: &str, domain: &str, title: Option<&str>, continue_url: &str) -> String {
+    PreviewTemplate {
+        year: 2025,
+        current: "preview",
+        url,
+        domain,
+        title,
+        continue_url,
+    }
+    .render()
+    .unwrap()
+}
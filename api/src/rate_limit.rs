@@ -0,0 +1,230 @@
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use ipnet::IpNet;
+
+use poem::{
+    Endpoint, Error, IntoResponse, Middleware, Request, Response, Result,
+    http::{Method, StatusCode, header},
+};
+use service::RateLimiter;
+use uuid::Uuid;
+
+use crate::config::RateLimits;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Category {
+    Create,
+    Mutate,
+    Image,
+    Redirect,
+}
+
+/// Which limit a request counts against, reading endpoints other than images are unlimited.
+fn category(method: &Method, path: &str) -> Option<Category> {
+    let reading = [Method::GET, Method::HEAD, Method::OPTIONS].contains(method);
+
+    if path.starts_with("/r/") || path == "/api/redirect" {
+        Some(Category::Redirect)
+    } else if reading && path.starts_with("/api/image/") {
+        Some(Category::Image)
+    } else if *method == Method::POST && path == "/api/qr" {
+        Some(Category::Create)
+    } else if !reading && path.starts_with("/api/") {
+        Some(Category::Mutate)
+    } else {
+        None
+    }
+}
+
+/// The client is the remote address, unless it is a trusted proxy. Then `X-Forwarded-For`
+/// is walked from the right, skipping further trusted proxies.
+pub fn client_ip(
+    remote_addr: Option<IpAddr>,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpNet],
+) -> Option<IpAddr> {
+    let trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+
+    let remote_addr = remote_addr?;
+    if !trusted(&remote_addr) {
+        return Some(remote_addr);
+    }
+
+    let mut client = remote_addr;
+    for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+        let Ok(hop) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = hop;
+        if !trusted(&hop) {
+            break;
+        }
+    }
+
+    Some(client)
+}
+
+fn too_many_requests(wait: Duration) -> Response {
+    let seconds = wait.as_secs_f64().ceil().max(1.0) as u64;
+
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(header::RETRY_AFTER, seconds)
+        .body(format!(
+            "Too many requests, please try again in {seconds} seconds."
+        ))
+}
+
+/// Limit of the kind of endpoint a request is for, left in the request so api keys are
+/// charged once they are authenticated, see [`RateLimiting`].
+#[derive(Clone)]
+pub struct KeyRateLimit(RateLimiter);
+
+impl KeyRateLimit {
+    pub fn charge(&self, api_key_id: Uuid) -> Result<()> {
+        self.0
+            .check(&format!("key:{api_key_id}"))
+            .map_err(|wait| Error::from_response(too_many_requests(wait)))
+    }
+}
+
+/// Limits requests per client with a token bucket for each kind of endpoint.
+///
+/// Every request counts against its ip. Requests with an api key also count against the
+/// key once it is authenticated, so a key shared by many clients can't exceed the limit
+/// either. Made up keys only count against the ip, they can't use up the bucket of a real
+/// key sharing their prefix.
+pub struct RateLimiting {
+    create: RateLimiter,
+    mutate: RateLimiter,
+    image: RateLimiter,
+    redirect: RateLimiter,
+    trusted_proxies: Arc<[IpNet]>,
+}
+
+impl RateLimiting {
+    pub fn new(limits: RateLimits, trusted_proxies: &[IpNet]) -> Self {
+        Self {
+            create: RateLimiter::new(limits.create),
+            mutate: RateLimiter::new(limits.mutate),
+            image: RateLimiter::new(limits.image),
+            redirect: RateLimiter::new(limits.redirect),
+            trusted_proxies: trusted_proxies.into(),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for RateLimiting {
+    type Output = RateLimitingEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RateLimitingEndpoint {
+            inner: ep,
+            create: self.create.clone(),
+            mutate: self.mutate.clone(),
+            image: self.image.clone(),
+            redirect: self.redirect.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+        }
+    }
+}
+
+pub struct RateLimitingEndpoint<E> {
+    inner: E,
+    create: RateLimiter,
+    mutate: RateLimiter,
+    image: RateLimiter,
+    redirect: RateLimiter,
+    trusted_proxies: Arc<[IpNet]>,
+}
+
+impl<E> RateLimitingEndpoint<E> {
+    fn client_key(&self, req: &Request) -> String {
+        let ip = client_ip(
+            req.remote_addr().as_socket_addr().map(|x| x.ip()),
+            req.header("X-Forwarded-For"),
+            &self.trusted_proxies,
+        );
+
+        match ip {
+            Some(ip) => format!("ip:{ip}"),
+            None => format!("addr:{}", req.remote_addr()),
+        }
+    }
+}
+
+impl<E: Endpoint> Endpoint for RateLimitingEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        if let Some(category) = category(req.method(), req.uri().path()) {
+            let limiter = match category {
+                Category::Create => &self.create,
+                Category::Mutate => &self.mutate,
+                Category::Image => &self.image,
+                Category::Redirect => &self.redirect,
+            };
+
+            if let Err(wait) = limiter.check(&self.client_key(&req)) {
+                return Ok(too_many_requests(wait));
+            }
+            if category != Category::Redirect {
+                req.extensions_mut().insert(KeyRateLimit(limiter.clone()));
+            }
+        }
+
+        self.inner.call(req).await.map(IntoResponse::into_response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_requests_into_limits() {
+        assert_eq!(category(&Method::POST, "/api/qr"), Some(Category::Create));
+        assert_eq!(
+            category(&Method::PUT, "/api/qr/1234"),
+            Some(Category::Mutate)
+        );
+        assert_eq!(
+            category(&Method::GET, "/api/image/1234"),
+            Some(Category::Image)
+        );
+        assert_eq!(category(&Method::GET, "/r/promo"), Some(Category::Redirect));
+        assert_eq!(
+            category(&Method::POST, "/api/redirect"),
+            Some(Category::Redirect)
+        );
+        assert_eq!(category(&Method::GET, "/api/qr/1234"), None);
+        assert_eq!(category(&Method::GET, "/"), None);
+    }
+
+    #[test]
+    fn only_trusts_forwarded_for_from_trusted_proxies() {
+        let ip = |x: &str| x.parse::<IpAddr>().unwrap();
+        let trusted = ["10.0.0.0/8".parse::<IpNet>().unwrap()];
+
+        assert_eq!(
+            client_ip(Some(ip("203.0.113.9")), Some("198.51.100.1"), &trusted),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            client_ip(
+                Some(ip("10.0.0.2")),
+                Some("198.51.100.1, 203.0.113.7, 10.0.0.1"),
+                &trusted
+            ),
+            Some(ip("203.0.113.7"))
+        );
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.2")), None, &trusted),
+            Some(ip("10.0.0.2"))
+        );
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.2")), Some("garbage"), &trusted),
+            Some(ip("10.0.0.2"))
+        );
+    }
+}
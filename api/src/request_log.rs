@@ -0,0 +1,139 @@
+use std::time::Instant;
+
+use poem::{
+    Endpoint, FromRequest, IntoResponse, Middleware, Request, Response, Result,
+    http::{Method, Uri},
+    web::RealIp,
+};
+use tracing::{Instrument, Level};
+
+const REDACTED: &str = "[redacted]";
+const SECRET_QUERY_KEYS: [&str; 5] = ["code", "pass", "passphrase", "password", "token"];
+
+/// Logs requests like [`poem::middleware::Tracing`], without the secrets that deprecated
+/// endpoints still accept as part of the url.
+#[derive(Default)]
+pub struct RedactedTracing;
+
+impl<E: Endpoint> Middleware<E> for RedactedTracing {
+    type Output = RedactedTracingEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RedactedTracingEndpoint { inner: ep }
+    }
+}
+
+pub struct RedactedTracingEndpoint<E> {
+    inner: E,
+}
+
+impl<E: Endpoint> Endpoint for RedactedTracingEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let remote_addr = RealIp::from_request_without_body(&req)
+            .await
+            .ok()
+            .and_then(|real_ip| real_ip.0)
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| req.remote_addr().to_string());
+
+        let span = tracing::span!(
+            Level::INFO,
+            "request",
+            remote_addr = %remote_addr,
+            version = ?req.version(),
+            method = %req.method(),
+            uri = %redacted_uri(req.method(), req.original_uri()),
+        );
+
+        async move {
+            let now = Instant::now();
+            let res = self.inner.call(req).await;
+            let duration = now.elapsed();
+
+            match res {
+                Ok(resp) => {
+                    let resp = resp.into_response();
+                    tracing::info!(status = %resp.status(), duration = ?duration, "response");
+                    Ok(resp)
+                }
+                Err(err) => {
+                    tracing::info!(
+                        status = %err.status(),
+                        error = %err,
+                        duration = ?duration,
+                        "error"
+                    );
+                    Err(err)
+                }
+            }
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+/// Replaces the passphrase of deprecated delete paths, `/api/qr/:id/:pass` and
+/// `/api/qr/:id/<resource>/:resource_id/:pass`, and the values of secret query parameters.
+fn redacted_uri(method: &Method, uri: &Uri) -> String {
+    let mut path = uri.path().to_string();
+
+    if method == Method::DELETE
+        && let Some(rest) = uri.path().strip_prefix("/api/qr/")
+    {
+        let segments: Vec<&str> = rest.split('/').collect();
+
+        if matches!(segments.len(), 2 | 4) {
+            path = format!(
+                "/api/qr/{}/{REDACTED}",
+                segments[..segments.len() - 1].join("/")
+            );
+        }
+    }
+
+    let Some(query) = uri.query() else {
+        return path;
+    };
+
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SECRET_QUERY_KEYS.contains(&key.to_ascii_lowercase().as_str()) => {
+                format!("{key}={REDACTED}")
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("{path}?{query}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_path_passphrases_and_secret_query_values() {
+        let redact = |method: Method, uri: &str| redacted_uri(&method, &uri.parse().unwrap());
+
+        assert_eq!(
+            redact(Method::DELETE, "/api/qr/1234/secret"),
+            "/api/qr/1234/[redacted]"
+        );
+        assert_eq!(
+            redact(Method::DELETE, "/api/qr/1234/aliases/promo/secret"),
+            "/api/qr/1234/aliases/promo/[redacted]"
+        );
+        assert_eq!(redact(Method::DELETE, "/api/qr/1234"), "/api/qr/1234");
+        assert_eq!(
+            redact(Method::GET, "/api/qr/1234/history"),
+            "/api/qr/1234/history"
+        );
+        assert_eq!(
+            redact(Method::GET, "/api/redirect?id=1234&Password=hunter2"),
+            "/api/redirect?id=1234&Password=[redacted]"
+        );
+    }
+}
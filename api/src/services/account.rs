@@ -0,0 +1,400 @@
+use chrono::{DateTime, Utc};
+use poem::web::{Data, cookie::CookieJar};
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::Path,
+    payload::{Json, PlainText},
+};
+use service::{ConfiguredMailer, QrCodeDatabase, UserDatabase, UserError};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    audit::Audit,
+    config::AppConfig,
+    services::{
+        ApiTags,
+        passphrase::{QrCodePassphraseRequest, throttle_passphrase},
+        qr::QrCodeResponse,
+    },
+    session::{CurrentUser, remove_session_cookie, session_token, set_session_cookie},
+};
+
+#[derive(Object, Debug)]
+struct AccountRequest {
+    #[oai(validator(max_length = 254, pattern = r"^[^@\s]+@[^@\s]+\.[^@\s]+$"))]
+    pub email: String,
+    #[oai(validator(min_length = 8, max_length = 256))]
+    pub password: String,
+}
+
+#[derive(Object, Debug)]
+struct VerifyEmailRequest {
+    #[oai(validator(max_length = 128))]
+    pub token: String,
+}
+
+#[derive(Object, Debug)]
+pub struct AccountResponse {
+    pub id: Uuid,
+    pub email: String,
+    /// Whether the token mailed on registration was redeemed, or the identity provider
+    /// verified the email.
+    pub email_verified: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::user::Model> for AccountResponse {
+    fn from(value: entity::user::Model) -> Self {
+        Self {
+            id: value.id,
+            email: value.email,
+            email_verified: value.email_verified,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum AccountJsonResponse {
+    #[oai(status = 200)]
+    Ok(Json<AccountResponse>),
+
+    #[oai(status = 201)]
+    Created(Json<AccountResponse>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 409)]
+    Taken(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum VerificationResponse {
+    #[oai(status = 202)]
+    Accepted(PlainText<String>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum VerifyEmailResponse {
+    #[oai(status = 200)]
+    Ok(Json<AccountResponse>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum LogoutResponse {
+    #[oai(status = 200)]
+    Ok,
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum OwnedCodesResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<QrCodeResponse>>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(ApiResponse)]
+enum ClaimResponse {
+    #[oai(status = 200)]
+    Ok(Json<QrCodeResponse>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+const NOT_LOGGED_IN: &str = "You need to be logged in.";
+
+async fn start_session(
+    users: &UserDatabase,
+    config: &AppConfig,
+    cookie_jar: &CookieJar,
+    user: &entity::user::Model,
+) -> Result<(), AccountJsonResponse> {
+    let ttl = chrono::Duration::days(config.session_ttl_days);
+
+    match users.create_session(user.id, ttl).await {
+        Ok(token) => {
+            set_session_cookie(cookie_jar, token, ttl);
+            Ok(())
+        }
+        Err(why) => {
+            error!("Failed to create session for user {}, {why}", user.id);
+            Err(AccountJsonResponse::InternalError(PlainText(
+                "Could not log in, because of an internal error.".to_string(),
+            )))
+        }
+    }
+}
+
+/// Mails the verification token, failures are only logged since the account exists anyway
+/// and the mail can be requested again.
+async fn send_verification(
+    users: &UserDatabase,
+    config: &AppConfig,
+    mailer: &ConfiguredMailer,
+    user: entity::user::Model,
+) -> Result<(), UserError> {
+    let user_id = user.id;
+    let server_url = format!("http://{}", config.domain_name);
+
+    users
+        .request_verification(user, &server_url, mailer)
+        .await
+        .inspect_err(|why| error!("Failed to send the verification mail to user {user_id}, {why}"))
+}
+
+pub struct AccountApi;
+
+#[OpenApi]
+impl AccountApi {
+    /// Creates an account and mails a token to verify its email.
+    #[oai(path = "/account/register", method = "post", tag = "ApiTags::Account")]
+    async fn register(
+        &self,
+        Data(users): Data<&UserDatabase>,
+        Data(config): Data<&AppConfig>,
+        Data(mailer): Data<&ConfiguredMailer>,
+        cookie_jar: &CookieJar,
+        Json(request): Json<AccountRequest>,
+    ) -> AccountJsonResponse {
+        let user = match users.register(&request.email, &request.password).await {
+            Ok(user) => user,
+            Err(UserError::Taken) => {
+                return AccountJsonResponse::Taken(PlainText(
+                    "This email is already registered.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to register user, {why}");
+                return AccountJsonResponse::InternalError(PlainText(
+                    "Could not register, because of an internal error.".to_string(),
+                ));
+            }
+        };
+
+        let _ = send_verification(users, config, mailer, user.clone()).await;
+
+        match start_session(users, config, cookie_jar, &user).await {
+            Ok(()) => AccountJsonResponse::Created(Json(user.into())),
+            Err(response) => response,
+        }
+    }
+
+    /// Mails a new verification token to the email of the logged in user.
+    #[oai(
+        path = "/account/verify/resend",
+        method = "post",
+        tag = "ApiTags::Account"
+    )]
+    async fn resend_verification(
+        &self,
+        Data(users): Data<&UserDatabase>,
+        Data(config): Data<&AppConfig>,
+        Data(mailer): Data<&ConfiguredMailer>,
+        current_user: CurrentUser,
+    ) -> VerificationResponse {
+        let Some(user_id) = current_user.0 else {
+            return VerificationResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        let user = match users.get(user_id).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return VerificationResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+            }
+            Err(why) => {
+                error!("Failed to load user {user_id}, {why}");
+                return VerificationResponse::InternalError(PlainText(
+                    "Could not send the verification mail, because of an internal error."
+                        .to_string(),
+                ));
+            }
+        };
+
+        match send_verification(users, config, mailer, user).await {
+            Ok(()) => VerificationResponse::Accepted(PlainText(
+                "A verification token was sent unless the email is verified already.".to_string(),
+            )),
+            Err(_) => VerificationResponse::InternalError(PlainText(
+                "Could not send the verification mail, because of an internal error.".to_string(),
+            )),
+        }
+    }
+
+    #[oai(path = "/account/verify", method = "post", tag = "ApiTags::Account")]
+    async fn verify_email(
+        &self,
+        Data(users): Data<&UserDatabase>,
+        Json(request): Json<VerifyEmailRequest>,
+    ) -> VerifyEmailResponse {
+        match users.verify_email(&request.token).await {
+            Ok(Some(user)) => VerifyEmailResponse::Ok(Json(user.into())),
+            Ok(None) => VerifyEmailResponse::NotFound(PlainText(
+                "The verification token is invalid or expired.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to verify an email, {why}");
+                VerifyEmailResponse::InternalError(PlainText(
+                    "Could not verify the email, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[oai(path = "/account/login", method = "post", tag = "ApiTags::Account")]
+    async fn login(
+        &self,
+        Data(users): Data<&UserDatabase>,
+        Data(config): Data<&AppConfig>,
+        cookie_jar: &CookieJar,
+        Json(request): Json<AccountRequest>,
+    ) -> AccountJsonResponse {
+        let user = match users.login(&request.email, &request.password).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return AccountJsonResponse::Unauthorized(PlainText(
+                    "The email or password is wrong.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to log in user, {why}");
+                return AccountJsonResponse::InternalError(PlainText(
+                    "Could not log in, because of an internal error.".to_string(),
+                ));
+            }
+        };
+
+        match start_session(users, config, cookie_jar, &user).await {
+            Ok(()) => AccountJsonResponse::Ok(Json(user.into())),
+            Err(response) => response,
+        }
+    }
+
+    #[oai(path = "/account/logout", method = "post", tag = "ApiTags::Account")]
+    async fn logout(
+        &self,
+        Data(users): Data<&UserDatabase>,
+        cookie_jar: &CookieJar,
+    ) -> LogoutResponse {
+        if let Some(token) = session_token(cookie_jar)
+            && let Err(why) = users.end_session(&token).await
+        {
+            error!("Failed to end session, {why}");
+            return LogoutResponse::InternalError(PlainText(
+                "Could not log out, because of an internal error.".to_string(),
+            ));
+        }
+
+        remove_session_cookie(cookie_jar);
+        LogoutResponse::Ok
+    }
+
+    #[oai(path = "/account/me", method = "get", tag = "ApiTags::Account")]
+    async fn me(
+        &self,
+        Data(users): Data<&UserDatabase>,
+        current_user: CurrentUser,
+    ) -> AccountJsonResponse {
+        let Some(user_id) = current_user.0 else {
+            return AccountJsonResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match users.get(user_id).await {
+            Ok(Some(user)) => AccountJsonResponse::Ok(Json(user.into())),
+            Ok(None) => AccountJsonResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string())),
+            Err(why) => {
+                error!("Failed to load user {user_id}, {why}");
+                AccountJsonResponse::InternalError(PlainText(
+                    "Could not load the account, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[oai(path = "/account/qr", method = "get", tag = "ApiTags::Account")]
+    async fn owned_codes(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        current_user: CurrentUser,
+    ) -> OwnedCodesResponse {
+        let Some(user_id) = current_user.0 else {
+            return OwnedCodesResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match database.list_owned(user_id).await {
+            Ok(codes) => {
+                OwnedCodesResponse::Ok(Json(codes.into_iter().map(QrCodeResponse::from).collect()))
+            }
+            Err(why) => {
+                error!("Failed to list qr codes of user {user_id}, {why}");
+                OwnedCodesResponse::InternalError(PlainText(
+                    "Could not retrieve your qr codes, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[oai(
+        path = "/account/qr/:id/claim",
+        method = "post",
+        tag = "ApiTags::Account",
+        transform = "throttle_passphrase"
+    )]
+    async fn claim(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        current_user: CurrentUser,
+        Path(id): Path<Uuid>,
+        Audit(audit): Audit,
+        Json(request): Json<QrCodePassphraseRequest>,
+    ) -> ClaimResponse {
+        let Some(user_id) = current_user.0 else {
+            return ClaimResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match database.claim(id, request.password, user_id, &audit).await {
+            Ok(Some(model)) => ClaimResponse::Ok(Json(model.into())),
+            Ok(None) => ClaimResponse::NotFound(PlainText(
+                "No qr code could be found for this id.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to claim qr code {id}, {why}");
+                ClaimResponse::InternalError(PlainText(
+                    "Could not claim the qr code, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use chrono::{DateTime, Utc};
+use poem::web::{Data, cookie::CookieJar};
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::{Header, Path},
+    payload::{Json, PlainText},
+};
+use service::{AliasDatabase, AliasError, Credential, QrCodeDatabase};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    api_key::{ApiKeyAuth, Read, Write},
+    services::{
+        ApiTags,
+        passphrase::{
+            MISSING_PASSPHRASE, PassphraseBody, credential, deprecated_path_passphrase, passphrase,
+            throttle_passphrase,
+        },
+        readable::find_readable,
+    },
+    session::CurrentUser,
+};
+
+#[derive(Object, Debug)]
+struct AliasRequest {
+    #[oai(validator(pattern = r"^[A-Za-z0-9_-]{3,64}$"))]
+    pub alias: String,
+    pub password: Option<String>,
+}
+
+#[derive(Object, Debug)]
+pub struct AliasResponse {
+    pub alias: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::qr_code_alias::Model> for AliasResponse {
+    fn from(value: entity::qr_code_alias::Model) -> Self {
+        Self {
+            alias: value.alias,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum AliasListResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<AliasResponse>>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum AliasCreateResponse {
+    #[oai(status = 201)]
+    Created(Json<AliasResponse>),
+
+    #[oai(status = 400)]
+    Rejected(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    Taken(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum AliasDeleteResponse {
+    #[oai(status = 200)]
+    Ok,
+
+    #[oai(status = 400)]
+    MissingPassphrase(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+async fn delete_alias(
+    aliases: &AliasDatabase,
+    id: Uuid,
+    alias: String,
+    credential: Credential,
+) -> AliasDeleteResponse {
+    match aliases.delete(id, credential, alias).await {
+        Ok(Some(_)) => AliasDeleteResponse::Ok,
+        Ok(None) => AliasDeleteResponse::NotFound(PlainText(
+            "No alias could be found with this name.".to_string(),
+        )),
+        Err(why) => {
+            error!("Failed to delete alias of qr code {id}, {why}");
+            AliasDeleteResponse::InternalError(PlainText(
+                "Could not delete the alias, because of an internal error.".to_string(),
+            ))
+        }
+    }
+}
+
+pub struct AliasApi;
+
+#[OpenApi]
+impl AliasApi {
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/aliases",
+        method = "get",
+        tag = "ApiTags::Alias",
+        transform = "throttle_passphrase"
+    )]
+    async fn list(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(aliases): Data<&AliasDatabase>,
+        cookie_jar: &CookieJar,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
+    ) -> AliasListResponse {
+        let credential = credential(passphrase_header.0, auth.or(current_user));
+        match find_readable(database, cookie_jar, id, credential).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return AliasListResponse::NotFound(PlainText(
+                    "No qr code could be found for this id.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to look up qr code {id}, {why}");
+                return AliasListResponse::InternalError(PlainText(
+                    "Could not retrieve the aliases, because of an internal error.".to_string(),
+                ));
+            }
+        }
+
+        match aliases.list(id).await {
+            Ok(aliases) => {
+                AliasListResponse::Ok(Json(aliases.into_iter().map(AliasResponse::from).collect()))
+            }
+            Err(why) => {
+                error!("Failed to list aliases of qr code {id}, {why}");
+                AliasListResponse::InternalError(PlainText(
+                    "Could not retrieve the aliases, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[oai(
+        path = "/qr/:id/aliases",
+        method = "post",
+        tag = "ApiTags::Alias",
+        transform = "throttle_passphrase"
+    )]
+    async fn create(
+        &self,
+        Data(aliases): Data<&AliasDatabase>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<AliasRequest>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> AliasCreateResponse {
+        let Some(credential) = credential(
+            passphrase(passphrase_header.0, request.password),
+            auth.or(current_user),
+        ) else {
+            return AliasCreateResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+
+        match aliases.create(id, credential, request.alias).await {
+            Ok(Some(alias)) => AliasCreateResponse::Created(Json(alias.into())),
+            Ok(None) => AliasCreateResponse::NotFound(PlainText(
+                "No qr code could be found for this id.".to_string(),
+            )),
+            Err(AliasError::Reserved) => AliasCreateResponse::Rejected(PlainText(
+                "This alias is reserved and cannot be used.".to_string(),
+            )),
+            Err(AliasError::Taken) => {
+                AliasCreateResponse::Taken(PlainText("This alias is already in use.".to_string()))
+            }
+            Err(why) => {
+                error!("Failed to create alias for qr code {id}, {why}");
+                AliasCreateResponse::InternalError(PlainText(
+                    "Could not create the alias, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/aliases/:alias",
+        method = "delete",
+        tag = "ApiTags::Alias",
+        transform = "throttle_passphrase"
+    )]
+    async fn delete(
+        &self,
+        Data(aliases): Data<&AliasDatabase>,
+        Path(id): Path<Uuid>,
+        Path(alias): Path<String>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> AliasDeleteResponse {
+        match credential(passphrase(passphrase_header.0, body), auth.or(current_user)) {
+            Some(credential) => delete_alias(aliases, id, alias, credential).await,
+            None => {
+                AliasDeleteResponse::MissingPassphrase(PlainText(MISSING_PASSPHRASE.to_string()))
+            }
+        }
+    }
+
+    #[oai(
+        path = "/qr/:id/aliases/:alias/:pass",
+        method = "delete",
+        tag = "ApiTags::Alias",
+        deprecated,
+        transform = "deprecated_path_passphrase"
+    )]
+    async fn delete_with_path_passphrase(
+        &self,
+        Data(aliases): Data<&AliasDatabase>,
+        Path(id): Path<Uuid>,
+        Path(alias): Path<String>,
+        Path(password): Path<String>,
+    ) -> AliasDeleteResponse {
+        delete_alias(aliases, id, alias, Credential::Passphrase(password)).await
+    }
+}
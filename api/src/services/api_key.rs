@@ -0,0 +1,204 @@
+use chrono::{DateTime, Utc};
+use poem::web::Data;
+use poem_openapi::{
+    ApiResponse, Enum, Object, OpenApi,
+    param::Path,
+    payload::{Json, PlainText},
+};
+use service::{ApiKeyDatabase, ApiKeyScope, api_key_scopes};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{services::ApiTags, session::CurrentUser};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Write,
+    Stats,
+}
+
+impl From<Scope> for ApiKeyScope {
+    fn from(value: Scope) -> Self {
+        match value {
+            Scope::Read => Self::Read,
+            Scope::Write => Self::Write,
+            Scope::Stats => Self::Stats,
+        }
+    }
+}
+
+impl From<ApiKeyScope> for Scope {
+    fn from(value: ApiKeyScope) -> Self {
+        match value {
+            ApiKeyScope::Read => Self::Read,
+            ApiKeyScope::Write => Self::Write,
+            ApiKeyScope::Stats => Self::Stats,
+        }
+    }
+}
+
+#[derive(Object, Debug)]
+struct ApiKeyRequest {
+    #[oai(validator(min_length = 1, max_length = 64))]
+    pub name: String,
+    #[oai(validator(min_items = 1))]
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Object, Debug)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    /// Only returned once when the key is created.
+    pub token: Option<String>,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::api_key::Model> for ApiKeyResponse {
+    fn from(value: entity::api_key::Model) -> Self {
+        Self {
+            scopes: api_key_scopes(&value).into_iter().map(Into::into).collect(),
+            id: value.id,
+            name: value.name,
+            prefix: value.prefix,
+            token: None,
+            expires_at: value.expires_at,
+            last_used_at: value.last_used_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum ApiKeyCreateResponse {
+    #[oai(status = 201)]
+    Created(Json<ApiKeyResponse>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum ApiKeyListResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<ApiKeyResponse>>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum ApiKeyDeleteResponse {
+    #[oai(status = 200)]
+    Ok,
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+const NOT_LOGGED_IN: &str = "You need to be logged in.";
+
+pub struct ApiKeyApi;
+
+#[OpenApi]
+impl ApiKeyApi {
+    #[oai(path = "/account/keys", method = "post", tag = "ApiTags::ApiKey")]
+    async fn create(
+        &self,
+        Data(api_keys): Data<&ApiKeyDatabase>,
+        current_user: CurrentUser,
+        Json(request): Json<ApiKeyRequest>,
+    ) -> ApiKeyCreateResponse {
+        let Some(user_id) = current_user.0 else {
+            return ApiKeyCreateResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+        let scopes: Vec<ApiKeyScope> = request.scopes.into_iter().map(Into::into).collect();
+
+        match api_keys
+            .create(user_id, request.name, &scopes, request.expires_at)
+            .await
+        {
+            Ok((api_key, token)) => ApiKeyCreateResponse::Created(Json(ApiKeyResponse {
+                token: Some(token),
+                ..api_key.into()
+            })),
+            Err(why) => {
+                error!("Failed to create api key for user {user_id}, {why}");
+                ApiKeyCreateResponse::InternalError(PlainText(
+                    "Could not create the api key, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[oai(path = "/account/keys", method = "get", tag = "ApiTags::ApiKey")]
+    async fn list(
+        &self,
+        Data(api_keys): Data<&ApiKeyDatabase>,
+        current_user: CurrentUser,
+    ) -> ApiKeyListResponse {
+        let Some(user_id) = current_user.0 else {
+            return ApiKeyListResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match api_keys.list(user_id).await {
+            Ok(keys) => {
+                ApiKeyListResponse::Ok(Json(keys.into_iter().map(ApiKeyResponse::from).collect()))
+            }
+            Err(why) => {
+                error!("Failed to list api keys of user {user_id}, {why}");
+                ApiKeyListResponse::InternalError(PlainText(
+                    "Could not retrieve the api keys, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[oai(
+        path = "/account/keys/:key_id",
+        method = "delete",
+        tag = "ApiTags::ApiKey"
+    )]
+    async fn delete(
+        &self,
+        Data(api_keys): Data<&ApiKeyDatabase>,
+        current_user: CurrentUser,
+        Path(key_id): Path<Uuid>,
+    ) -> ApiKeyDeleteResponse {
+        let Some(user_id) = current_user.0 else {
+            return ApiKeyDeleteResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match api_keys.revoke(user_id, key_id).await {
+            Ok(Some(_)) => ApiKeyDeleteResponse::Ok,
+            Ok(None) => ApiKeyDeleteResponse::NotFound(PlainText(
+                "No api key could be found with this id.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to revoke api key {key_id}, {why}");
+                ApiKeyDeleteResponse::InternalError(PlainText(
+                    "Could not revoke the api key, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use chrono::{DateTime, Utc};
+use entity::sea_orm_active_enums::AuditAction;
+use poem::web::Data;
+use poem_openapi::{
+    ApiResponse, Enum, Object, OpenApi,
+    param::Query,
+    payload::{Json, PlainText},
+};
+use service::{AuditDatabase, AuditFilter, UserDatabase};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    api_key::{ApiKeyAuth, Read},
+    config::AppConfig,
+    services::ApiTags,
+    session::CurrentUser,
+};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+#[oai(rename_all = "snake_case")]
+pub enum Action {
+    Create,
+    Update,
+    Pause,
+    Resume,
+    Rollback,
+    Claim,
+    RotatePassphrase,
+    RecoverPassphrase,
+    Delete,
+    Restore,
+}
+
+impl From<Action> for AuditAction {
+    fn from(value: Action) -> Self {
+        match value {
+            Action::Create => Self::Create,
+            Action::Update => Self::Update,
+            Action::Pause => Self::Pause,
+            Action::Resume => Self::Resume,
+            Action::Rollback => Self::Rollback,
+            Action::Claim => Self::Claim,
+            Action::RotatePassphrase => Self::RotatePassphrase,
+            Action::RecoverPassphrase => Self::RecoverPassphrase,
+            Action::Delete => Self::Delete,
+            Action::Restore => Self::Restore,
+        }
+    }
+}
+
+impl From<AuditAction> for Action {
+    fn from(value: AuditAction) -> Self {
+        match value {
+            AuditAction::Create => Self::Create,
+            AuditAction::Update => Self::Update,
+            AuditAction::Pause => Self::Pause,
+            AuditAction::Resume => Self::Resume,
+            AuditAction::Rollback => Self::Rollback,
+            AuditAction::Claim => Self::Claim,
+            AuditAction::RotatePassphrase => Self::RotatePassphrase,
+            AuditAction::RecoverPassphrase => Self::RecoverPassphrase,
+            AuditAction::Delete => Self::Delete,
+            AuditAction::Restore => Self::Restore,
+        }
+    }
+}
+
+#[derive(Object, Debug)]
+pub struct AuditEntryResponse {
+    pub id: Uuid,
+    pub qr_code_id: Uuid,
+    pub action: Action,
+    /// `user:<id>`, `passphrase`, `recovery_token` or `anonymous`.
+    pub actor: String,
+    /// Changed fields before the action, secrets are only marked as changed.
+    pub old_values: Option<serde_json::Value>,
+    /// Changed fields after the action, all fields for created codes.
+    pub new_values: Option<serde_json::Value>,
+    pub client_ip_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::audit_log::Model> for AuditEntryResponse {
+    fn from(value: entity::audit_log::Model) -> Self {
+        let parse = |values: Option<String>| values.and_then(|x| serde_json::from_str(&x).ok());
+
+        Self {
+            id: value.id,
+            qr_code_id: value.qr_code_id,
+            action: value.action.into(),
+            actor: value.actor,
+            old_values: parse(value.old_values),
+            new_values: parse(value.new_values),
+            client_ip_hash: value.client_ip_hash,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Object, Debug)]
+pub struct AuditPageResponse {
+    pub entries: Vec<AuditEntryResponse>,
+    pub page: u64,
+    pub page_size: u64,
+    /// Number of matching entries on all pages.
+    pub total: u64,
+}
+
+#[derive(ApiResponse)]
+enum AuditListResponse {
+    #[oai(status = 200)]
+    Ok(Json<AuditPageResponse>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 403)]
+    Forbidden(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+pub struct AuditApi;
+
+#[OpenApi]
+impl AuditApi {
+    /// Lists who changed which qr code when, newest first. Only users whose verified email is
+    /// listed in `ADMIN_EMAILS` may read it.
+    #[allow(clippy::too_many_arguments)]
+    #[oai(path = "/admin/audit", method = "get", tag = "ApiTags::Audit")]
+    async fn list(
+        &self,
+        Data(audit_log): Data<&AuditDatabase>,
+        Data(users): Data<&UserDatabase>,
+        Data(config): Data<&AppConfig>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
+        Query(qr_code_id): Query<Option<Uuid>>,
+        Query(actor): Query<Option<String>>,
+        Query(action): Query<Option<Action>>,
+        /// Only entries at or after this time.
+        Query(since): Query<Option<DateTime<Utc>>>,
+        /// Only entries before this time.
+        Query(until): Query<Option<DateTime<Utc>>>,
+        /// Starts at 1.
+        #[oai(validator(minimum(value = "1")))]
+        Query(page): Query<Option<u64>>,
+        /// Defaults to 50, at most 200.
+        #[oai(validator(minimum(value = "1"), maximum(value = "200")))]
+        Query(page_size): Query<Option<u64>>,
+    ) -> AuditListResponse {
+        let Some(user_id) = auth.or(current_user).0 else {
+            return AuditListResponse::Unauthorized(PlainText(
+                "You need to be logged in.".to_string(),
+            ));
+        };
+
+        match users.get(user_id).await {
+            Ok(Some(user)) if user.email_verified && config.admin_emails.contains(&user.email) => {}
+            Ok(_) => {
+                return AuditListResponse::Forbidden(PlainText(
+                    "Only administrators may read the audit log.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to look up user {user_id}, {why}");
+                return AuditListResponse::InternalError(PlainText(
+                    "Could not retrieve the audit log, because of an internal error.".to_string(),
+                ));
+            }
+        }
+
+        let filter = AuditFilter {
+            qr_code_id,
+            actor,
+            action: action.map(Into::into),
+            since,
+            until,
+        };
+        let page = page.unwrap_or(1);
+        let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+
+        match audit_log.list(filter, page - 1, page_size).await {
+            Ok((entries, total)) => AuditListResponse::Ok(Json(AuditPageResponse {
+                entries: entries.into_iter().map(AuditEntryResponse::from).collect(),
+                page,
+                page_size,
+                total,
+            })),
+            Err(why) => {
+                error!("Failed to list the audit log, {why}");
+                AuditListResponse::InternalError(PlainText(
+                    "Could not retrieve the audit log, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+}
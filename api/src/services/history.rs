@@ -0,0 +1,181 @@
+use chrono::{DateTime, Utc};
+use poem::web::Data;
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::{Header, Path},
+    payload::{Json, PlainText},
+};
+use service::{Action, LinkPolicy, QrCodeDatabase, RevisionDatabase, RevisionError};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    api_key::{ApiKeyAuth, Read, Write},
+    audit::Audit,
+    services::{
+        ApiTags,
+        passphrase::{
+            MISSING_PASSPHRASE, PassphraseBody, credential, passphrase, throttle_passphrase,
+        },
+        qr::QrCodeResponse,
+    },
+    session::CurrentUser,
+};
+
+#[derive(Object, Debug)]
+pub struct RevisionResponse {
+    pub id: Uuid,
+    pub link: String,
+    /// `user:<id>` or `passphrase`, `None` for older revisions.
+    pub actor: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::qr_code_revision::Model> for RevisionResponse {
+    fn from(value: entity::qr_code_revision::Model) -> Self {
+        Self {
+            id: value.id,
+            link: value.link,
+            actor: value.actor,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum HistoryListResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<RevisionResponse>>),
+
+    #[oai(status = 400)]
+    MissingPassphrase(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(ApiResponse)]
+enum RollbackResponse {
+    #[oai(status = 200)]
+    Ok(Json<QrCodeResponse>),
+
+    #[oai(status = 400)]
+    Rejected(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+pub struct HistoryApi;
+
+#[OpenApi]
+impl HistoryApi {
+    /// Lists the previous links, only to those who may edit the code.
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/history",
+        method = "get",
+        tag = "ApiTags::History",
+        transform = "throttle_passphrase"
+    )]
+    async fn list(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(revisions): Data<&RevisionDatabase>,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
+    ) -> HistoryListResponse {
+        let Some(credential) = credential(passphrase_header.0, auth.or(current_user)) else {
+            return HistoryListResponse::MissingPassphrase(PlainText(
+                MISSING_PASSPHRASE.to_string(),
+            ));
+        };
+
+        match database.get_authorized(id, &credential, Action::Edit).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return HistoryListResponse::NotFound(PlainText(
+                    "No qr code could be found for this id.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to look up qr code {id}, {why}");
+                return HistoryListResponse::InternalError(PlainText(
+                    "Could not retrieve the history, because of an internal error.".to_string(),
+                ));
+            }
+        }
+
+        match revisions.list(id).await {
+            Ok(revisions) => HistoryListResponse::Ok(Json(
+                revisions.into_iter().map(RevisionResponse::from).collect(),
+            )),
+            Err(why) => {
+                error!("Failed to list the history of qr code {id}, {why}");
+                HistoryListResponse::InternalError(PlainText(
+                    "Could not retrieve the history, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/history/:revision_id/rollback",
+        method = "post",
+        tag = "ApiTags::History",
+        transform = "throttle_passphrase"
+    )]
+    async fn rollback(
+        &self,
+        Data(revisions): Data<&RevisionDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
+        Path(id): Path<Uuid>,
+        Path(revision_id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
+    ) -> RollbackResponse {
+        let Some(credential) =
+            credential(passphrase(passphrase_header.0, body), auth.or(current_user))
+        else {
+            return RollbackResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+
+        match revisions
+            .rollback(id, revision_id, credential, link_policy, &audit)
+            .await
+        {
+            Ok(Some(qr_code)) => RollbackResponse::Ok(Json(qr_code.into())),
+            Ok(None) => RollbackResponse::NotFound(PlainText(
+                "No revision could be found for this id.".to_string(),
+            )),
+            Err(RevisionError::Rejected(why)) => RollbackResponse::Rejected(PlainText(format!(
+                "The link of the revision was rejected, {why}."
+            ))),
+            Err(RevisionError::Immutable) => RollbackResponse::Conflict(PlainText(
+                "Immutable qr codes can't be edited.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to roll back qr code {id} to revision {revision_id}, {why}");
+                RollbackResponse::InternalError(PlainText(
+                    "Could not roll back the qr code, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+}
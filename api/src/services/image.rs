@@ -8,7 +8,11 @@ use serde::Deserialize;
 use service::{QrCodeGenerator, QrImageType};
 use uuid::Uuid;
 
-use crate::services::ApiTags;
+use crate::{
+    api_key::{ApiKeyAuth, Read},
+    services::ApiTags,
+    session::CurrentUser,
+};
 
 #[derive(ApiResponse)]
 enum ImageResponse {
@@ -39,9 +43,9 @@ enum ImageType {
     Svg,
 }
 
-impl Into<QrImageType> for ImageType {
-    fn into(self) -> QrImageType {
-        match self {
+impl From<ImageType> for QrImageType {
+    fn from(value: ImageType) -> Self {
+        match value {
             ImageType::Png => QrImageType::Png,
             ImageType::Jpg => QrImageType::Jpg,
             ImageType::Svg => QrImageType::Svg,
@@ -59,8 +63,11 @@ impl ImageApi {
         Data(generator): Data<&QrCodeGenerator>,
         Path(id): Path<Uuid>,
         Query(img_type): Query<ImageType>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
     ) -> ImageResponse {
-        match generator.generate(id, img_type.into()).await {
+        let viewer = auth.or(current_user).0;
+        match generator.generate(id, img_type.into(), viewer).await {
             Ok(Some(data)) => match img_type {
                 ImageType::Png => ImageResponse::Png(Binary(data)),
                 ImageType::Jpg => ImageResponse::Jpg(Binary(data)),
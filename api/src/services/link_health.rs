@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use poem::web::{Data, cookie::CookieJar};
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::{Header, Path},
+    payload::{Json, PlainText},
+};
+use service::{LinkHealthDatabase, QrCodeDatabase};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    api_key::{ApiKeyAuth, Read},
+    services::{
+        ApiTags,
+        passphrase::{credential, throttle_passphrase},
+        readable::find_readable,
+    },
+    session::CurrentUser,
+};
+
+#[derive(Object, Debug)]
+pub struct LinkCheckResponse {
+    pub qr_code_id: Uuid,
+    /// Hidden for codes with an access password, unless the caller manages the code or
+    /// unlocked it.
+    pub link: Option<String>,
+    pub healthy: bool,
+    pub status_code: Option<i32>,
+    pub latency_ms: i32,
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl From<entity::link_check::Model> for LinkCheckResponse {
+    fn from(value: entity::link_check::Model) -> Self {
+        Self {
+            qr_code_id: value.qr_code_id,
+            link: Some(value.link),
+            healthy: value.healthy,
+            status_code: value.status_code,
+            latency_ms: value.latency_ms,
+            error: value.error,
+            checked_at: value.checked_at,
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum LinkCheckListResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<LinkCheckResponse>>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum LinkCheckJsonResponse {
+    #[oai(status = 200)]
+    Ok(Json<LinkCheckResponse>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+const NOT_LOGGED_IN: &str = "You need to be logged in.";
+
+pub struct LinkHealthApi;
+
+#[OpenApi]
+impl LinkHealthApi {
+    /// Lists the broken links of the codes the caller owns or can see in their
+    /// organizations.
+    #[oai(path = "/links/broken", method = "get", tag = "ApiTags::LinkHealth")]
+    async fn broken(
+        &self,
+        Data(link_health): Data<&LinkHealthDatabase>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
+    ) -> LinkCheckListResponse {
+        let Some(user_id) = auth.or(current_user).0 else {
+            return LinkCheckListResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match link_health.broken(user_id).await {
+            Ok(checks) => LinkCheckListResponse::Ok(Json(
+                checks.into_iter().map(|(check, _)| check.into()).collect(),
+            )),
+            Err(why) => {
+                error!("Failed to list broken links of user {user_id}, {why}");
+                LinkCheckListResponse::InternalError(PlainText(
+                    "Could not retrieve the broken links, because of an internal error."
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/link-health",
+        method = "get",
+        tag = "ApiTags::LinkHealth",
+        transform = "throttle_passphrase"
+    )]
+    async fn get(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(link_health): Data<&LinkHealthDatabase>,
+        cookie_jar: &CookieJar,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
+    ) -> LinkCheckJsonResponse {
+        let credential = credential(passphrase_header.0, auth.or(current_user));
+        let readable = match find_readable(database, cookie_jar, id, credential).await {
+            Ok(Some(readable)) => readable,
+            Ok(None) => {
+                return LinkCheckJsonResponse::NotFound(PlainText(
+                    "No qr code could be found for this id.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to look up qr code {id}, {why}");
+                return LinkCheckJsonResponse::InternalError(PlainText(
+                    "Could not retrieve the link health, because of an internal error.".to_string(),
+                ));
+            }
+        };
+
+        match link_health.get(id).await {
+            Ok(Some(check)) => LinkCheckJsonResponse::Ok(Json(LinkCheckResponse {
+                link: readable.link(check.link.clone()),
+                ..check.into()
+            })),
+            Ok(None) => LinkCheckJsonResponse::NotFound(PlainText(
+                "The link of this qr code has not been checked yet.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to retrieve the link health of qr code {id}, {why}");
+                LinkCheckJsonResponse::InternalError(PlainText(
+                    "Could not retrieve the link health, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+}
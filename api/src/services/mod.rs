@@ -1,16 +1,44 @@
 use poem_openapi::Tags;
 
+mod account;
+mod alias;
+mod api_key;
+mod audit;
 mod health;
+mod history;
+mod image;
+mod link_health;
+mod organization;
+mod passphrase;
 mod qr;
+mod readable;
+mod recovery;
 mod redirect;
+mod schedule;
+mod sso;
+mod stats;
+mod targeting;
+mod variant;
 mod version;
-mod image;
 
+pub use account::AccountApi;
+pub use alias::AliasApi;
+pub use api_key::ApiKeyApi;
+pub use audit::AuditApi;
 pub use health::HealthApi;
+pub use history::HistoryApi;
+pub use image::ImageApi;
+pub use link_health::LinkHealthApi;
+pub use organization::OrganizationApi;
 pub use qr::QrCodeApi;
-pub use redirect::RedirectApi;
+pub use recovery::RecoveryApi;
+pub use redirect::{RedirectApi, short_redirect};
+pub use schedule::ScheduleApi;
+pub use sso::SsoApi;
+pub use stats::StatsApi;
+pub use targeting::TargetingApi;
+pub use variant::VariantApi;
 pub use version::VersionApi;
-pub use image::ImageApi;
 
 #[derive(Tags)]
 pub enum ApiTags {
@@ -19,6 +47,16 @@ pub enum ApiTags {
     Redirect,
     Version,
     Image,
+    Schedule,
+    Targeting,
+    Variant,
+    Stats,
+    Alias,
+    History,
+    LinkHealth,
+    Recovery,
+    Account,
+    ApiKey,
+    Organization,
+    Audit,
 }
-
-
@@ -0,0 +1,345 @@
+use chrono::{DateTime, Utc};
+use entity::sea_orm_active_enums::OrganizationRole;
+use poem::web::Data;
+use poem_openapi::{
+    ApiResponse, Enum, Object, OpenApi,
+    param::Path,
+    payload::{Json, PlainText},
+    types::ToJSON,
+};
+use service::{OrganizationDatabase, OrganizationError, OrganizationMember};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    services::{ApiTags, qr::QrCodeResponse},
+    session::CurrentUser,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+pub enum Role {
+    /// Manages members and may delete, restore or hand over codes.
+    Owner,
+    /// Creates and changes codes.
+    Editor,
+    /// Only reads codes, their images and stats.
+    Viewer,
+}
+
+impl From<Role> for OrganizationRole {
+    fn from(value: Role) -> Self {
+        match value {
+            Role::Owner => Self::Owner,
+            Role::Editor => Self::Editor,
+            Role::Viewer => Self::Viewer,
+        }
+    }
+}
+
+impl From<OrganizationRole> for Role {
+    fn from(value: OrganizationRole) -> Self {
+        match value {
+            OrganizationRole::Owner => Self::Owner,
+            OrganizationRole::Editor => Self::Editor,
+            OrganizationRole::Viewer => Self::Viewer,
+        }
+    }
+}
+
+#[derive(Object, Debug)]
+struct OrganizationRequest {
+    #[oai(validator(min_length = 1, max_length = 64))]
+    pub name: String,
+}
+
+#[derive(Object, Debug)]
+pub struct OrganizationResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// Role of the logged in user.
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Object, Debug)]
+struct MemberRequest {
+    /// Email of a registered user.
+    #[oai(validator(max_length = 254))]
+    pub email: String,
+    pub role: Role,
+}
+
+#[derive(Object, Debug)]
+pub struct MemberResponse {
+    pub user_id: Uuid,
+    pub email: String,
+    pub role: Role,
+}
+
+impl From<OrganizationMember> for MemberResponse {
+    fn from(value: OrganizationMember) -> Self {
+        Self {
+            user_id: value.user_id,
+            email: value.email,
+            role: value.role.into(),
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum OrganizationCreateResponse {
+    #[oai(status = 201)]
+    Created(Json<OrganizationResponse>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum OrganizationJsonResponse<T: ToJSON + Send + Sync + 'static> {
+    #[oai(status = 200)]
+    Ok(Json<T>),
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 403)]
+    Forbidden(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    LastOwner(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum OrganizationDeleteResponse {
+    #[oai(status = 200)]
+    Ok,
+
+    #[oai(status = 401)]
+    Unauthorized(PlainText<String>),
+
+    #[oai(status = 403)]
+    Forbidden(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    LastOwner(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+const NOT_LOGGED_IN: &str = "You need to be logged in.";
+const NO_ORGANIZATION: &str = "No organization could be found for this id.";
+const NO_USER: &str = "No user is registered with this email.";
+const FORBIDDEN: &str = "Your role in this organization does not allow this.";
+const LAST_OWNER: &str = "An organization needs at least one owner.";
+const INTERNAL_ERROR: &str = "Could not access the organization, because of an internal error.";
+
+impl<T: ToJSON + Send + Sync + 'static> From<OrganizationError> for OrganizationJsonResponse<T> {
+    fn from(value: OrganizationError) -> Self {
+        match value {
+            OrganizationError::NotFound => Self::NotFound(PlainText(NO_ORGANIZATION.to_string())),
+            OrganizationError::UnknownUser => Self::NotFound(PlainText(NO_USER.to_string())),
+            OrganizationError::Forbidden => Self::Forbidden(PlainText(FORBIDDEN.to_string())),
+            OrganizationError::LastOwner => Self::LastOwner(PlainText(LAST_OWNER.to_string())),
+            OrganizationError::Database(why) => {
+                error!("Failed to access organization, {why}");
+                Self::InternalError(PlainText(INTERNAL_ERROR.to_string()))
+            }
+        }
+    }
+}
+
+impl From<OrganizationError> for OrganizationDeleteResponse {
+    fn from(value: OrganizationError) -> Self {
+        match value {
+            OrganizationError::NotFound | OrganizationError::UnknownUser => {
+                Self::NotFound(PlainText(NO_ORGANIZATION.to_string()))
+            }
+            OrganizationError::Forbidden => Self::Forbidden(PlainText(FORBIDDEN.to_string())),
+            OrganizationError::LastOwner => Self::LastOwner(PlainText(LAST_OWNER.to_string())),
+            OrganizationError::Database(why) => {
+                error!("Failed to remove organization member, {why}");
+                Self::InternalError(PlainText(INTERNAL_ERROR.to_string()))
+            }
+        }
+    }
+}
+
+pub struct OrganizationApi;
+
+#[OpenApi]
+impl OrganizationApi {
+    #[oai(
+        path = "/organizations",
+        method = "post",
+        tag = "ApiTags::Organization"
+    )]
+    async fn create(
+        &self,
+        Data(organizations): Data<&OrganizationDatabase>,
+        current_user: CurrentUser,
+        Json(request): Json<OrganizationRequest>,
+    ) -> OrganizationCreateResponse {
+        let Some(user_id) = current_user.0 else {
+            return OrganizationCreateResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match organizations.create(user_id, request.name).await {
+            Ok(organization) => OrganizationCreateResponse::Created(Json(OrganizationResponse {
+                id: organization.id,
+                name: organization.name,
+                role: Role::Owner,
+                created_at: organization.created_at,
+            })),
+            Err(why) => {
+                error!("Failed to create organization for user {user_id}, {why}");
+                OrganizationCreateResponse::InternalError(PlainText(
+                    "Could not create the organization, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[oai(path = "/organizations", method = "get", tag = "ApiTags::Organization")]
+    async fn list(
+        &self,
+        Data(organizations): Data<&OrganizationDatabase>,
+        current_user: CurrentUser,
+    ) -> OrganizationJsonResponse<Vec<OrganizationResponse>> {
+        let Some(user_id) = current_user.0 else {
+            return OrganizationJsonResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match organizations.list(user_id).await {
+            Ok(list) => OrganizationJsonResponse::Ok(Json(
+                list.into_iter()
+                    .map(|(organization, role)| OrganizationResponse {
+                        id: organization.id,
+                        name: organization.name,
+                        role: role.into(),
+                        created_at: organization.created_at,
+                    })
+                    .collect(),
+            )),
+            Err(why) => OrganizationError::from(why).into(),
+        }
+    }
+
+    #[oai(
+        path = "/organizations/:organization_id/members",
+        method = "get",
+        tag = "ApiTags::Organization"
+    )]
+    async fn members(
+        &self,
+        Data(organizations): Data<&OrganizationDatabase>,
+        current_user: CurrentUser,
+        Path(organization_id): Path<Uuid>,
+    ) -> OrganizationJsonResponse<Vec<MemberResponse>> {
+        let Some(user_id) = current_user.0 else {
+            return OrganizationJsonResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match organizations.members(organization_id, user_id).await {
+            Ok(members) => OrganizationJsonResponse::Ok(Json(
+                members.into_iter().map(MemberResponse::from).collect(),
+            )),
+            Err(why) => why.into(),
+        }
+    }
+
+    /// Adds a registered user to the organization or changes their role, only owners may
+    /// do this.
+    #[oai(
+        path = "/organizations/:organization_id/members",
+        method = "put",
+        tag = "ApiTags::Organization"
+    )]
+    async fn set_member(
+        &self,
+        Data(organizations): Data<&OrganizationDatabase>,
+        current_user: CurrentUser,
+        Path(organization_id): Path<Uuid>,
+        Json(request): Json<MemberRequest>,
+    ) -> OrganizationJsonResponse<MemberResponse> {
+        let Some(user_id) = current_user.0 else {
+            return OrganizationJsonResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match organizations
+            .set_member(
+                organization_id,
+                user_id,
+                &request.email,
+                request.role.into(),
+            )
+            .await
+        {
+            Ok(member) => OrganizationJsonResponse::Ok(Json(member.into())),
+            Err(why) => why.into(),
+        }
+    }
+
+    /// Removes a member, every member may remove themselves.
+    #[oai(
+        path = "/organizations/:organization_id/members/:user_id",
+        method = "delete",
+        tag = "ApiTags::Organization"
+    )]
+    async fn remove_member(
+        &self,
+        Data(organizations): Data<&OrganizationDatabase>,
+        current_user: CurrentUser,
+        Path(organization_id): Path<Uuid>,
+        #[oai(name = "user_id")] Path(member_id): Path<Uuid>,
+    ) -> OrganizationDeleteResponse {
+        let Some(user_id) = current_user.0 else {
+            return OrganizationDeleteResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match organizations
+            .remove_member(organization_id, user_id, member_id)
+            .await
+        {
+            Ok(()) => OrganizationDeleteResponse::Ok,
+            Err(why) => why.into(),
+        }
+    }
+
+    #[oai(
+        path = "/organizations/:organization_id/qr",
+        method = "get",
+        tag = "ApiTags::Organization"
+    )]
+    async fn codes(
+        &self,
+        Data(organizations): Data<&OrganizationDatabase>,
+        current_user: CurrentUser,
+        Path(organization_id): Path<Uuid>,
+    ) -> OrganizationJsonResponse<Vec<QrCodeResponse>> {
+        let Some(user_id) = current_user.0 else {
+            return OrganizationJsonResponse::Unauthorized(PlainText(NOT_LOGGED_IN.to_string()));
+        };
+
+        match organizations.codes(organization_id, user_id).await {
+            Ok(codes) => OrganizationJsonResponse::Ok(Json(
+                codes.into_iter().map(QrCodeResponse::from).collect(),
+            )),
+            Err(why) => why.into(),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use poem::{
+    Endpoint, EndpointExt, IntoResponse, Request, RequestBody, Response,
+    http::{StatusCode, header},
+    middleware::SetHeader,
+};
+use poem_openapi::{
+    ApiExtractor, ApiExtractorType, ExtractParamOptions, Object,
+    payload::Json,
+    registry::{MetaRequest, Registry},
+};
+use service::{AttemptThrottle, Credential, report_passphrase_check};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{config::AppConfig, rate_limit::client_ip, session::CurrentUser};
+
+const PASSPHRASE_HEADER: &str = "X-Qr-Passphrase";
+
+pub(super) const MISSING_PASSPHRASE: &str = "A passphrase is required unless you are logged in, send it in the X-Qr-Passphrase header or the json body.";
+
+#[derive(Object, Debug)]
+pub(super) struct QrCodePassphraseRequest {
+    pub password: String,
+}
+
+/// Optional json body with the passphrase, unlike [`Json`] it may be sent without a
+/// content type, so clients using the header don't need to send a body.
+pub(super) struct PassphraseBody(pub Option<String>);
+
+impl<'a> ApiExtractor<'a> for PassphraseBody {
+    const TYPES: &'static [ApiExtractorType] = &[ApiExtractorType::RequestObject];
+
+    type ParamType = ();
+    type ParamRawType = ();
+
+    fn register(registry: &mut Registry) {
+        <Json<Option<QrCodePassphraseRequest>> as ApiExtractor>::register(registry);
+    }
+
+    fn request_meta() -> Option<MetaRequest> {
+        <Json<Option<QrCodePassphraseRequest>> as ApiExtractor>::request_meta()
+    }
+
+    async fn from_request(
+        request: &'a Request,
+        body: &mut RequestBody,
+        param_opts: ExtractParamOptions<Self::ParamType>,
+    ) -> poem::Result<Self> {
+        if request.content_type().is_none() {
+            return Ok(Self(None));
+        }
+
+        let Json(body) =
+            Json::<Option<QrCodePassphraseRequest>>::from_request(request, body, param_opts)
+                .await?;
+
+        Ok(Self(body.map(|x| x.password)))
+    }
+}
+
+/// Prefers the `X-Qr-Passphrase` header over the password of the json body.
+pub(super) fn passphrase(header: Option<String>, body: Option<String>) -> Option<String> {
+    header.or(body).filter(|x| !x.is_empty())
+}
+
+/// Resolves who manages the qr code, a passphrase takes precedence over the session of a
+/// logged in user.
+pub(super) fn credential(passphrase: Option<String>, user: CurrentUser) -> Option<Credential> {
+    match passphrase.filter(|x| !x.is_empty()) {
+        Some(passphrase) => Some(Credential::Passphrase(passphrase)),
+        None => user.0.map(Credential::User),
+    }
+}
+
+/// Resolves who manages the qr code on the deprecated endpoints, the path always carries a
+/// passphrase, so a logged in user or api key takes precedence there.
+pub(super) fn path_credential(passphrase: String, user: CurrentUser) -> Credential {
+    match user.0 {
+        Some(user_id) => Credential::User(user_id),
+        None => Credential::Passphrase(passphrase),
+    }
+}
+
+fn too_many_attempts(retry_after: Duration) -> Response {
+    let seconds = retry_after.as_secs().max(1);
+
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(header::RETRY_AFTER, seconds)
+        .body(format!(
+            "Too many wrong passphrases, please try again in {seconds} seconds."
+        ))
+}
+
+/// Id of the code a request is about, the segment after `qr` as in `/api/qr/:id/...`.
+fn code_id(path: &str) -> Option<&str> {
+    let mut segments = path.split('/');
+    segments.find(|x| *x == "qr")?;
+    segments.next().filter(|x| !x.is_empty())
+}
+
+/// The passphrase the request tries, in the header or the json body.
+async fn sniff_passphrase(req: &mut Request) -> poem::Result<Option<String>> {
+    if let Some(passphrase) = req.header(PASSPHRASE_HEADER).filter(|x| !x.is_empty()) {
+        return Ok(Some(passphrase.to_string()));
+    }
+    if !req
+        .content_type()
+        .is_some_and(|x| x.starts_with("application/json"))
+    {
+        return Ok(None);
+    }
+
+    let body = req.take_body().into_bytes().await?;
+    let in_body = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|x| x.get("password")?.as_str().map(str::to_string))
+        .filter(|x| !x.is_empty());
+    req.set_body(body);
+
+    Ok(in_body)
+}
+
+/// The passphrase of the deprecated endpoints, always their last path segment.
+fn path_passphrase(path: &str) -> Option<String> {
+    path.rsplit('/')
+        .next()
+        .filter(|x| !x.is_empty())
+        .map(str::to_string)
+}
+
+/// Locks out guessing passphrases with a growing delay like wrong pins are, for the code
+/// and client as well as for the client across all codes.
+///
+/// Only passphrases the endpoint found wrong count as failed attempts. Nobody else can lock
+/// the owner out of their code, and requests for missing codes or entries, or made with a
+/// session or api key taking precedence, aren't counted either.
+pub(super) fn throttle_passphrase(ep: impl Endpoint + 'static) -> impl Endpoint {
+    throttle(ep, false)
+}
+
+fn throttle(ep: impl Endpoint + 'static, in_path: bool) -> impl Endpoint {
+    ep.around(move |ep, mut req| async move {
+        let (Some(throttle), Some(config), Some(id)) = (
+            req.data::<AttemptThrottle>().cloned(),
+            req.data::<AppConfig>(),
+            code_id(req.uri().path()).and_then(|x| x.parse::<Uuid>().ok()),
+        ) else {
+            return ep.call(req).await.map(IntoResponse::into_response);
+        };
+
+        let client_ip = client_ip(
+            req.remote_addr().as_socket_addr().map(|x| x.ip()),
+            req.header("X-Forwarded-For"),
+            &config.trusted_proxies,
+        )
+        .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+        let code_key = format!("passphrase:{id}:{client_ip}");
+        let keys = [code_key.clone(), format!("passphrase:{client_ip}")];
+
+        let passphrase = match in_path {
+            true => path_passphrase(req.uri().path()),
+            false => sniff_passphrase(&mut req).await?,
+        };
+        if passphrase.is_none() {
+            return ep.call(req).await.map(IntoResponse::into_response);
+        }
+        if let Some(retry_after) = keys.iter().filter_map(|key| throttle.check(key)).max() {
+            warn!(target: "security", qr_code = %id, client_ip = %client_ip, "Rejected passphrase attempt during lockout");
+            return Ok(too_many_attempts(retry_after));
+        }
+
+        let (response, matches) = report_passphrase_check(ep.call(req)).await;
+        match matches {
+            Some(true) => throttle.reset(&code_key),
+            Some(false) => {
+                warn!(target: "security", qr_code = %id, client_ip = %client_ip, "Wrong passphrase");
+
+                if let Some(lockout) = keys
+                    .iter()
+                    .filter_map(|key| throttle.record_failure(key))
+                    .max()
+                {
+                    warn!(
+                        target: "security",
+                        qr_code = %id,
+                        client_ip = %client_ip,
+                        lockout = ?lockout,
+                        "Locked out passphrase attempts"
+                    );
+                }
+            }
+            None => {}
+        }
+
+        response.map(IntoResponse::into_response)
+    })
+}
+
+/// Marks the responses of endpoints taking the passphrase as a path segment as deprecated,
+/// those end up in access logs and browser histories.
+pub(super) fn deprecated_path_passphrase(ep: impl Endpoint + 'static) -> impl Endpoint {
+    throttle(ep, true).with(
+        SetHeader::new()
+            .overriding("Deprecation", "true")
+            .overriding(
+                header::WARNING,
+                "299 - \"Passing the passphrase in the path is deprecated, use the X-Qr-Passphrase header instead\"",
+            ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_code_in_the_path() {
+        assert_eq!(code_id("/api/qr/1234/pause"), Some("1234"));
+        assert_eq!(code_id("/api/account/qr/1234/claim"), Some("1234"));
+        assert_eq!(code_id("/api/qr/1234"), Some("1234"));
+        assert_eq!(code_id("/api/qr"), None);
+        assert_eq!(code_id("/api/qr/"), None);
+    }
+
+    #[test]
+    fn finds_the_passphrase_in_the_path() {
+        assert_eq!(
+            path_passphrase("/api/qr/1234/aliases/promo/secret"),
+            Some("secret".to_string())
+        );
+        assert_eq!(path_passphrase("/api/qr/1234/"), None);
+    }
+}
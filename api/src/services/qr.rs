@@ -1,26 +1,204 @@
-use poem::web::Data;
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{DateTime, Utc};
+use entity::sea_orm_active_enums::RedirectStatus;
+use poem::web::{Data, cookie::CookieJar};
 use poem_openapi::{
     ApiResponse, Object, OpenApi,
-    param::Path,
+    param::{Header, Path},
     payload::{Json, PlainText},
     types::ToJSON,
+    validation::Validator,
+};
+use service::{
+    AuditContext, Credential, LinkPolicy, QrCodeDatabase, QrCodeDatabaseError, QrCodeOptions,
+    QueryParams,
 };
-use service::QrCodeDatabase;
 use tracing::error;
 use url::Url;
 use uuid::Uuid;
 
-use crate::services::ApiTags;
+use crate::{
+    api_key::{ApiKeyAuth, Read, Write},
+    audit::Audit,
+    config::AppConfig,
+    services::{
+        ApiTags,
+        passphrase::{
+            MISSING_PASSPHRASE, PassphraseBody, credential, deprecated_path_passphrase, passphrase,
+            path_credential, throttle_passphrase,
+        },
+        readable::{Readable, find_readable},
+    },
+    session::CurrentUser,
+};
+
+const NOT_AN_EDITOR: &str = "You are not allowed to add codes to this organization.";
+
+#[derive(Object, Debug)]
+pub struct QrCodeQueryParams {
+    #[oai(validator(max_length = 255))]
+    pub utm_source: Option<String>,
+    #[oai(validator(max_length = 255))]
+    pub utm_medium: Option<String>,
+    #[oai(validator(max_length = 255))]
+    pub utm_campaign: Option<String>,
+    #[oai(validator(max_length = 255))]
+    pub utm_content: Option<String>,
+    #[oai(validator(max_length = 255))]
+    pub utm_term: Option<String>,
+    #[oai(default)]
+    pub params: BTreeMap<String, String>,
+    #[oai(default)]
+    pub pass_through: bool,
+}
+
+impl From<QrCodeQueryParams> for QueryParams {
+    fn from(value: QrCodeQueryParams) -> Self {
+        Self {
+            utm_source: value.utm_source,
+            utm_medium: value.utm_medium,
+            utm_campaign: value.utm_campaign,
+            utm_content: value.utm_content,
+            utm_term: value.utm_term,
+            extra: value.params,
+            pass_through: value.pass_through,
+        }
+    }
+}
+
+impl From<QueryParams> for QrCodeQueryParams {
+    fn from(value: QueryParams) -> Self {
+        Self {
+            utm_source: value.utm_source,
+            utm_medium: value.utm_medium,
+            utm_campaign: value.utm_campaign,
+            utm_content: value.utm_content,
+            utm_term: value.utm_term,
+            params: value.extra,
+            pass_through: value.pass_through,
+        }
+    }
+}
+
+fn redirect_status(code: u16) -> Option<RedirectStatus> {
+    match code {
+        301 => Some(RedirectStatus::MovedPermanently),
+        302 => Some(RedirectStatus::Found),
+        307 => Some(RedirectStatus::TemporaryRedirect),
+        308 => Some(RedirectStatus::PermanentRedirect),
+        _ => None,
+    }
+}
+
+fn status_code(status: RedirectStatus) -> u16 {
+    match status {
+        RedirectStatus::MovedPermanently => 301,
+        RedirectStatus::Found => 302,
+        RedirectStatus::TemporaryRedirect => 307,
+        RedirectStatus::PermanentRedirect => 308,
+    }
+}
+
+struct RedirectStatusCode;
+
+impl fmt::Display for RedirectStatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "one of 301, 302, 307 or 308")
+    }
+}
+
+impl Validator<u16> for RedirectStatusCode {
+    fn check(&self, value: &u16) -> bool {
+        redirect_status(*value).is_some()
+    }
+}
 
 #[derive(Object, Debug)]
 struct QrCodePostRequest {
     pub link: Url,
+    #[oai(validator(minimum(value = "1")))]
+    pub max_scans: Option<i32>,
+    #[oai(validator(min_length = 4, max_length = 128))]
+    pub access_password: Option<String>,
+    pub sticky_variants: Option<bool>,
+    pub query_params: Option<QrCodeQueryParams>,
+    /// Permanent statuses (301, 308) are only used for immutable codes without dynamic
+    /// features, others get the matching temporary status.
+    #[oai(validator(custom = "RedirectStatusCode"))]
+    pub redirect_status: Option<u16>,
+    #[oai(validator(minimum(value = "0")))]
+    pub cache_max_age: Option<i32>,
+    /// Shows a page naming the destination before redirecting, the scan is counted once the
+    /// visitor continues.
+    pub preview: Option<bool>,
+    /// Shown on the preview page instead of the title of the destination page.
+    #[oai(validator(max_length = 255))]
+    pub preview_title: Option<String>,
+    /// Used instead of an error whenever the code is expired, paused, exhausted or broken.
+    pub fallback_link: Option<Url>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Enables recovering a lost passphrase with a token mailed to this address.
+    #[oai(validator(max_length = 254, pattern = r"^[^@\s]+@[^@\s]+\.[^@\s]+$"))]
+    pub owner_email: Option<String>,
+    /// Adds the code to an organization the caller is an editor of, moving an existing code
+    /// requires managing it.
+    pub organization_id: Option<Uuid>,
+    /// Fixes the link for good, browsers and caches may then keep permanent redirects. The
+    /// code can't be edited, paused, deleted or given schedules, targeting rules and variants
+    /// afterwards.
+    pub immutable: Option<bool>,
 }
 
 #[derive(Object, Debug)]
 struct QrCodePutRequest {
     pub link: Url,
-    pub password: String,
+    pub password: Option<String>,
+    #[oai(validator(minimum(value = "1")))]
+    pub max_scans: Option<i32>,
+    /// Removes the scan limit, can't be combined with `max_scans`.
+    #[oai(default)]
+    pub clear_max_scans: bool,
+    #[oai(validator(min_length = 4, max_length = 128))]
+    pub access_password: Option<String>,
+    /// Removes the access password, can't be combined with `access_password`.
+    #[oai(default)]
+    pub clear_access_password: bool,
+    pub sticky_variants: Option<bool>,
+    pub query_params: Option<QrCodeQueryParams>,
+    /// Permanent statuses (301, 308) are only used for immutable codes without dynamic
+    /// features, others get the matching temporary status.
+    #[oai(validator(custom = "RedirectStatusCode"))]
+    pub redirect_status: Option<u16>,
+    #[oai(validator(minimum(value = "0")))]
+    pub cache_max_age: Option<i32>,
+    /// Removes the cache lifetime, can't be combined with `cache_max_age`.
+    #[oai(default)]
+    pub clear_cache_max_age: bool,
+    /// Shows a page naming the destination before redirecting, the scan is counted once the
+    /// visitor continues.
+    pub preview: Option<bool>,
+    /// Shown on the preview page instead of the title of the destination page.
+    #[oai(validator(max_length = 255))]
+    pub preview_title: Option<String>,
+    /// Used instead of an error whenever the code is expired, paused, exhausted or broken.
+    pub fallback_link: Option<Url>,
+    /// Removes the fallback link, can't be combined with `fallback_link`.
+    #[oai(default)]
+    pub clear_fallback_link: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Lets the code redirect again without an end, can't be combined with `expires_at`.
+    #[oai(default)]
+    pub clear_expires_at: bool,
+    /// Enables recovering a lost passphrase with a token mailed to this address.
+    #[oai(validator(max_length = 254, pattern = r"^[^@\s]+@[^@\s]+\.[^@\s]+$"))]
+    pub owner_email: Option<String>,
+    /// Removes the owner email, can't be combined with `owner_email`.
+    #[oai(default)]
+    pub clear_owner_email: bool,
+    /// Adds the code to an organization the caller is an editor of, moving an existing code
+    /// requires managing it.
+    pub organization_id: Option<Uuid>,
 }
 
 #[derive(ApiResponse)]
@@ -28,9 +206,18 @@ enum QrCodeTextResponse<T: Into<String> + Send + Sync + 'static> {
     #[oai(status = 200)]
     Ok(PlainText<T>),
 
+    #[oai(status = 400)]
+    Rejected(PlainText<String>),
+
+    #[oai(status = 403)]
+    Forbidden(PlainText<String>),
+
     #[oai(status = 404)]
     NotFound(PlainText<String>),
 
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
     #[oai(status = 500)]
     InternalError(PlainText<String>),
 }
@@ -40,9 +227,15 @@ enum QrCodeJsonResponse<T: ToJSON + Send + Sync + 'static> {
     #[oai(status = 200)]
     Ok(Json<T>),
 
+    #[oai(status = 400)]
+    MissingPassphrase(PlainText<String>),
+
     #[oai(status = 404)]
     NotFound(PlainText<String>),
 
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
     #[oai(status = 500)]
     InternalError(PlainText<String>),
 }
@@ -52,9 +245,15 @@ enum QrCodeDeleteResponse {
     #[oai(status = 200)]
     Ok,
 
+    #[oai(status = 400)]
+    MissingPassphrase(PlainText<String>),
+
     #[oai(status = 404)]
     NotFound(PlainText<String>),
 
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
     #[oai(status = 500)]
     InternalError(PlainText<String>),
 }
@@ -62,19 +261,151 @@ enum QrCodeDeleteResponse {
 #[derive(Object, Debug)]
 pub struct QrCodeResponse {
     pub id: Uuid,
-    pub link: String,
+    /// Hidden for codes with an access password, unless the caller manages the code or
+    /// unlocked it.
+    pub link: Option<String>,
+    pub slug: Option<String>,
     pub passphrase: Option<String>,
+    pub scan_count: i32,
+    pub max_scans: Option<i32>,
+    pub password_protected: bool,
+    pub sticky_variants: bool,
+    pub query_params: QrCodeQueryParams,
+    pub redirect_status: u16,
+    pub cache_max_age: Option<i32>,
+    pub active: bool,
+    pub preview: bool,
+    pub preview_title: Option<String>,
+    /// Hidden like `link`.
+    pub fallback_link: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub recoverable: bool,
+    pub organization_id: Option<Uuid>,
+    pub immutable: bool,
+}
+
+impl From<entity::qr_code::Model> for QrCodeResponse {
+    fn from(value: entity::qr_code::Model) -> Self {
+        Self {
+            query_params: QueryParams::from_model(&value).into(),
+            id: value.id,
+            link: Some(value.link),
+            slug: value.slug,
+            passphrase: None,
+            scan_count: value.scan_count,
+            max_scans: value.max_scans,
+            password_protected: value.access_password_hash.is_some(),
+            sticky_variants: value.sticky_variants,
+            redirect_status: status_code(value.redirect_status),
+            cache_max_age: value.cache_max_age,
+            active: value.active,
+            preview: value.preview,
+            preview_title: value.preview_title,
+            fallback_link: value.fallback_link,
+            expires_at: value.expires_at,
+            recoverable: value.owner_email.is_some(),
+            organization_id: value.organization_id,
+            immutable: value.immutable,
+        }
+    }
+}
+
+impl From<Readable> for QrCodeResponse {
+    fn from(value: Readable) -> Self {
+        let response = Self::from(value.qr_code);
+
+        match value.reveal_links {
+            true => response,
+            false => Self {
+                link: None,
+                fallback_link: None,
+                ..response
+            },
+        }
+    }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(ApiResponse)]
 pub enum QrCodeCreateResponse {
     #[oai(status = 201)]
     Created(Json<QrCodeResponse>),
 
+    #[oai(status = 400)]
+    Rejected(PlainText<String>),
+
+    #[oai(status = 403)]
+    Forbidden(PlainText<String>),
+
     #[oai(status = 500)]
     Database(PlainText<String>),
 }
 
+fn check_links(
+    link_policy: &LinkPolicy,
+    link: &Url,
+    fallback_link: &Option<Url>,
+) -> Result<(), String> {
+    link_policy
+        .check(link)
+        .map_err(|why| format!("The link was rejected, {why}."))?;
+
+    match fallback_link {
+        Some(fallback_link) => link_policy
+            .check(fallback_link)
+            .map_err(|why| format!("The fallback link was rejected, {why}.")),
+        None => Ok(()),
+    }
+}
+
+async fn set_active(
+    database: &QrCodeDatabase,
+    id: Uuid,
+    credential: Option<Credential>,
+    active: bool,
+    audit: &AuditContext,
+) -> QrCodeJsonResponse<QrCodeResponse> {
+    let Some(credential) = credential else {
+        return QrCodeJsonResponse::MissingPassphrase(PlainText(MISSING_PASSPHRASE.to_string()));
+    };
+
+    match database.set_active(id, credential, active, audit).await {
+        Ok(Some(model)) => QrCodeJsonResponse::Ok(Json(model.into())),
+        Ok(None) => QrCodeJsonResponse::NotFound(PlainText(
+            "No qr code could be found for this id.".to_string(),
+        )),
+        Err(QrCodeDatabaseError::Immutable) => QrCodeJsonResponse::Conflict(PlainText(
+            "Immutable qr codes can't be edited.".to_string(),
+        )),
+        Err(why) => {
+            error!("Failed to change the state of qr code {id}, {why}");
+            QrCodeJsonResponse::InternalError(PlainText(
+                "Could not change the qr code state, because of an internal error.".to_string(),
+            ))
+        }
+    }
+}
+
+async fn delete_qr_code(
+    database: &QrCodeDatabase,
+    id: Uuid,
+    credential: Credential,
+    audit: &AuditContext,
+) -> QrCodeDeleteResponse {
+    match database.delete(id, credential, audit).await {
+        Ok(Some(_)) => QrCodeDeleteResponse::Ok,
+        Ok(None) => QrCodeDeleteResponse::NotFound(PlainText(
+            "No qr code could be found with this id.".to_string(),
+        )),
+        Err(QrCodeDatabaseError::Immutable) => QrCodeDeleteResponse::Conflict(PlainText(
+            "Immutable qr codes can't be edited.".to_string(),
+        )),
+        Err(_) => QrCodeDeleteResponse::InternalError(PlainText(
+            "Could not retrieve qr code information, because of an internal error.".to_string(),
+        )),
+    }
+}
+
 pub struct QrCodeApi;
 
 #[OpenApi]
@@ -83,35 +414,75 @@ impl QrCodeApi {
     async fn create(
         &self,
         Data(database): Data<&QrCodeDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
         Json(request): Json<QrCodePostRequest>,
     ) -> QrCodeCreateResponse {
-        match database.create(request.link).await {
-            Ok(m) => QrCodeCreateResponse::Created(Json(QrCodeResponse {
-                id: m.id,
-                link: m.link,
-                passphrase: Some(m.passphrase),
+        if let Err(why) = check_links(link_policy, &request.link, &request.fallback_link) {
+            return QrCodeCreateResponse::Rejected(PlainText(why));
+        }
+
+        let options = QrCodeOptions {
+            max_scans: request.max_scans,
+            access_password: request.access_password,
+            clear_access_password: false,
+            clear_fallback_link: false,
+            clear_expires_at: false,
+            clear_max_scans: false,
+            clear_cache_max_age: false,
+            clear_owner_email: false,
+            sticky_variants: request.sticky_variants,
+            query_params: request.query_params.map(Into::into),
+            redirect_status: request.redirect_status.and_then(redirect_status),
+            cache_max_age: request.cache_max_age,
+            preview: request.preview,
+            preview_title: request.preview_title,
+            fallback_link: request.fallback_link,
+            expires_at: request.expires_at,
+            owner_email: request.owner_email,
+            owner_id: auth.or(current_user).0,
+            organization_id: request.organization_id,
+            immutable: request.immutable,
+        };
+
+        match database.create(request.link, options, &audit).await {
+            Ok((m, passphrase)) => QrCodeCreateResponse::Created(Json(QrCodeResponse {
+                passphrase: Some(passphrase),
+                ..m.into()
             })),
+            Err(QrCodeDatabaseError::Forbidden) => {
+                QrCodeCreateResponse::Forbidden(PlainText(NOT_AN_EDITOR.to_string()))
+            }
             Err(why) => {
                 error!("Failed to create new qr code, {why}");
-                return QrCodeCreateResponse::Database(PlainText(
+                QrCodeCreateResponse::Database(PlainText(
                     "Could not create qr code because of an internal error.".to_string(),
-                ));
+                ))
             }
         }
     }
 
-    #[oai(path = "/qr/:id", method = "get", tag = "ApiTags::QrCode")]
+    #[oai(
+        path = "/qr/:id",
+        method = "get",
+        tag = "ApiTags::QrCode",
+        transform = "throttle_passphrase"
+    )]
     async fn get(
         &self,
         Data(database): Data<&QrCodeDatabase>,
+        cookie_jar: &CookieJar,
         Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
     ) -> QrCodeJsonResponse<QrCodeResponse> {
-        match database.get(id).await {
-            Ok(Some(model)) => QrCodeJsonResponse::Ok(Json(QrCodeResponse {
-                id: model.id,
-                link: model.link,
-                passphrase: None,
-            })),
+        let credential = credential(passphrase_header.0, auth.or(current_user));
+
+        match find_readable(database, cookie_jar, id, credential).await {
+            Ok(Some(readable)) => QrCodeJsonResponse::Ok(Json(readable.into())),
             Ok(None) => QrCodeJsonResponse::NotFound(PlainText(
                 "No qr code could be found for this id.".to_string(),
             )),
@@ -121,39 +492,277 @@ impl QrCodeApi {
         }
     }
 
-    #[oai(path = "/qr/:id", method = "put", tag = "ApiTags::QrCode")]
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id",
+        method = "put",
+        tag = "ApiTags::QrCode",
+        transform = "throttle_passphrase"
+    )]
     async fn update(
         &self,
         Data(database): Data<&QrCodeDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
         Json(request): Json<QrCodePutRequest>,
         Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
     ) -> QrCodeTextResponse<Uuid> {
-        match database.update(id, request.password, request.link).await {
+        let Some(credential) = credential(
+            passphrase(passphrase_header.0, request.password),
+            auth.or(current_user),
+        ) else {
+            return QrCodeTextResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+        if let Err(why) = check_links(link_policy, &request.link, &request.fallback_link) {
+            return QrCodeTextResponse::Rejected(PlainText(why));
+        }
+        if request.max_scans.is_some() && request.clear_max_scans {
+            return QrCodeTextResponse::Rejected(PlainText(
+                "Either set or clear the scan limit, not both.".to_string(),
+            ));
+        }
+        if request.access_password.is_some() && request.clear_access_password {
+            return QrCodeTextResponse::Rejected(PlainText(
+                "Either set or clear the access password, not both.".to_string(),
+            ));
+        }
+        if request.fallback_link.is_some() && request.clear_fallback_link {
+            return QrCodeTextResponse::Rejected(PlainText(
+                "Either set or clear the fallback link, not both.".to_string(),
+            ));
+        }
+        if request.expires_at.is_some() && request.clear_expires_at {
+            return QrCodeTextResponse::Rejected(PlainText(
+                "Either set or clear the expiry, not both.".to_string(),
+            ));
+        }
+        if request.cache_max_age.is_some() && request.clear_cache_max_age {
+            return QrCodeTextResponse::Rejected(PlainText(
+                "Either set or clear the cache lifetime, not both.".to_string(),
+            ));
+        }
+        if request.owner_email.is_some() && request.clear_owner_email {
+            return QrCodeTextResponse::Rejected(PlainText(
+                "Either set or clear the owner email, not both.".to_string(),
+            ));
+        }
+
+        let options = QrCodeOptions {
+            max_scans: request.max_scans,
+            clear_max_scans: request.clear_max_scans,
+            access_password: request.access_password,
+            clear_access_password: request.clear_access_password,
+            sticky_variants: request.sticky_variants,
+            query_params: request.query_params.map(Into::into),
+            redirect_status: request.redirect_status.and_then(redirect_status),
+            cache_max_age: request.cache_max_age,
+            clear_cache_max_age: request.clear_cache_max_age,
+            preview: request.preview,
+            preview_title: request.preview_title,
+            fallback_link: request.fallback_link,
+            clear_fallback_link: request.clear_fallback_link,
+            expires_at: request.expires_at,
+            clear_expires_at: request.clear_expires_at,
+            owner_email: request.owner_email,
+            clear_owner_email: request.clear_owner_email,
+            owner_id: None,
+            organization_id: request.organization_id,
+            immutable: None,
+        };
+
+        match database
+            .update(id, credential, request.link, options, &audit)
+            .await
+        {
             Ok(Some(model)) => QrCodeTextResponse::Ok(PlainText(model.id)),
             Ok(None) => QrCodeTextResponse::NotFound(PlainText(
                 "No qr code could be found for this id.".to_string(),
             )),
+            Err(QrCodeDatabaseError::Forbidden) => {
+                QrCodeTextResponse::Forbidden(PlainText(NOT_AN_EDITOR.to_string()))
+            }
+            Err(QrCodeDatabaseError::Immutable) => QrCodeTextResponse::Conflict(PlainText(
+                "Immutable qr codes can't be edited.".to_string(),
+            )),
             Err(_) => QrCodeTextResponse::InternalError(PlainText(
                 "Could not retrieve qr code information, because of an internal error.".to_string(),
             )),
         }
     }
 
-    #[oai(path = "/qr/:id/:pass", method = "delete", tag = "ApiTags::QrCode")]
-    async fn delete(
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/pause",
+        method = "post",
+        tag = "ApiTags::QrCode",
+        transform = "throttle_passphrase"
+    )]
+    async fn pause(
         &self,
         Data(database): Data<&QrCodeDatabase>,
         Path(id): Path<Uuid>,
-        Path(password): Path<String>,
-    ) -> QrCodeDeleteResponse {
-        match database.delete(id, password).await {
-            Ok(Some(_)) => QrCodeDeleteResponse::Ok,
-            Ok(None) => QrCodeDeleteResponse::NotFound(PlainText(
-                "No qr code could be found with this id.".to_string(),
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
+    ) -> QrCodeJsonResponse<QrCodeResponse> {
+        let credential = credential(passphrase(passphrase_header.0, body), auth.or(current_user));
+
+        set_active(database, id, credential, false, &audit).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/resume",
+        method = "post",
+        tag = "ApiTags::QrCode",
+        transform = "throttle_passphrase"
+    )]
+    async fn resume(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
+    ) -> QrCodeJsonResponse<QrCodeResponse> {
+        let credential = credential(passphrase(passphrase_header.0, body), auth.or(current_user));
+
+        set_active(database, id, credential, true, &audit).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/passphrase",
+        method = "post",
+        tag = "ApiTags::QrCode",
+        transform = "throttle_passphrase"
+    )]
+    async fn rotate_passphrase(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
+    ) -> QrCodeJsonResponse<QrCodeResponse> {
+        let Some(credential) =
+            credential(passphrase(passphrase_header.0, body), auth.or(current_user))
+        else {
+            return QrCodeJsonResponse::MissingPassphrase(PlainText(
+                MISSING_PASSPHRASE.to_string(),
+            ));
+        };
+
+        match database.rotate_passphrase(id, credential, &audit).await {
+            Ok(Some((model, passphrase))) => QrCodeJsonResponse::Ok(Json(QrCodeResponse {
+                passphrase: Some(passphrase),
+                ..model.into()
+            })),
+            Ok(None) => QrCodeJsonResponse::NotFound(PlainText(
+                "No qr code could be found for this id.".to_string(),
             )),
-            Err(_) => QrCodeDeleteResponse::InternalError(PlainText(
-                "Could not retrieve qr code information, because of an internal error.".to_string(),
+            Err(why) => {
+                error!("Failed to rotate the passphrase of qr code {id}, {why}");
+                QrCodeJsonResponse::InternalError(PlainText(
+                    "Could not rotate the passphrase, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/restore",
+        method = "post",
+        tag = "ApiTags::QrCode",
+        transform = "throttle_passphrase"
+    )]
+    async fn restore(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(config): Data<&AppConfig>,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
+    ) -> QrCodeJsonResponse<QrCodeResponse> {
+        let Some(credential) =
+            credential(passphrase(passphrase_header.0, body), auth.or(current_user))
+        else {
+            return QrCodeJsonResponse::MissingPassphrase(PlainText(
+                MISSING_PASSPHRASE.to_string(),
+            ));
+        };
+        let retention = chrono::Duration::days(config.trash_retention_days);
+
+        match database.restore(id, credential, retention, &audit).await {
+            Ok(Some(model)) => QrCodeJsonResponse::Ok(Json(model.into())),
+            Ok(None) => QrCodeJsonResponse::NotFound(PlainText(
+                "No deleted qr code could be found for this id.".to_string(),
             )),
+            Err(why) => {
+                error!("Failed to restore qr code {id}, {why}");
+                QrCodeJsonResponse::InternalError(PlainText(
+                    "Could not restore the qr code, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id",
+        method = "delete",
+        tag = "ApiTags::QrCode",
+        transform = "throttle_passphrase"
+    )]
+    async fn delete(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
+    ) -> QrCodeDeleteResponse {
+        match credential(passphrase(passphrase_header.0, body), auth.or(current_user)) {
+            Some(credential) => delete_qr_code(database, id, credential, &audit).await,
+            None => {
+                QrCodeDeleteResponse::MissingPassphrase(PlainText(MISSING_PASSPHRASE.to_string()))
+            }
         }
     }
+
+    #[oai(
+        path = "/qr/:id/:pass",
+        method = "delete",
+        tag = "ApiTags::QrCode",
+        deprecated,
+        transform = "deprecated_path_passphrase"
+    )]
+    async fn delete_with_path_passphrase(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Path(id): Path<Uuid>,
+        Path(password): Path<String>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+        Audit(audit): Audit,
+    ) -> QrCodeDeleteResponse {
+        let credential = path_credential(password, auth.or(current_user));
+
+        delete_qr_code(database, id, credential, &audit).await
+    }
 }
@@ -0,0 +1,59 @@
+use entity::qr_code::Model;
+use poem::web::cookie::CookieJar;
+use service::{Credential, DbErr, QrCodeDatabase};
+use uuid::Uuid;
+
+use crate::services::redirect::is_unlocked;
+
+/// A qr code looked up by one of the read endpoints.
+pub(super) struct Readable {
+    pub qr_code: Model,
+    /// Links of codes with an access password are only shown to those managing the code and
+    /// to visitors who unlocked it with its PIN.
+    pub reveal_links: bool,
+}
+
+impl Readable {
+    /// Hides a destination link of the code unless links may be revealed.
+    pub fn link(&self, link: String) -> Option<String> {
+        self.reveal_links.then_some(link)
+    }
+}
+
+/// Looks up a qr code for reading, like [`QrCodeDatabase::get_visible`].
+///
+/// A passphrase that doesn't match is answered like a missing code, the same way the
+/// endpoints changing a code do.
+pub(super) async fn find_readable(
+    database: &QrCodeDatabase,
+    cookie_jar: &CookieJar,
+    id: Uuid,
+    credential: Option<Credential>,
+) -> Result<Option<Readable>, DbErr> {
+    let viewer = match credential {
+        Some(Credential::User(user_id)) => Some(user_id),
+        _ => None,
+    };
+    let Some(qr_code) = database.get_visible(id, viewer).await? else {
+        return Ok(None);
+    };
+
+    let authorized = match &credential {
+        Some(credential) => database.may_view(&qr_code, credential).await?,
+        None => false,
+    };
+    if !authorized && matches!(credential, Some(Credential::Passphrase(_))) {
+        return Ok(None);
+    }
+
+    let reveal_links = authorized
+        || qr_code
+            .access_password_hash
+            .as_deref()
+            .is_none_or(|password_hash| is_unlocked(cookie_jar, id, password_hash));
+
+    Ok(Some(Readable {
+        qr_code,
+        reveal_links,
+    }))
+}
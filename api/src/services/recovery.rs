@@ -0,0 +1,103 @@
+use poem::web::Data;
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::Path,
+    payload::{Json, PlainText},
+};
+use service::{ConfiguredMailer, PassphraseRecovery};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    audit::Audit,
+    services::{ApiTags, qr::QrCodeResponse},
+};
+
+#[derive(Object, Debug)]
+struct RecoveryRequest {
+    #[oai(validator(max_length = 254))]
+    pub email: String,
+}
+
+#[derive(Object, Debug)]
+struct RecoveryRedeemRequest {
+    #[oai(validator(max_length = 128))]
+    pub token: String,
+}
+
+#[derive(ApiResponse)]
+enum RecoveryRequestResponse {
+    #[oai(status = 202)]
+    Accepted(PlainText<String>),
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(ApiResponse)]
+enum RecoveryRedeemResponse {
+    #[oai(status = 200)]
+    Ok(Json<QrCodeResponse>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+pub struct RecoveryApi;
+
+#[OpenApi]
+impl RecoveryApi {
+    /// Answers the same right away whether or not the email belongs to the code, the token
+    /// is looked up and mailed in the background so the timing doesn't tell either.
+    #[oai(path = "/qr/:id/recovery", method = "post", tag = "ApiTags::Recovery")]
+    async fn request(
+        &self,
+        Data(recovery): Data<&PassphraseRecovery>,
+        Data(mailer): Data<&ConfiguredMailer>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<RecoveryRequest>,
+    ) -> RecoveryRequestResponse {
+        let recovery = recovery.clone();
+        let mailer = mailer.clone();
+        tokio::spawn(async move {
+            if let Err(why) = recovery.request(id, &request.email, &mailer).await {
+                error!("Failed to send recovery token for qr code {id}, {why}");
+            }
+        });
+
+        RecoveryRequestResponse::Accepted(PlainText(
+            "If the email belongs to this qr code, a recovery token will be sent to it."
+                .to_string(),
+        ))
+    }
+
+    #[oai(
+        path = "/qr/:id/recovery/redeem",
+        method = "post",
+        tag = "ApiTags::Recovery"
+    )]
+    async fn redeem(
+        &self,
+        Data(recovery): Data<&PassphraseRecovery>,
+        Path(id): Path<Uuid>,
+        Audit(audit): Audit,
+        Json(request): Json<RecoveryRedeemRequest>,
+    ) -> RecoveryRedeemResponse {
+        match recovery.redeem(id, &request.token, &audit).await {
+            Ok(Some((model, passphrase))) => RecoveryRedeemResponse::Ok(Json(QrCodeResponse {
+                passphrase: Some(passphrase),
+                ..model.into()
+            })),
+            Ok(None) => RecoveryRedeemResponse::NotFound(PlainText(
+                "The recovery token is invalid or expired.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to redeem recovery token for qr code {id}, {why}");
+                RecoveryRedeemResponse::InternalError(PlainText(
+                    "Could not recover the passphrase, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+}
@@ -1,55 +1,729 @@
-use poem::web::Data;
-use poem_openapi::{ApiResponse, OpenApi, param::Query, payload::PlainText};
-use service::QrCodeDatabase;
-use tracing::error;
+use chrono::{Duration, Utc};
+use entity::{qr_code::Model, sea_orm_active_enums::RedirectStatus};
+use poem::{
+    Request, handler,
+    http::header,
+    web::{
+        Data, Path,
+        cookie::{Cookie, CookieJar, SameSite},
+    },
+};
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::Query,
+    payload::{Form, Html, PlainText},
+};
+use serde::Deserialize;
+use service::{
+    AttemptThrottle, ClientInfo, Destination, DestinationResolver, QrCodeDatabase, QueryParams,
+    RedirectPolicy, ScanOutcome, TitleFetcher, token_hash, verify_password,
+};
+use tracing::{error, warn};
 use url::Url;
 use uuid::Uuid;
 
-use crate::services::ApiTags;
+use crate::{
+    config::AppConfig,
+    pages::{preview_page, unavailable_page, unlock_page},
+    rate_limit::client_ip,
+    services::ApiTags,
+};
 
 #[derive(ApiResponse)]
 enum RedirectResponse {
+    #[oai(status = 301)]
+    MovedPermanently(
+        #[oai(header = "Location")] Url,
+        #[oai(header = "Cache-Control")] String,
+    ),
     #[oai(status = 302)]
-    Redirect(#[oai(header = "Location")] Url),
+    Found(
+        #[oai(header = "Location")] Url,
+        #[oai(header = "Cache-Control")] String,
+    ),
+    #[oai(status = 307)]
+    TemporaryRedirect(
+        #[oai(header = "Location")] Url,
+        #[oai(header = "Cache-Control")] String,
+    ),
+    #[oai(status = 308)]
+    PermanentRedirect(
+        #[oai(header = "Location")] Url,
+        #[oai(header = "Cache-Control")] String,
+    ),
+    #[oai(status = 200)]
+    Preview(Html<String>, #[oai(header = "Cache-Control")] String),
+    #[oai(status = 401)]
+    Locked(Html<String>),
+    #[oai(status = 403)]
+    Blocked(PlainText<String>),
     #[oai(status = 404)]
     NotFound(PlainText<String>),
+    #[oai(status = 410)]
+    Exhausted(PlainText<String>),
+    #[oai(status = 410)]
+    Expired(PlainText<String>),
+    #[oai(status = 429)]
+    TooManyAttempts(Html<String>, #[oai(header = "Retry-After")] u64),
+    #[oai(status = 503)]
+    Unavailable(Html<String>),
     #[oai(status = 500)]
     DatabaseError(PlainText<String>),
     #[oai(status = 500)]
     InvalidUrl(PlainText<String>),
 }
 
+#[derive(Object, Deserialize, Debug)]
+struct UnlockForm {
+    pub pin: String,
+}
+
+const VARIANT_COOKIE_MAX_AGE: u64 = 30 * 24 * 60 * 60;
+const RESERVED_QUERY_KEYS: [&str; 3] = ["id", "preview", "destination"];
+/// How long the continue link of a preview page keeps its destination.
+const PREVIEW_LIFETIME_MINUTES: i64 = 30;
+
+fn unlock_cookie_name(id: Uuid) -> String {
+    format!("qr_unlock_{}", id.simple())
+}
+
+fn variant_cookie_name(id: Uuid) -> String {
+    format!("qr_variant_{}", id.simple())
+}
+
+/// Identifies the PIN an unlock cookie was issued for, so changing the PIN revokes them.
+fn pin_fingerprint(password_hash: &str) -> String {
+    token_hash(password_hash)[..16].to_string()
+}
+
+pub(super) fn is_unlocked(cookie_jar: &CookieJar, id: Uuid, password_hash: &str) -> bool {
+    let Some(cookie) = cookie_jar.signed().get(&unlock_cookie_name(id)) else {
+        return false;
+    };
+    let Some((expires_at, fingerprint)) = cookie.value_str().split_once('.') else {
+        return false;
+    };
+
+    fingerprint == pin_fingerprint(password_hash)
+        && expires_at
+            .parse::<i64>()
+            .is_ok_and(|expires_at| expires_at > Utc::now().timestamp())
+}
+
+fn set_unlock_cookie(cookie_jar: &CookieJar, id: Uuid, password_hash: &str, ttl_minutes: i64) {
+    let ttl = Duration::minutes(ttl_minutes);
+    let value = format!(
+        "{}.{}",
+        (Utc::now() + ttl).timestamp(),
+        pin_fingerprint(password_hash)
+    );
+    let mut cookie = Cookie::new_with_str(unlock_cookie_name(id), value);
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_max_age(ttl.to_std().unwrap_or_default());
+
+    cookie_jar.signed().add(cookie);
+}
+
+fn client_info(request: &Request, config: &AppConfig) -> ClientInfo {
+    let header = |name: &str| request.headers().get(name).and_then(|x| x.to_str().ok());
+
+    ClientInfo::from_headers(
+        header(header::USER_AGENT.as_str()),
+        header(header::ACCEPT_LANGUAGE.as_str()),
+        header(&config.country_header),
+    )
+}
+
+async fn find_qr_code(database: &QrCodeDatabase, id: Uuid) -> Result<Model, RedirectResponse> {
+    match database.get(id).await {
+        Ok(Some(qr_code)) => Ok(qr_code),
+        Ok(None) => Err(RedirectResponse::NotFound(PlainText(
+            "The requested qr code id could not be found.".to_string(),
+        ))),
+        Err(why) => {
+            error!("Could not redirect user because of {why}");
+            Err(RedirectResponse::DatabaseError(PlainText(
+                "The redirection failed because of an internal error.".to_string(),
+            )))
+        }
+    }
+}
+
+fn preferred_variant(cookie_jar: &CookieJar, id: Uuid) -> Option<Uuid> {
+    cookie_jar
+        .get(&variant_cookie_name(id))
+        .and_then(|cookie| cookie.value_str().parse().ok())
+}
+
+fn set_variant_cookie(cookie_jar: &CookieJar, id: Uuid, variant_id: Uuid) {
+    let mut cookie = Cookie::new_with_str(variant_cookie_name(id), variant_id.to_string());
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_max_age(std::time::Duration::from_secs(VARIANT_COOKIE_MAX_AGE));
+
+    cookie_jar.add(cookie);
+}
+
+fn scanned_query(request: &Request) -> Vec<(String, String)> {
+    let query = request.uri().query().unwrap_or_default();
+
+    url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .filter(|(key, _)| !RESERVED_QUERY_KEYS.contains(&key.as_str()))
+        .collect()
+}
+
+/// `?preview=1` asks for the preview page, `?preview=0` skips it like its continue link
+/// does.
+fn preview_param(request: &Request) -> Option<bool> {
+    let query = request.uri().query().unwrap_or_default();
+
+    url::form_urlencoded::parse(query.as_bytes())
+        .filter(|(key, _)| key == "preview")
+        .find_map(|(_, value)| match value.as_ref() {
+            "1" | "true" => Some(true),
+            "0" | "false" => Some(false),
+            _ => None,
+        })
+}
+
+/// The destination the preview page signed into its continue link, see [`continue_url`].
+fn signed_destination(
+    resolver: &DestinationResolver,
+    request: &Request,
+    id: Uuid,
+) -> Option<Destination> {
+    let query = request.uri().query().unwrap_or_default();
+    let token = url::form_urlencoded::parse(query.as_bytes())
+        .find_map(|(key, value)| (key == "destination").then_some(value))?;
+
+    resolver.signer.verify(id, &token, Utc::now().timestamp())
+}
+
+/// Redirects again without the preview, only then the scan is counted.
+///
+/// The destination shown is signed into the link, so continuing goes there even if the
+/// resolver would pick another variant, rule or scheduled link by now.
+fn continue_url(
+    resolver: &DestinationResolver,
+    request: &Request,
+    id: Uuid,
+    destination: &Destination,
+) -> String {
+    let expires = (Utc::now() + Duration::minutes(PREVIEW_LIFETIME_MINUTES)).timestamp();
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("id", &id.to_string())
+        .append_pair("preview", "0")
+        .append_pair(
+            "destination",
+            &resolver.signer.sign(id, destination, expires),
+        )
+        .extend_pairs(scanned_query(request))
+        .finish();
+
+    format!("/api/redirect?{query}")
+}
+
+/// Sends the scanner to the fallback link of the code instead of answering with `response`.
+fn fallback_or(
+    resolver: &DestinationResolver,
+    qr_code: &Model,
+    response: RedirectResponse,
+) -> RedirectResponse {
+    let fallback = qr_code
+        .fallback_link
+        .as_deref()
+        .and_then(|link| Url::parse(link).ok())
+        .filter(|url| resolver.link_policy.check(url).is_ok());
+
+    match fallback {
+        Some(url) => RedirectResponse::Found(url, "no-store".to_string()),
+        None => response,
+    }
+}
+
+/// Answers scans of paused or expired codes, `None` if the code can be redirected.
+fn inactive_response(resolver: &DestinationResolver, qr_code: &Model) -> Option<RedirectResponse> {
+    let response = if !qr_code.active {
+        RedirectResponse::Unavailable(Html(unavailable_page()))
+    } else if qr_code.expires_at.is_some_and(|x| x <= Utc::now()) {
+        RedirectResponse::Expired(PlainText("This qr code has expired.".to_string()))
+    } else {
+        return None;
+    };
+
+    Some(fallback_or(resolver, qr_code, response))
+}
+
+fn redirect_to(url: Url, policy: RedirectPolicy) -> RedirectResponse {
+    let cache_control = policy.cache_control;
+
+    match policy.status {
+        RedirectStatus::MovedPermanently => RedirectResponse::MovedPermanently(url, cache_control),
+        RedirectStatus::Found => RedirectResponse::Found(url, cache_control),
+        RedirectStatus::TemporaryRedirect => {
+            RedirectResponse::TemporaryRedirect(url, cache_control)
+        }
+        RedirectStatus::PermanentRedirect => {
+            RedirectResponse::PermanentRedirect(url, cache_control)
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn finish_redirect(
+    database: &QrCodeDatabase,
+    resolver: &DestinationResolver,
+    titles: &TitleFetcher,
+    cookie_jar: &CookieJar,
+    request: &Request,
+    client: &ClientInfo,
+    qr_code: Model,
+) -> RedirectResponse {
+    let preferred = qr_code
+        .sticky_variants
+        .then(|| preferred_variant(cookie_jar, qr_code.id))
+        .flatten();
+
+    let preview = preview_param(request).unwrap_or(qr_code.preview);
+    let signed = match preview {
+        true => None,
+        false => signed_destination(resolver, request, qr_code.id),
+    };
+    let destination = match signed {
+        Some(destination) => destination,
+        None => match resolver.resolve(&qr_code, client, preferred).await {
+            Ok(destination) => destination,
+            Err(why) => {
+                error!("Could not resolve the destination because of {why}");
+                return RedirectResponse::DatabaseError(PlainText(
+                    "The redirection failed because of an internal error.".to_string(),
+                ));
+            }
+        },
+    };
+
+    let stored_url = match url::Url::parse(&destination.link) {
+        Ok(url) => url,
+        Err(why) => {
+            error!("Could not redirect user because of an malformed url, {why}");
+            return fallback_or(
+                resolver,
+                &qr_code,
+                RedirectResponse::InvalidUrl(PlainText("The redirect url is broken.".to_string())),
+            );
+        }
+    };
+
+    if let Err(why) = resolver.link_policy.check(&stored_url) {
+        warn!(
+            "Refused to redirect qr code {} to {stored_url}, {why}",
+            qr_code.id
+        );
+        return fallback_or(
+            resolver,
+            &qr_code,
+            RedirectResponse::Blocked(PlainText(
+                "The destination of this qr code has been blocked.".to_string(),
+            )),
+        );
+    }
+
+    let mut url = stored_url.clone();
+    QueryParams::from_model(&qr_code).apply(&mut url, &scanned_query(request));
+
+    let permanent = matches!(
+        qr_code.redirect_status,
+        RedirectStatus::MovedPermanently | RedirectStatus::PermanentRedirect
+    );
+    let cacheable = (permanent && qr_code.immutable) || qr_code.cache_max_age.is_some();
+    // Uncacheable redirects are answered the same way for static and dynamic codes, so the
+    // extra lookups are only needed when caching is configured.
+    let dynamic = if cacheable {
+        match resolver.is_dynamic(&qr_code).await {
+            Ok(dynamic) => dynamic,
+            Err(why) => {
+                error!("Could not inspect the qr code because of {why}");
+                return RedirectResponse::DatabaseError(PlainText(
+                    "The redirection failed because of an internal error.".to_string(),
+                ));
+            }
+        }
+    } else {
+        true
+    };
+    let policy = RedirectPolicy::new(
+        qr_code.redirect_status,
+        qr_code.cache_max_age,
+        qr_code.immutable,
+        dynamic,
+    );
+
+    if preview {
+        if qr_code.max_scans.is_some_and(|x| qr_code.scan_count >= x) {
+            return fallback_or(
+                resolver,
+                &qr_code,
+                RedirectResponse::Exhausted(PlainText(
+                    "This qr code has reached its maximum number of scans.".to_string(),
+                )),
+            );
+        }
+
+        let domain = url.host_str().unwrap_or_default().to_string();
+        let title = match qr_code.preview_title {
+            Some(title) => Some(title),
+            None => titles.title(&stored_url),
+        };
+        return RedirectResponse::Preview(
+            Html(preview_page(
+                url.as_str(),
+                &domain,
+                title.as_deref(),
+                &continue_url(resolver, request, qr_code.id, &destination),
+            )),
+            "no-store".to_string(),
+        );
+    }
+
+    match database
+        .register_scan(qr_code.id, destination.variant_id)
+        .await
+    {
+        Ok(ScanOutcome::Counted) => {
+            if let (true, Some(variant_id)) = (qr_code.sticky_variants, destination.variant_id) {
+                set_variant_cookie(cookie_jar, qr_code.id, variant_id);
+            }
+
+            redirect_to(url, policy)
+        }
+        Ok(ScanOutcome::NotFound) => RedirectResponse::NotFound(PlainText(
+            "The requested qr code id could not be found.".to_string(),
+        )),
+        Ok(ScanOutcome::Exhausted) => fallback_or(
+            resolver,
+            &qr_code,
+            RedirectResponse::Exhausted(PlainText(
+                "This qr code has reached its maximum number of scans.".to_string(),
+            )),
+        ),
+        Err(why) => {
+            error!("Could not register scan because of {why}");
+            RedirectResponse::DatabaseError(PlainText(
+                "The redirection failed because of an internal error.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Shows the unlock page for protected codes, otherwise redirects right away.
+///
+/// `unlock_query` is the query the unlock form posts back to `/api/redirect`.
+#[allow(clippy::too_many_arguments)]
+async fn start_redirect(
+    database: &QrCodeDatabase,
+    resolver: &DestinationResolver,
+    titles: &TitleFetcher,
+    config: &AppConfig,
+    cookie_jar: &CookieJar,
+    request: &Request,
+    qr_code: Model,
+    unlock_query: &str,
+) -> RedirectResponse {
+    if let Some(response) = inactive_response(resolver, &qr_code) {
+        return response;
+    }
+
+    if let Some(password_hash) = &qr_code.access_password_hash
+        && !is_unlocked(cookie_jar, qr_code.id, password_hash)
+    {
+        return RedirectResponse::Locked(Html(unlock_page(unlock_query, None)));
+    }
+
+    let client = client_info(request, config);
+    finish_redirect(
+        database, resolver, titles, cookie_jar, request, &client, qr_code,
+    )
+    .await
+}
+
+/// Serves the short links encoded into the qr code images, `/r/{slug}`.
+#[handler]
+pub async fn short_redirect(
+    Data(database): Data<&QrCodeDatabase>,
+    Data(resolver): Data<&DestinationResolver>,
+    Data(titles): Data<&TitleFetcher>,
+    Data(config): Data<&AppConfig>,
+    cookie_jar: &CookieJar,
+    request: &Request,
+    Path(slug): Path<String>,
+) -> RedirectResponse {
+    let qr_code = match database.find_by_slug(&slug).await {
+        Ok(Some(qr_code)) => qr_code,
+        Ok(None) => {
+            return RedirectResponse::NotFound(PlainText(
+                "The requested qr code could not be found.".to_string(),
+            ));
+        }
+        Err(why) => {
+            error!("Could not redirect user because of {why}");
+            return RedirectResponse::DatabaseError(PlainText(
+                "The redirection failed because of an internal error.".to_string(),
+            ));
+        }
+    };
+
+    let unlock_query = match request.uri().query() {
+        Some(query) if !query.is_empty() => format!("id={}&{query}", qr_code.id),
+        _ => format!("id={}", qr_code.id),
+    };
+
+    start_redirect(
+        database,
+        resolver,
+        titles,
+        config,
+        cookie_jar,
+        request,
+        qr_code,
+        &unlock_query,
+    )
+    .await
+}
+
+fn too_many_attempts(query: &str, retry_after: std::time::Duration) -> RedirectResponse {
+    RedirectResponse::TooManyAttempts(
+        Html(unlock_page(
+            query,
+            Some("Too many wrong attempts, please try again later."),
+        )),
+        retry_after.as_secs().max(1),
+    )
+}
+
 pub struct RedirectApi;
 
 #[OpenApi]
 impl RedirectApi {
+    #[allow(clippy::too_many_arguments)]
     #[oai(path = "/redirect", method = "get", tag = "ApiTags::Redirect")]
     async fn redirect(
         &self,
         Data(database): Data<&QrCodeDatabase>,
+        Data(resolver): Data<&DestinationResolver>,
+        Data(titles): Data<&TitleFetcher>,
+        Data(config): Data<&AppConfig>,
+        cookie_jar: &CookieJar,
+        request: &Request,
         Query(id): Query<Uuid>,
     ) -> RedirectResponse {
-        match database
-            .get(id)
-            .await
-            .map(|x| x.map(|y| url::Url::parse(&y.link)))
-        {
-            Ok(Some(Ok(url))) => RedirectResponse::Redirect(url),
-            Ok(Some(Err(why))) => {
-                error!("Could not redirect user because of an malformed url, {why}");
-                return RedirectResponse::InvalidUrl(PlainText(
-                    "The redirect url is broken.".to_string(),
-                ));
-            }
-            Ok(None) => RedirectResponse::NotFound(PlainText(
-                "The requested qr code id could not be found.".to_string(),
-            )),
-            Err(why) => {
-                error!("Could not redirect user because of {why}");
-                return RedirectResponse::DatabaseError(PlainText(
-                    "The redirection failed because of an internal error.".to_string(),
-                ));
-            }
+        let qr_code = match find_qr_code(database, id).await {
+            Ok(qr_code) => qr_code,
+            Err(response) => return response,
+        };
+
+        start_redirect(
+            database,
+            resolver,
+            titles,
+            config,
+            cookie_jar,
+            request,
+            qr_code,
+            request.uri().query().unwrap_or_default(),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(path = "/redirect", method = "post", tag = "ApiTags::Redirect")]
+    async fn unlock(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(resolver): Data<&DestinationResolver>,
+        Data(titles): Data<&TitleFetcher>,
+        Data(throttle): Data<&AttemptThrottle>,
+        Data(config): Data<&AppConfig>,
+        cookie_jar: &CookieJar,
+        request: &Request,
+        Query(id): Query<Uuid>,
+        Form(form): Form<UnlockForm>,
+    ) -> RedirectResponse {
+        let client = client_info(request, config);
+        let qr_code = match find_qr_code(database, id).await {
+            Ok(qr_code) => qr_code,
+            Err(response) => return response,
+        };
+
+        if let Some(response) = inactive_response(resolver, &qr_code) {
+            return response;
+        }
+
+        let Some(password_hash) = &qr_code.access_password_hash else {
+            return finish_redirect(
+                database, resolver, titles, cookie_jar, request, &client, qr_code,
+            )
+            .await;
+        };
+
+        let query = request.uri().query().unwrap_or_default();
+        let client_ip = client_ip(
+            request.remote_addr().as_socket_addr().map(|x| x.ip()),
+            request.header("X-Forwarded-For"),
+            &config.trusted_proxies,
+        )
+        .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+        // Only the client guessing is locked out, a lock on the code alone would let anyone
+        // keep its visitors out.
+        let key = format!("unlock:{id}:{client_ip}");
+
+        if let Some(retry_after) = throttle.check(&key) {
+            return too_many_attempts(query, retry_after);
+        }
+
+        if !verify_password(&form.pin, password_hash) {
+            warn!(target: "security", qr_code = %id, client_ip = %client_ip, "Wrong pin");
+
+            return match throttle.record_failure(&key) {
+                Some(retry_after) => too_many_attempts(query, retry_after),
+                None => RedirectResponse::Locked(Html(unlock_page(query, Some("Wrong PIN.")))),
+            };
         }
+
+        throttle.reset(&key);
+        set_unlock_cookie(cookie_jar, id, password_hash, config.unlock_ttl_minutes);
+
+        finish_redirect(
+            database, resolver, titles, cookie_jar, request, &client, qr_code,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use service::{AuditContext, Credential, QrCodeOptions, UserDatabase};
+
+    use super::*;
+    use crate::testing::database;
+
+    #[tokio::test]
+    async fn paused_codes_are_unavailable_until_resumed() {
+        let db_conn = database().await;
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let codes = QrCodeDatabase { db_conn };
+        let owner = users.register("a@example.com", "password").await.unwrap();
+        let context = AuditContext::default();
+        let options = QrCodeOptions {
+            owner_id: Some(owner.id),
+            ..Default::default()
+        };
+        let (qr_code, _) = codes
+            .create(
+                Url::parse("https://example.com").unwrap(),
+                options,
+                &context,
+            )
+            .await
+            .unwrap();
+        let id = qr_code.id;
+        let resolver = DestinationResolver::default();
+        assert!(inactive_response(&resolver, &qr_code).is_none());
+
+        let paused = codes
+            .set_active(id, Credential::User(owner.id), false, &context)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            inactive_response(&resolver, &paused),
+            Some(RedirectResponse::Unavailable(_))
+        ));
+
+        let stranger = Credential::User(Uuid::new_v4());
+        let resumed = codes
+            .set_active(id, stranger, true, &context)
+            .await
+            .unwrap();
+        assert!(resumed.is_none());
+        assert!(!codes.get(id).await.unwrap().unwrap().active);
+
+        let resumed = codes
+            .set_active(id, Credential::User(owner.id), true, &context)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(inactive_response(&resolver, &resumed).is_none());
+    }
+
+    #[tokio::test]
+    async fn expired_and_paused_codes_send_visitors_to_the_fallback() {
+        let db_conn = database().await;
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let codes = QrCodeDatabase { db_conn };
+        let owner = users.register("a@example.com", "password").await.unwrap();
+        let context = AuditContext::default();
+        let fallback = Url::parse("https://example.org/fallback").unwrap();
+        let options = QrCodeOptions {
+            owner_id: Some(owner.id),
+            fallback_link: Some(fallback.clone()),
+            expires_at: Some(Utc::now() - Duration::minutes(1)),
+            ..Default::default()
+        };
+        let link = Url::parse("https://example.com").unwrap();
+        let (qr_code, _) = codes.create(link.clone(), options, &context).await.unwrap();
+        let id = qr_code.id;
+        let resolver = DestinationResolver::default();
+        let update = |options| {
+            codes.update(
+                id,
+                Credential::User(owner.id),
+                link.clone(),
+                options,
+                &context,
+            )
+        };
+
+        let expired = inactive_response(&resolver, &qr_code);
+        assert!(matches!(expired, Some(RedirectResponse::Found(url, _)) if url == fallback));
+
+        let paused = codes
+            .set_active(id, Credential::User(owner.id), false, &context)
+            .await
+            .unwrap()
+            .unwrap();
+        let paused = inactive_response(&resolver, &paused);
+        assert!(matches!(paused, Some(RedirectResponse::Found(url, _)) if url == fallback));
+
+        codes
+            .set_active(id, Credential::User(owner.id), true, &context)
+            .await
+            .unwrap()
+            .unwrap();
+        let options = QrCodeOptions {
+            clear_fallback_link: true,
+            ..Default::default()
+        };
+        let qr_code = update(options).await.unwrap().unwrap();
+        assert!(qr_code.fallback_link.is_none());
+        assert!(matches!(
+            inactive_response(&resolver, &qr_code),
+            Some(RedirectResponse::Expired(_))
+        ));
+
+        let options = QrCodeOptions {
+            clear_expires_at: true,
+            ..Default::default()
+        };
+        let qr_code = update(options).await.unwrap().unwrap();
+        assert!(qr_code.expires_at.is_none());
+        assert!(inactive_response(&resolver, &qr_code).is_none());
     }
 }
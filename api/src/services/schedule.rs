@@ -0,0 +1,458 @@
+use chrono::{DateTime, Utc};
+use poem::web::Data;
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::{Header, Path},
+    payload::{Json, PlainText},
+};
+use service::{
+    Action, Credential, LinkPolicy, QrCodeDatabase, QrCodeDatabaseError, ScheduleDatabase,
+};
+use tracing::error;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    api_key::{ApiKeyAuth, Read, Write},
+    services::{
+        ApiTags,
+        passphrase::{
+            MISSING_PASSPHRASE, PassphraseBody, credential, deprecated_path_passphrase, passphrase,
+            throttle_passphrase,
+        },
+    },
+    session::CurrentUser,
+};
+
+#[derive(Object, Debug)]
+struct ScheduleEntryRequest {
+    pub link: Url,
+    pub starts_at: DateTime<Utc>,
+    pub password: Option<String>,
+}
+
+#[derive(Object, Debug)]
+pub struct ScheduleEntryResponse {
+    pub id: Uuid,
+    pub link: String,
+    pub starts_at: DateTime<Utc>,
+}
+
+impl From<entity::destination_schedule::Model> for ScheduleEntryResponse {
+    fn from(value: entity::destination_schedule::Model) -> Self {
+        Self {
+            id: value.id,
+            link: value.link,
+            starts_at: value.starts_at,
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum ScheduleListResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<ScheduleEntryResponse>>),
+
+    #[oai(status = 400)]
+    MissingPassphrase(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum ScheduleJsonResponse {
+    #[oai(status = 200)]
+    Ok(Json<ScheduleEntryResponse>),
+
+    #[oai(status = 201)]
+    Created(Json<ScheduleEntryResponse>),
+
+    #[oai(status = 400)]
+    Rejected(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum ScheduleDeleteResponse {
+    #[oai(status = 200)]
+    Ok,
+
+    #[oai(status = 400)]
+    MissingPassphrase(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+async fn delete_schedule_entry(
+    schedule: &ScheduleDatabase,
+    id: Uuid,
+    entry_id: Uuid,
+    credential: Credential,
+) -> ScheduleDeleteResponse {
+    match schedule.delete(id, entry_id, credential).await {
+        Ok(Some(_)) => ScheduleDeleteResponse::Ok,
+        Ok(None) => ScheduleDeleteResponse::NotFound(PlainText(
+            "No schedule entry could be found with this id.".to_string(),
+        )),
+        Err(QrCodeDatabaseError::Immutable) => ScheduleDeleteResponse::Conflict(PlainText(
+            "Immutable qr codes can't be edited.".to_string(),
+        )),
+        Err(why) => {
+            error!("Failed to delete schedule entry {entry_id}, {why}");
+            ScheduleDeleteResponse::InternalError(PlainText(
+                "Could not delete the schedule entry, because of an internal error.".to_string(),
+            ))
+        }
+    }
+}
+
+pub struct ScheduleApi;
+
+#[OpenApi]
+impl ScheduleApi {
+    /// Lists upcoming and past links, only to those who may view the code since the
+    /// upcoming ones aren't public yet.
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/schedule",
+        method = "get",
+        tag = "ApiTags::Schedule",
+        transform = "throttle_passphrase"
+    )]
+    async fn list(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(schedule): Data<&ScheduleDatabase>,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
+    ) -> ScheduleListResponse {
+        let Some(credential) = credential(passphrase_header.0, auth.or(current_user)) else {
+            return ScheduleListResponse::MissingPassphrase(PlainText(
+                MISSING_PASSPHRASE.to_string(),
+            ));
+        };
+
+        match database.get_authorized(id, &credential, Action::View).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return ScheduleListResponse::NotFound(PlainText(
+                    "No qr code could be found for this id.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to look up qr code {id}, {why}");
+                return ScheduleListResponse::InternalError(PlainText(
+                    "Could not retrieve the schedule, because of an internal error.".to_string(),
+                ));
+            }
+        }
+
+        match schedule.list(id).await {
+            Ok(entries) => ScheduleListResponse::Ok(Json(
+                entries
+                    .into_iter()
+                    .map(ScheduleEntryResponse::from)
+                    .collect(),
+            )),
+            Err(why) => {
+                error!("Failed to list schedule of qr code {id}, {why}");
+                ScheduleListResponse::InternalError(PlainText(
+                    "Could not retrieve the schedule, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/schedule",
+        method = "post",
+        tag = "ApiTags::Schedule",
+        transform = "throttle_passphrase"
+    )]
+    async fn create(
+        &self,
+        Data(schedule): Data<&ScheduleDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ScheduleEntryRequest>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> ScheduleJsonResponse {
+        if let Err(why) = link_policy.check(&request.link) {
+            return ScheduleJsonResponse::Rejected(PlainText(format!(
+                "The link was rejected, {why}."
+            )));
+        }
+
+        let Some(credential) = credential(
+            passphrase(passphrase_header.0, request.password),
+            auth.or(current_user),
+        ) else {
+            return ScheduleJsonResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+
+        match schedule
+            .create(id, credential, request.link, request.starts_at)
+            .await
+        {
+            Ok(Some(entry)) => ScheduleJsonResponse::Created(Json(entry.into())),
+            Ok(None) => ScheduleJsonResponse::NotFound(PlainText(
+                "No qr code could be found for this id.".to_string(),
+            )),
+            Err(QrCodeDatabaseError::Immutable) => ScheduleJsonResponse::Conflict(PlainText(
+                "Immutable qr codes can't be edited.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to create schedule entry for qr code {id}, {why}");
+                ScheduleJsonResponse::InternalError(PlainText(
+                    "Could not create the schedule entry, because of an internal error."
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/schedule/:entry_id",
+        method = "put",
+        tag = "ApiTags::Schedule",
+        transform = "throttle_passphrase"
+    )]
+    async fn update(
+        &self,
+        Data(schedule): Data<&ScheduleDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
+        Path(id): Path<Uuid>,
+        Path(entry_id): Path<Uuid>,
+        Json(request): Json<ScheduleEntryRequest>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> ScheduleJsonResponse {
+        if let Err(why) = link_policy.check(&request.link) {
+            return ScheduleJsonResponse::Rejected(PlainText(format!(
+                "The link was rejected, {why}."
+            )));
+        }
+
+        let Some(credential) = credential(
+            passphrase(passphrase_header.0, request.password),
+            auth.or(current_user),
+        ) else {
+            return ScheduleJsonResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+
+        match schedule
+            .update(id, entry_id, credential, request.link, request.starts_at)
+            .await
+        {
+            Ok(Some(entry)) => ScheduleJsonResponse::Ok(Json(entry.into())),
+            Ok(None) => ScheduleJsonResponse::NotFound(PlainText(
+                "No schedule entry could be found for this id.".to_string(),
+            )),
+            Err(QrCodeDatabaseError::Immutable) => ScheduleJsonResponse::Conflict(PlainText(
+                "Immutable qr codes can't be edited.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to update schedule entry {entry_id}, {why}");
+                ScheduleJsonResponse::InternalError(PlainText(
+                    "Could not update the schedule entry, because of an internal error."
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/schedule/:entry_id",
+        method = "delete",
+        tag = "ApiTags::Schedule",
+        transform = "throttle_passphrase"
+    )]
+    async fn delete(
+        &self,
+        Data(schedule): Data<&ScheduleDatabase>,
+        Path(id): Path<Uuid>,
+        Path(entry_id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> ScheduleDeleteResponse {
+        match credential(passphrase(passphrase_header.0, body), auth.or(current_user)) {
+            Some(credential) => delete_schedule_entry(schedule, id, entry_id, credential).await,
+            None => {
+                ScheduleDeleteResponse::MissingPassphrase(PlainText(MISSING_PASSPHRASE.to_string()))
+            }
+        }
+    }
+
+    #[oai(
+        path = "/qr/:id/schedule/:entry_id/:pass",
+        method = "delete",
+        tag = "ApiTags::Schedule",
+        deprecated,
+        transform = "deprecated_path_passphrase"
+    )]
+    async fn delete_with_path_passphrase(
+        &self,
+        Data(schedule): Data<&ScheduleDatabase>,
+        Path(id): Path<Uuid>,
+        Path(entry_id): Path<Uuid>,
+        Path(password): Path<String>,
+    ) -> ScheduleDeleteResponse {
+        delete_schedule_entry(schedule, id, entry_id, Credential::Passphrase(password)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poem::{
+        Endpoint, EndpointExt, Request, Route,
+        http::{Method, StatusCode},
+        middleware::CookieJarManager,
+    };
+    use poem_openapi::OpenApiService;
+    use service::{ApiKeyDatabase, ApiKeyScope, AuditContext, QrCodeOptions, UserDatabase};
+
+    use super::*;
+    use crate::{services::QrCodeApi, testing::database};
+
+    #[tokio::test]
+    async fn write_keys_edit_schedules_and_delete_codes() {
+        let db_conn = database().await;
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let api_keys = ApiKeyDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let database = QrCodeDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let user = users.register("a@example.com", "password").await.unwrap();
+        let (_, token) = api_keys
+            .create(user.id, "ci".to_string(), &[ApiKeyScope::Write], None)
+            .await
+            .unwrap();
+        let options = QrCodeOptions {
+            owner_id: Some(user.id),
+            ..Default::default()
+        };
+        let (qr_code, _) = database
+            .create(
+                Url::parse("https://example.com").unwrap(),
+                options,
+                &AuditContext::default(),
+            )
+            .await
+            .unwrap();
+        let id = qr_code.id;
+
+        let app = Route::new()
+            .nest(
+                "/api",
+                OpenApiService::new((QrCodeApi, ScheduleApi), "qrcode", "1.0"),
+            )
+            .with(CookieJarManager::new())
+            .data(database)
+            .data(ScheduleDatabase {
+                db_conn: db_conn.clone(),
+            })
+            .data(LinkPolicy::default())
+            .data(users)
+            .data(api_keys);
+        let request = |method: Method, uri: String, body: &str, key: Option<&str>| {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri.parse().unwrap())
+                .content_type("application/json");
+            if let Some(key) = key {
+                request = request.header("Authorization", format!("Bearer {key}"));
+            }
+            request.body(body.to_string())
+        };
+        let entry = r#"{"link": "https://example.org", "starts_at": "2030-01-01T00:00:00Z"}"#;
+
+        let response = app
+            .get_response(request(
+                Method::POST,
+                format!("/api/qr/{id}/schedule"),
+                entry,
+                None,
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .get_response(request(
+                Method::POST,
+                format!("/api/qr/{id}/schedule"),
+                entry,
+                Some(&token),
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().into_string().await.unwrap();
+        let entry_id = serde_json::from_str::<serde_json::Value>(&body).unwrap()["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let response = app
+            .get_response(request(
+                Method::PUT,
+                format!("/api/qr/{id}/schedule/{entry_id}"),
+                entry,
+                Some(&token),
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .get_response(request(
+                Method::DELETE,
+                format!("/api/qr/{id}/schedule/{entry_id}"),
+                "",
+                Some(&token),
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The path segment is ignored once the api key identifies the owner.
+        let response = app
+            .get_response(request(
+                Method::DELETE,
+                format!("/api/qr/{id}/not-the-passphrase"),
+                "",
+                Some(&token),
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
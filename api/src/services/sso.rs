@@ -0,0 +1,167 @@
+use poem::web::{
+    Data,
+    cookie::{Cookie, CookieJar, SameSite},
+};
+use poem_openapi::{ApiResponse, OpenApi, param::Query, payload::PlainText};
+use service::{OidcClient, OidcLogin, UserDatabase, UserError};
+use tracing::{error, warn};
+
+use crate::{config::AppConfig, services::ApiTags, session::set_session_cookie};
+
+const LOGIN_COOKIE: &str = "qr_oidc_login";
+const LOGIN_COOKIE_PATH: &str = "/api/account/oidc";
+const LOGIN_COOKIE_MAX_AGE: u64 = 10 * 60;
+
+#[derive(ApiResponse)]
+enum SsoResponse {
+    #[oai(status = 302)]
+    Found(#[oai(header = "Location")] String),
+
+    #[oai(status = 400)]
+    Rejected(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotConfigured(PlainText<String>),
+
+    #[oai(status = 409)]
+    Unverified(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+
+    #[oai(status = 502)]
+    ProviderError(PlainText<String>),
+}
+
+const NOT_CONFIGURED: &str = "Single sign-on is not configured.";
+
+fn set_login_cookie(cookie_jar: &CookieJar, login: &OidcLogin) {
+    let mut cookie = Cookie::new(LOGIN_COOKIE, login);
+    cookie.set_path(LOGIN_COOKIE_PATH);
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_max_age(std::time::Duration::from_secs(LOGIN_COOKIE_MAX_AGE));
+
+    cookie_jar.private().add(cookie);
+}
+
+/// Takes the pending login out of its cookie, so each one can only be completed once.
+fn take_login_cookie(cookie_jar: &CookieJar) -> Option<OidcLogin> {
+    let login = cookie_jar
+        .private()
+        .get(LOGIN_COOKIE)
+        .and_then(|cookie| cookie.value::<OidcLogin>().ok());
+
+    let mut cookie = Cookie::named(LOGIN_COOKIE);
+    cookie.set_path(LOGIN_COOKIE_PATH);
+    cookie.make_removal();
+    cookie_jar.add(cookie);
+
+    login
+}
+
+pub struct SsoApi;
+
+#[OpenApi]
+impl SsoApi {
+    /// Redirects to the identity provider to log in with single sign-on.
+    #[oai(path = "/account/oidc/login", method = "get", tag = "ApiTags::Account")]
+    async fn login(
+        &self,
+        Data(oidc): Data<&Option<OidcClient>>,
+        cookie_jar: &CookieJar,
+    ) -> SsoResponse {
+        let Some(oidc) = oidc else {
+            return SsoResponse::NotConfigured(PlainText(NOT_CONFIGURED.to_string()));
+        };
+
+        match oidc.authorization_url().await {
+            Ok((url, login)) => {
+                set_login_cookie(cookie_jar, &login);
+                SsoResponse::Found(url.to_string())
+            }
+            Err(why) => {
+                error!("Failed to discover the identity provider, {why}");
+                SsoResponse::ProviderError(PlainText(
+                    "Could not reach the identity provider.".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// The identity provider redirects here after the login, the user is logged in with
+    /// the account of their email and sent to the account page.
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/account/oidc/callback",
+        method = "get",
+        tag = "ApiTags::Account"
+    )]
+    async fn callback(
+        &self,
+        Data(oidc): Data<&Option<OidcClient>>,
+        Data(users): Data<&UserDatabase>,
+        Data(config): Data<&AppConfig>,
+        cookie_jar: &CookieJar,
+        Query(code): Query<Option<String>>,
+        Query(state): Query<Option<String>>,
+        Query(error): Query<Option<String>>,
+    ) -> SsoResponse {
+        let Some(oidc) = oidc else {
+            return SsoResponse::NotConfigured(PlainText(NOT_CONFIGURED.to_string()));
+        };
+        let login = take_login_cookie(cookie_jar);
+
+        if let Some(error) = error {
+            return SsoResponse::Rejected(PlainText(format!(
+                "The identity provider refused the login, {error}."
+            )));
+        }
+        let (Some(code), Some(login)) = (code, login.filter(|x| Some(&x.state) == state.as_ref()))
+        else {
+            return SsoResponse::Rejected(PlainText(
+                "The login expired or was started in another browser, please try again."
+                    .to_string(),
+            ));
+        };
+
+        let identity = match oidc.exchange(&code, &login).await {
+            Ok(identity) => identity,
+            Err(why) => {
+                warn!("Single sign-on failed, {why}");
+                return SsoResponse::ProviderError(PlainText(
+                    "The identity provider could not confirm the login.".to_string(),
+                ));
+            }
+        };
+
+        let user = match users.login_with_identity(&identity).await {
+            Ok(user) => user,
+            Err(UserError::Unverified) => {
+                return SsoResponse::Unverified(PlainText(
+                    "The identity provider did not verify the email.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to look up the user of {}, {why}", identity.subject);
+                return SsoResponse::InternalError(PlainText(
+                    "Could not log in, because of an internal error.".to_string(),
+                ));
+            }
+        };
+
+        let ttl = chrono::Duration::days(config.session_ttl_days);
+        match users.create_session(user.id, ttl).await {
+            Ok(token) => {
+                set_session_cookie(cookie_jar, token, ttl);
+                SsoResponse::Found("/account".to_string())
+            }
+            Err(why) => {
+                error!("Failed to create session for user {}, {why}", user.id);
+                SsoResponse::InternalError(PlainText(
+                    "Could not log in, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use poem::web::{Data, cookie::CookieJar};
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::{Header, Path},
+    payload::{Json, PlainText},
+};
+use service::{QrCodeDatabase, VariantDatabase};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    api_key::{ApiKeyAuth, Stats},
+    services::{
+        ApiTags,
+        passphrase::{credential, throttle_passphrase},
+        readable::find_readable,
+    },
+    session::CurrentUser,
+};
+
+#[derive(Object, Debug)]
+pub struct VariantStatsResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// Hidden for codes with an access password, unless the caller manages the code or
+    /// unlocked it.
+    pub link: Option<String>,
+    pub weight: i32,
+    pub scans: i64,
+}
+
+#[derive(Object, Debug)]
+pub struct QrCodeStatsResponse {
+    pub id: Uuid,
+    pub scan_count: i32,
+    pub max_scans: Option<i32>,
+    pub unattributed_scans: i64,
+    pub variants: Vec<VariantStatsResponse>,
+}
+
+#[derive(ApiResponse)]
+enum StatsResponse {
+    #[oai(status = 200)]
+    Ok(Json<QrCodeStatsResponse>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+pub struct StatsApi;
+
+#[OpenApi]
+impl StatsApi {
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/stats",
+        method = "get",
+        tag = "ApiTags::Stats",
+        transform = "throttle_passphrase"
+    )]
+    async fn stats(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(variants): Data<&VariantDatabase>,
+        cookie_jar: &CookieJar,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Stats>,
+    ) -> StatsResponse {
+        let credential = credential(passphrase_header.0, auth.or(current_user));
+        let readable = match find_readable(database, cookie_jar, id, credential).await {
+            Ok(Some(readable)) => readable,
+            Ok(None) => {
+                return StatsResponse::NotFound(PlainText(
+                    "No qr code could be found for this id.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to load qr code {id} for stats, {why}");
+                return StatsResponse::InternalError(PlainText(
+                    "Could not retrieve the stats, because of an internal error.".to_string(),
+                ));
+            }
+        };
+
+        match variants.stats(id).await {
+            Ok((variant_stats, unattributed_scans)) => {
+                StatsResponse::Ok(Json(QrCodeStatsResponse {
+                    id,
+                    scan_count: readable.qr_code.scan_count,
+                    max_scans: readable.qr_code.max_scans,
+                    unattributed_scans,
+                    variants: variant_stats
+                        .into_iter()
+                        .map(|x| VariantStatsResponse {
+                            id: x.variant.id,
+                            name: x.variant.name,
+                            link: readable.link(x.variant.link),
+                            weight: x.variant.weight,
+                            scans: x.scans,
+                        })
+                        .collect(),
+                }))
+            }
+            Err(why) => {
+                error!("Failed to compute stats of qr code {id}, {why}");
+                StatsResponse::InternalError(PlainText(
+                    "Could not retrieve the stats, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use chrono::{DateTime, Utc};
+use entity::sea_orm_active_enums::{DeviceClass, TargetOs};
+use poem::web::{Data, cookie::CookieJar};
+use poem_openapi::{
+    ApiResponse, Enum, Object, OpenApi,
+    param::{Header, Path},
+    payload::{Json, PlainText},
+};
+use service::{
+    Credential, LinkPolicy, QrCodeDatabase, QrCodeDatabaseError, TargetingDatabase,
+    TargetingRuleData,
+};
+use tracing::error;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    api_key::{ApiKeyAuth, Read, Write},
+    services::{
+        ApiTags,
+        passphrase::{
+            MISSING_PASSPHRASE, PassphraseBody, credential, deprecated_path_passphrase, passphrase,
+            throttle_passphrase,
+        },
+        readable::find_readable,
+    },
+    session::CurrentUser,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+pub enum RuleOs {
+    Ios,
+    Android,
+    Windows,
+    MacOs,
+    Linux,
+    ChromeOs,
+}
+
+impl From<RuleOs> for TargetOs {
+    fn from(value: RuleOs) -> Self {
+        match value {
+            RuleOs::Ios => TargetOs::Ios,
+            RuleOs::Android => TargetOs::Android,
+            RuleOs::Windows => TargetOs::Windows,
+            RuleOs::MacOs => TargetOs::MacOs,
+            RuleOs::Linux => TargetOs::Linux,
+            RuleOs::ChromeOs => TargetOs::ChromeOs,
+        }
+    }
+}
+
+impl From<TargetOs> for RuleOs {
+    fn from(value: TargetOs) -> Self {
+        match value {
+            TargetOs::Ios => RuleOs::Ios,
+            TargetOs::Android => RuleOs::Android,
+            TargetOs::Windows => RuleOs::Windows,
+            TargetOs::MacOs => RuleOs::MacOs,
+            TargetOs::Linux => RuleOs::Linux,
+            TargetOs::ChromeOs => RuleOs::ChromeOs,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+pub enum RuleDevice {
+    Mobile,
+    Tablet,
+    Desktop,
+}
+
+impl From<RuleDevice> for DeviceClass {
+    fn from(value: RuleDevice) -> Self {
+        match value {
+            RuleDevice::Mobile => DeviceClass::Mobile,
+            RuleDevice::Tablet => DeviceClass::Tablet,
+            RuleDevice::Desktop => DeviceClass::Desktop,
+        }
+    }
+}
+
+impl From<DeviceClass> for RuleDevice {
+    fn from(value: DeviceClass) -> Self {
+        match value {
+            DeviceClass::Mobile => RuleDevice::Mobile,
+            DeviceClass::Tablet => RuleDevice::Tablet,
+            DeviceClass::Desktop => RuleDevice::Desktop,
+        }
+    }
+}
+
+#[derive(Object, Debug)]
+struct TargetingRuleRequest {
+    pub position: i32,
+    pub os: Option<RuleOs>,
+    pub device: Option<RuleDevice>,
+    #[oai(validator(pattern = "^[A-Za-z]{2,8}$"))]
+    pub language: Option<String>,
+    #[oai(validator(pattern = "^[A-Za-z]{2}$"))]
+    pub country: Option<String>,
+    pub link: Url,
+    pub password: Option<String>,
+}
+
+impl From<TargetingRuleRequest> for TargetingRuleData {
+    fn from(value: TargetingRuleRequest) -> Self {
+        Self {
+            position: value.position,
+            os: value.os.map(Into::into),
+            device: value.device.map(Into::into),
+            language: value.language.map(|x| x.to_ascii_lowercase()),
+            country: value.country.map(|x| x.to_ascii_uppercase()),
+            link: value.link,
+        }
+    }
+}
+
+#[derive(Object, Debug)]
+pub struct TargetingRuleResponse {
+    pub id: Uuid,
+    pub position: i32,
+    pub os: Option<RuleOs>,
+    pub device: Option<RuleDevice>,
+    pub language: Option<String>,
+    pub country: Option<String>,
+    /// Hidden for codes with an access password, unless the caller manages the code or
+    /// unlocked it.
+    pub link: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::targeting_rule::Model> for TargetingRuleResponse {
+    fn from(value: entity::targeting_rule::Model) -> Self {
+        Self {
+            id: value.id,
+            position: value.position,
+            os: value.os.map(Into::into),
+            device: value.device.map(Into::into),
+            language: value.language,
+            country: value.country,
+            link: Some(value.link),
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum TargetingListResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<TargetingRuleResponse>>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum TargetingJsonResponse {
+    #[oai(status = 200)]
+    Ok(Json<TargetingRuleResponse>),
+
+    #[oai(status = 201)]
+    Created(Json<TargetingRuleResponse>),
+
+    #[oai(status = 400)]
+    Rejected(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum TargetingDeleteResponse {
+    #[oai(status = 200)]
+    Ok,
+
+    #[oai(status = 400)]
+    MissingPassphrase(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+async fn delete_targeting_rule(
+    targeting: &TargetingDatabase,
+    id: Uuid,
+    rule_id: Uuid,
+    credential: Credential,
+) -> TargetingDeleteResponse {
+    match targeting.delete(id, rule_id, credential).await {
+        Ok(Some(_)) => TargetingDeleteResponse::Ok,
+        Ok(None) => TargetingDeleteResponse::NotFound(PlainText(
+            "No targeting rule could be found with this id.".to_string(),
+        )),
+        Err(QrCodeDatabaseError::Immutable) => TargetingDeleteResponse::Conflict(PlainText(
+            "Immutable qr codes can't be edited.".to_string(),
+        )),
+        Err(why) => {
+            error!("Failed to delete targeting rule {rule_id}, {why}");
+            TargetingDeleteResponse::InternalError(PlainText(
+                "Could not delete the targeting rule, because of an internal error.".to_string(),
+            ))
+        }
+    }
+}
+
+pub struct TargetingApi;
+
+#[OpenApi]
+impl TargetingApi {
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/targeting",
+        method = "get",
+        tag = "ApiTags::Targeting",
+        transform = "throttle_passphrase"
+    )]
+    async fn list(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(targeting): Data<&TargetingDatabase>,
+        cookie_jar: &CookieJar,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
+    ) -> TargetingListResponse {
+        let credential = credential(passphrase_header.0, auth.or(current_user));
+        let readable = match find_readable(database, cookie_jar, id, credential).await {
+            Ok(Some(readable)) => readable,
+            Ok(None) => {
+                return TargetingListResponse::NotFound(PlainText(
+                    "No qr code could be found for this id.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to look up qr code {id}, {why}");
+                return TargetingListResponse::InternalError(PlainText(
+                    "Could not retrieve the targeting rules, because of an internal error."
+                        .to_string(),
+                ));
+            }
+        };
+
+        match targeting.list(id).await {
+            Ok(rules) => TargetingListResponse::Ok(Json(
+                rules
+                    .into_iter()
+                    .map(|x| TargetingRuleResponse {
+                        link: readable.link(x.link.clone()),
+                        ..x.into()
+                    })
+                    .collect(),
+            )),
+            Err(why) => {
+                error!("Failed to list targeting rules of qr code {id}, {why}");
+                TargetingListResponse::InternalError(PlainText(
+                    "Could not retrieve the targeting rules, because of an internal error."
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/targeting",
+        method = "post",
+        tag = "ApiTags::Targeting",
+        transform = "throttle_passphrase"
+    )]
+    async fn create(
+        &self,
+        Data(targeting): Data<&TargetingDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<TargetingRuleRequest>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> TargetingJsonResponse {
+        if let Err(why) = link_policy.check(&request.link) {
+            return TargetingJsonResponse::Rejected(PlainText(format!(
+                "The link was rejected, {why}."
+            )));
+        }
+
+        let Some(credential) = credential(
+            passphrase(passphrase_header.0, request.password.clone()),
+            auth.or(current_user),
+        ) else {
+            return TargetingJsonResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+
+        match targeting.create(id, credential, request.into()).await {
+            Ok(Some(rule)) => TargetingJsonResponse::Created(Json(rule.into())),
+            Ok(None) => TargetingJsonResponse::NotFound(PlainText(
+                "No qr code could be found for this id.".to_string(),
+            )),
+            Err(QrCodeDatabaseError::Immutable) => TargetingJsonResponse::Conflict(PlainText(
+                "Immutable qr codes can't be edited.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to create targeting rule for qr code {id}, {why}");
+                TargetingJsonResponse::InternalError(PlainText(
+                    "Could not create the targeting rule, because of an internal error."
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/targeting/:rule_id",
+        method = "put",
+        tag = "ApiTags::Targeting",
+        transform = "throttle_passphrase"
+    )]
+    async fn update(
+        &self,
+        Data(targeting): Data<&TargetingDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
+        Path(id): Path<Uuid>,
+        Path(rule_id): Path<Uuid>,
+        Json(request): Json<TargetingRuleRequest>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> TargetingJsonResponse {
+        if let Err(why) = link_policy.check(&request.link) {
+            return TargetingJsonResponse::Rejected(PlainText(format!(
+                "The link was rejected, {why}."
+            )));
+        }
+
+        let Some(credential) = credential(
+            passphrase(passphrase_header.0, request.password.clone()),
+            auth.or(current_user),
+        ) else {
+            return TargetingJsonResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+
+        match targeting
+            .update(id, rule_id, credential, request.into())
+            .await
+        {
+            Ok(Some(rule)) => TargetingJsonResponse::Ok(Json(rule.into())),
+            Ok(None) => TargetingJsonResponse::NotFound(PlainText(
+                "No targeting rule could be found for this id.".to_string(),
+            )),
+            Err(QrCodeDatabaseError::Immutable) => TargetingJsonResponse::Conflict(PlainText(
+                "Immutable qr codes can't be edited.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to update targeting rule {rule_id}, {why}");
+                TargetingJsonResponse::InternalError(PlainText(
+                    "Could not update the targeting rule, because of an internal error."
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/targeting/:rule_id",
+        method = "delete",
+        tag = "ApiTags::Targeting",
+        transform = "throttle_passphrase"
+    )]
+    async fn delete(
+        &self,
+        Data(targeting): Data<&TargetingDatabase>,
+        Path(id): Path<Uuid>,
+        Path(rule_id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> TargetingDeleteResponse {
+        match credential(passphrase(passphrase_header.0, body), auth.or(current_user)) {
+            Some(credential) => delete_targeting_rule(targeting, id, rule_id, credential).await,
+            None => TargetingDeleteResponse::MissingPassphrase(PlainText(
+                MISSING_PASSPHRASE.to_string(),
+            )),
+        }
+    }
+
+    #[oai(
+        path = "/qr/:id/targeting/:rule_id/:pass",
+        method = "delete",
+        tag = "ApiTags::Targeting",
+        deprecated,
+        transform = "deprecated_path_passphrase"
+    )]
+    async fn delete_with_path_passphrase(
+        &self,
+        Data(targeting): Data<&TargetingDatabase>,
+        Path(id): Path<Uuid>,
+        Path(rule_id): Path<Uuid>,
+        Path(password): Path<String>,
+    ) -> TargetingDeleteResponse {
+        delete_targeting_rule(targeting, id, rule_id, Credential::Passphrase(password)).await
+    }
+}
@@ -0,0 +1,338 @@
+use chrono::{DateTime, Utc};
+use poem::web::{Data, cookie::CookieJar};
+use poem_openapi::{
+    ApiResponse, Object, OpenApi,
+    param::{Header, Path},
+    payload::{Json, PlainText},
+};
+use service::{Credential, LinkPolicy, QrCodeDatabase, QrCodeDatabaseError, VariantDatabase};
+use tracing::error;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    api_key::{ApiKeyAuth, Read, Write},
+    services::{
+        ApiTags,
+        passphrase::{
+            MISSING_PASSPHRASE, PassphraseBody, credential, deprecated_path_passphrase, passphrase,
+            throttle_passphrase,
+        },
+        readable::find_readable,
+    },
+    session::CurrentUser,
+};
+
+#[derive(Object, Debug)]
+struct VariantRequest {
+    #[oai(validator(min_length = 1, max_length = 64))]
+    pub name: String,
+    pub link: Url,
+    #[oai(validator(minimum(value = "1"), maximum(value = "10000")))]
+    pub weight: i32,
+    pub password: Option<String>,
+}
+
+#[derive(Object, Debug)]
+pub struct VariantResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// Hidden for codes with an access password, unless the caller manages the code or
+    /// unlocked it.
+    pub link: Option<String>,
+    pub weight: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::destination_variant::Model> for VariantResponse {
+    fn from(value: entity::destination_variant::Model) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            link: Some(value.link),
+            weight: value.weight,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum VariantListResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<VariantResponse>>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum VariantJsonResponse {
+    #[oai(status = 200)]
+    Ok(Json<VariantResponse>),
+
+    #[oai(status = 201)]
+    Created(Json<VariantResponse>),
+
+    #[oai(status = 400)]
+    Rejected(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum VariantDeleteResponse {
+    #[oai(status = 200)]
+    Ok,
+
+    #[oai(status = 400)]
+    MissingPassphrase(PlainText<String>),
+
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+
+    #[oai(status = 500)]
+    InternalError(PlainText<String>),
+}
+
+async fn delete_variant(
+    variants: &VariantDatabase,
+    id: Uuid,
+    variant_id: Uuid,
+    credential: Credential,
+) -> VariantDeleteResponse {
+    match variants.delete(id, variant_id, credential).await {
+        Ok(Some(_)) => VariantDeleteResponse::Ok,
+        Ok(None) => VariantDeleteResponse::NotFound(PlainText(
+            "No variant could be found with this id.".to_string(),
+        )),
+        Err(QrCodeDatabaseError::Immutable) => VariantDeleteResponse::Conflict(PlainText(
+            "Immutable qr codes can't be edited.".to_string(),
+        )),
+        Err(why) => {
+            error!("Failed to delete variant {variant_id}, {why}");
+            VariantDeleteResponse::InternalError(PlainText(
+                "Could not delete the variant, because of an internal error.".to_string(),
+            ))
+        }
+    }
+}
+
+pub struct VariantApi;
+
+#[OpenApi]
+impl VariantApi {
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/variants",
+        method = "get",
+        tag = "ApiTags::Variant",
+        transform = "throttle_passphrase"
+    )]
+    async fn list(
+        &self,
+        Data(database): Data<&QrCodeDatabase>,
+        Data(variants): Data<&VariantDatabase>,
+        cookie_jar: &CookieJar,
+        Path(id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Read>,
+    ) -> VariantListResponse {
+        let credential = credential(passphrase_header.0, auth.or(current_user));
+        let readable = match find_readable(database, cookie_jar, id, credential).await {
+            Ok(Some(readable)) => readable,
+            Ok(None) => {
+                return VariantListResponse::NotFound(PlainText(
+                    "No qr code could be found for this id.".to_string(),
+                ));
+            }
+            Err(why) => {
+                error!("Failed to look up qr code {id}, {why}");
+                return VariantListResponse::InternalError(PlainText(
+                    "Could not retrieve the variants, because of an internal error.".to_string(),
+                ));
+            }
+        };
+
+        match variants.list(id).await {
+            Ok(variants) => VariantListResponse::Ok(Json(
+                variants
+                    .into_iter()
+                    .map(|x| VariantResponse {
+                        link: readable.link(x.link.clone()),
+                        ..x.into()
+                    })
+                    .collect(),
+            )),
+            Err(why) => {
+                error!("Failed to list variants of qr code {id}, {why}");
+                VariantListResponse::InternalError(PlainText(
+                    "Could not retrieve the variants, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/variants",
+        method = "post",
+        tag = "ApiTags::Variant",
+        transform = "throttle_passphrase"
+    )]
+    async fn create(
+        &self,
+        Data(variants): Data<&VariantDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<VariantRequest>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> VariantJsonResponse {
+        if let Err(why) = link_policy.check(&request.link) {
+            return VariantJsonResponse::Rejected(PlainText(format!(
+                "The link was rejected, {why}."
+            )));
+        }
+
+        let Some(credential) = credential(
+            passphrase(passphrase_header.0, request.password),
+            auth.or(current_user),
+        ) else {
+            return VariantJsonResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+
+        match variants
+            .create(id, credential, request.name, request.link, request.weight)
+            .await
+        {
+            Ok(Some(variant)) => VariantJsonResponse::Created(Json(variant.into())),
+            Ok(None) => VariantJsonResponse::NotFound(PlainText(
+                "No qr code could be found for this id.".to_string(),
+            )),
+            Err(QrCodeDatabaseError::Immutable) => VariantJsonResponse::Conflict(PlainText(
+                "Immutable qr codes can't be edited.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to create variant for qr code {id}, {why}");
+                VariantJsonResponse::InternalError(PlainText(
+                    "Could not create the variant, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/variants/:variant_id",
+        method = "put",
+        tag = "ApiTags::Variant",
+        transform = "throttle_passphrase"
+    )]
+    async fn update(
+        &self,
+        Data(variants): Data<&VariantDatabase>,
+        Data(link_policy): Data<&LinkPolicy>,
+        Path(id): Path<Uuid>,
+        Path(variant_id): Path<Uuid>,
+        Json(request): Json<VariantRequest>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> VariantJsonResponse {
+        if let Err(why) = link_policy.check(&request.link) {
+            return VariantJsonResponse::Rejected(PlainText(format!(
+                "The link was rejected, {why}."
+            )));
+        }
+
+        let Some(credential) = credential(
+            passphrase(passphrase_header.0, request.password),
+            auth.or(current_user),
+        ) else {
+            return VariantJsonResponse::Rejected(PlainText(MISSING_PASSPHRASE.to_string()));
+        };
+
+        match variants
+            .update(
+                id,
+                variant_id,
+                credential,
+                request.name,
+                request.link,
+                request.weight,
+            )
+            .await
+        {
+            Ok(Some(variant)) => VariantJsonResponse::Ok(Json(variant.into())),
+            Ok(None) => VariantJsonResponse::NotFound(PlainText(
+                "No variant could be found for this id.".to_string(),
+            )),
+            Err(QrCodeDatabaseError::Immutable) => VariantJsonResponse::Conflict(PlainText(
+                "Immutable qr codes can't be edited.".to_string(),
+            )),
+            Err(why) => {
+                error!("Failed to update variant {variant_id}, {why}");
+                VariantJsonResponse::InternalError(PlainText(
+                    "Could not update the variant, because of an internal error.".to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[oai(
+        path = "/qr/:id/variants/:variant_id",
+        method = "delete",
+        tag = "ApiTags::Variant",
+        transform = "throttle_passphrase"
+    )]
+    async fn delete(
+        &self,
+        Data(variants): Data<&VariantDatabase>,
+        Path(id): Path<Uuid>,
+        Path(variant_id): Path<Uuid>,
+        #[oai(name = "X-Qr-Passphrase")] passphrase_header: Header<Option<String>>,
+        PassphraseBody(body): PassphraseBody,
+        current_user: CurrentUser,
+        auth: ApiKeyAuth<Write>,
+    ) -> VariantDeleteResponse {
+        match credential(passphrase(passphrase_header.0, body), auth.or(current_user)) {
+            Some(credential) => delete_variant(variants, id, variant_id, credential).await,
+            None => {
+                VariantDeleteResponse::MissingPassphrase(PlainText(MISSING_PASSPHRASE.to_string()))
+            }
+        }
+    }
+
+    #[oai(
+        path = "/qr/:id/variants/:variant_id/:pass",
+        method = "delete",
+        tag = "ApiTags::Variant",
+        deprecated,
+        transform = "deprecated_path_passphrase"
+    )]
+    async fn delete_with_path_passphrase(
+        &self,
+        Data(variants): Data<&VariantDatabase>,
+        Path(id): Path<Uuid>,
+        Path(variant_id): Path<Uuid>,
+        Path(password): Path<String>,
+    ) -> VariantDeleteResponse {
+        delete_variant(variants, id, variant_id, Credential::Passphrase(password)).await
+    }
+}
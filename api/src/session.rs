@@ -0,0 +1,56 @@
+use poem::{
+    FromRequest, Request, RequestBody, Result,
+    web::cookie::{Cookie, CookieJar, SameSite},
+};
+use service::UserDatabase;
+use tracing::error;
+use uuid::Uuid;
+
+const SESSION_COOKIE: &str = "qr_session";
+
+/// The user logged in with the session cookie of the request, if any.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CurrentUser(pub Option<Uuid>);
+
+impl<'a> FromRequest<'a> for CurrentUser {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let Some(token) = session_token(req.cookie()) else {
+            return Ok(Self(None));
+        };
+        let Some(users) = req.data::<UserDatabase>() else {
+            return Ok(Self(None));
+        };
+
+        match users.find_session(&token).await {
+            Ok(user) => Ok(Self(user.map(|user| user.id))),
+            Err(why) => {
+                error!("Failed to look up session, {why}");
+                Ok(Self(None))
+            }
+        }
+    }
+}
+
+pub fn session_token(cookie_jar: &CookieJar) -> Option<String> {
+    cookie_jar
+        .get(SESSION_COOKIE)
+        .map(|cookie| cookie.value_str().to_string())
+}
+
+pub fn set_session_cookie(cookie_jar: &CookieJar, token: String, ttl: chrono::Duration) {
+    let mut cookie = Cookie::new_with_str(SESSION_COOKIE, token);
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_max_age(ttl.to_std().unwrap_or_default());
+
+    cookie_jar.add(cookie);
+}
+
+pub fn remove_session_cookie(cookie_jar: &CookieJar) {
+    let mut cookie = Cookie::named(SESSION_COOKIE);
+    cookie.set_path("/");
+    cookie.make_removal();
+
+    cookie_jar.add(cookie);
+}
@@ -2,4 +2,19 @@
 
 pub mod prelude;
 
+pub mod api_key;
+pub mod audit_log;
+pub mod destination_schedule;
+pub mod destination_variant;
+pub mod link_check;
+pub mod organization;
+pub mod organization_member;
 pub mod qr_code;
+pub mod qr_code_alias;
+pub mod qr_code_revision;
+pub mod scan_event;
+pub mod sea_orm_active_enums;
+pub mod targeting_rule;
+pub mod user;
+pub mod user_identity;
+pub mod user_session;
@@ -1,3 +1,17 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+pub use super::api_key::Entity as ApiKey;
+pub use super::audit_log::Entity as AuditLog;
+pub use super::destination_schedule::Entity as DestinationSchedule;
+pub use super::destination_variant::Entity as DestinationVariant;
+pub use super::link_check::Entity as LinkCheck;
+pub use super::organization::Entity as Organization;
+pub use super::organization_member::Entity as OrganizationMember;
 pub use super::qr_code::Entity as QrCode;
+pub use super::qr_code_alias::Entity as QrCodeAlias;
+pub use super::qr_code_revision::Entity as QrCodeRevision;
+pub use super::scan_event::Entity as ScanEvent;
+pub use super::targeting_rule::Entity as TargetingRule;
+pub use super::user::Entity as User;
+pub use super::user_identity::Entity as UserIdentity;
+pub use super::user_session::Entity as UserSession;
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+use super::sea_orm_active_enums::RedirectStatus;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -12,9 +13,121 @@ pub struct Model {
     pub passphrase: String,
     pub created_at: DateTimeUtc,
     pub modified_at: Option<DateTimeUtc>,
+    pub scan_count: i32,
+    pub max_scans: Option<i32>,
+    pub access_password_hash: Option<String>,
+    pub sticky_variants: bool,
+    pub utm_source: Option<String>,
+    pub utm_medium: Option<String>,
+    pub utm_campaign: Option<String>,
+    pub utm_content: Option<String>,
+    pub utm_term: Option<String>,
+    pub extra_query: Option<Json>,
+    pub pass_through_query: bool,
+    #[sea_orm(unique)]
+    pub slug: Option<String>,
+    pub redirect_status: RedirectStatus,
+    pub cache_max_age: Option<i32>,
+    pub active: bool,
+    pub deleted_at: Option<DateTimeUtc>,
+    pub preview: bool,
+    pub preview_title: Option<String>,
+    pub fallback_link: Option<String>,
+    pub expires_at: Option<DateTimeUtc>,
+    pub passphrase_hashed: bool,
+    pub owner_email: Option<String>,
+    pub owner_id: Option<Uuid>,
+    pub organization_id: Option<Uuid>,
+    pub link_changed_at: Option<DateTimeUtc>,
+    pub immutable: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::destination_schedule::Entity")]
+    DestinationSchedule,
+    #[sea_orm(has_many = "super::destination_variant::Entity")]
+    DestinationVariant,
+    #[sea_orm(has_one = "super::link_check::Entity")]
+    LinkCheck,
+    #[sea_orm(
+        belongs_to = "super::organization::Entity",
+        from = "Column::OrganizationId",
+        to = "super::organization::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Organization,
+    #[sea_orm(has_many = "super::qr_code_alias::Entity")]
+    QrCodeAlias,
+    #[sea_orm(has_many = "super::qr_code_revision::Entity")]
+    QrCodeRevision,
+    #[sea_orm(has_many = "super::scan_event::Entity")]
+    ScanEvent,
+    #[sea_orm(has_many = "super::targeting_rule::Entity")]
+    TargetingRule,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::OwnerId",
+        to = "super::user::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    User,
+}
+
+impl Related<super::destination_schedule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DestinationSchedule.def()
+    }
+}
+
+impl Related<super::destination_variant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DestinationVariant.def()
+    }
+}
+
+impl Related<super::link_check::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LinkCheck.def()
+    }
+}
+
+impl Related<super::organization::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
+impl Related<super::qr_code_alias::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::QrCodeAlias.def()
+    }
+}
+
+impl Related<super::qr_code_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::QrCodeRevision.def()
+    }
+}
+
+impl Related<super::scan_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ScanEvent.def()
+    }
+}
+
+impl Related<super::targeting_rule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TargetingRule.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
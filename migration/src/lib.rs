@@ -1,12 +1,56 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20261019_000001_add_scan_limit;
+mod m20261019_000002_add_access_password;
+mod m20261019_000003_create_destination_schedule;
+mod m20261019_000004_create_targeting_rule;
+mod m20261019_000005_create_destination_variant;
+mod m20261019_000006_add_query_params;
+mod m20261019_000007_add_slugs_and_aliases;
+mod m20261019_000008_add_redirect_status;
+mod m20261019_000009_add_active_state;
+mod m20261019_000010_create_qr_code_revision;
+mod m20261019_000011_add_soft_delete;
+mod m20261019_000012_add_preview;
+mod m20261019_000013_create_link_check;
+mod m20261019_000014_add_fallback_link;
+mod m20261019_000015_hash_passphrases;
+mod m20261019_000016_add_owner_email;
+mod m20261019_000017_create_user;
+mod m20261019_000018_create_api_key;
+mod m20261019_000019_create_organization;
+mod m20261019_000020_create_user_identity;
+mod m20261019_000021_create_audit_log;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261019_000001_add_scan_limit::Migration),
+            Box::new(m20261019_000002_add_access_password::Migration),
+            Box::new(m20261019_000003_create_destination_schedule::Migration),
+            Box::new(m20261019_000004_create_targeting_rule::Migration),
+            Box::new(m20261019_000005_create_destination_variant::Migration),
+            Box::new(m20261019_000006_add_query_params::Migration),
+            Box::new(m20261019_000007_add_slugs_and_aliases::Migration),
+            Box::new(m20261019_000008_add_redirect_status::Migration),
+            Box::new(m20261019_000009_add_active_state::Migration),
+            Box::new(m20261019_000010_create_qr_code_revision::Migration),
+            Box::new(m20261019_000011_add_soft_delete::Migration),
+            Box::new(m20261019_000012_add_preview::Migration),
+            Box::new(m20261019_000013_create_link_check::Migration),
+            Box::new(m20261019_000014_add_fallback_link::Migration),
+            Box::new(m20261019_000015_hash_passphrases::Migration),
+            Box::new(m20261019_000016_add_owner_email::Migration),
+            Box::new(m20261019_000017_create_user::Migration),
+            Box::new(m20261019_000018_create_api_key::Migration),
+            Box::new(m20261019_000019_create_organization::Migration),
+            Box::new(m20261019_000020_create_user_identity::Migration),
+            Box::new(m20261019_000021_create_audit_log::Migration),
+        ]
     }
 }
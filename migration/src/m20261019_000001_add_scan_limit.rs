@@ -0,0 +1,55 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one column per ALTER TABLE statement.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .add_column(integer(QrCode::ScanCount).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .add_column(integer_null(QrCode::MaxScans))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .drop_column(QrCode::MaxScans)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .drop_column(QrCode::ScanCount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    ScanCount,
+    MaxScans,
+}
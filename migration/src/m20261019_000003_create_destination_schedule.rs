@@ -0,0 +1,83 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DestinationSchedule::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(DestinationSchedule::Id))
+                    .col(uuid(DestinationSchedule::QrCodeId))
+                    .col(string_len(DestinationSchedule::Link, 512))
+                    .col(timestamp(DestinationSchedule::StartsAt))
+                    .col(timestamp(DestinationSchedule::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_destination_schedule_qr_code")
+                            .from(DestinationSchedule::Table, DestinationSchedule::QrCodeId)
+                            .to(QrCode::Table, QrCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_destination_schedule_qr_code_starts_at")
+                    .table(DestinationSchedule::Table)
+                    .col(DestinationSchedule::QrCodeId)
+                    .col(DestinationSchedule::StartsAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Entries that started before the link was last changed directly are superseded.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .add_column(timestamp_null(QrCode::LinkChangedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .drop_column(QrCode::LinkChangedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(DestinationSchedule::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DestinationSchedule {
+    Table,
+    Id,
+    QrCodeId,
+    Link,
+    StartsAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    Id,
+    LinkChangedAt,
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TargetingRule::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(TargetingRule::Id))
+                    .col(uuid(TargetingRule::QrCodeId))
+                    .col(integer(TargetingRule::Position))
+                    .col(string_len_null(TargetingRule::Os, 16))
+                    .col(string_len_null(TargetingRule::Device, 16))
+                    .col(string_len_null(TargetingRule::Language, 16))
+                    .col(string_len_null(TargetingRule::Country, 2))
+                    .col(string_len(TargetingRule::Link, 512))
+                    .col(timestamp(TargetingRule::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_targeting_rule_qr_code")
+                            .from(TargetingRule::Table, TargetingRule::QrCodeId)
+                            .to(QrCode::Table, QrCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_targeting_rule_qr_code_position")
+                    .table(TargetingRule::Table)
+                    .col(TargetingRule::QrCodeId)
+                    .col(TargetingRule::Position)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TargetingRule::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TargetingRule {
+    Table,
+    Id,
+    QrCodeId,
+    Position,
+    Os,
+    Device,
+    Language,
+    Country,
+    Link,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    Id,
+}
@@ -0,0 +1,124 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .add_column(boolean(QrCode::StickyVariants).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(DestinationVariant::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(DestinationVariant::Id))
+                    .col(uuid(DestinationVariant::QrCodeId))
+                    .col(string_len(DestinationVariant::Name, 64))
+                    .col(string_len(DestinationVariant::Link, 512))
+                    .col(integer(DestinationVariant::Weight))
+                    .col(timestamp(DestinationVariant::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_destination_variant_qr_code")
+                            .from(DestinationVariant::Table, DestinationVariant::QrCodeId)
+                            .to(QrCode::Table, QrCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScanEvent::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ScanEvent::Id))
+                    .col(uuid(ScanEvent::QrCodeId))
+                    .col(uuid_null(ScanEvent::VariantId))
+                    .col(timestamp(ScanEvent::ScannedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scan_event_qr_code")
+                            .from(ScanEvent::Table, ScanEvent::QrCodeId)
+                            .to(QrCode::Table, QrCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scan_event_destination_variant")
+                            .from(ScanEvent::Table, ScanEvent::VariantId)
+                            .to(DestinationVariant::Table, DestinationVariant::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_scan_event_qr_code_variant")
+                    .table(ScanEvent::Table)
+                    .col(ScanEvent::QrCodeId)
+                    .col(ScanEvent::VariantId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ScanEvent::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(DestinationVariant::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .drop_column(QrCode::StickyVariants)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DestinationVariant {
+    Table,
+    Id,
+    QrCodeId,
+    Name,
+    Link,
+    Weight,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ScanEvent {
+    Table,
+    Id,
+    QrCodeId,
+    VariantId,
+    ScannedAt,
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    Id,
+    StickyVariants,
+}
@@ -0,0 +1,78 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UTM_COLUMNS: [QrCode; 5] = [
+    QrCode::UtmSource,
+    QrCode::UtmMedium,
+    QrCode::UtmCampaign,
+    QrCode::UtmContent,
+    QrCode::UtmTerm,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one column per ALTER TABLE statement.
+        for column in UTM_COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(QrCode::Table)
+                        .add_column(string_len_null(column, 255))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .add_column(json_null(QrCode::ExtraQuery))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .add_column(boolean(QrCode::PassThroughQuery).default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = UTM_COLUMNS
+            .into_iter()
+            .chain([QrCode::ExtraQuery, QrCode::PassThroughQuery]);
+
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(QrCode::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum QrCode {
+    Table,
+    UtmSource,
+    UtmMedium,
+    UtmCampaign,
+    UtmContent,
+    UtmTerm,
+    ExtraQuery,
+    PassThroughQuery,
+}
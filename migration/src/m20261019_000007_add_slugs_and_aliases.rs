@@ -0,0 +1,87 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .add_column(string_len_null(QrCode::Slug, 64))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_qr_code_slug")
+                    .table(QrCode::Table)
+                    .col(QrCode::Slug)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(QrCodeAlias::Table)
+                    .if_not_exists()
+                    .col(string_len(QrCodeAlias::Alias, 64).primary_key())
+                    .col(uuid(QrCodeAlias::QrCodeId))
+                    .col(timestamp(QrCodeAlias::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_qr_code_alias_qr_code")
+                            .from(QrCodeAlias::Table, QrCodeAlias::QrCodeId)
+                            .to(QrCode::Table, QrCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(QrCodeAlias::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_qr_code_slug")
+                    .table(QrCode::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .drop_column(QrCode::Slug)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    Id,
+    Slug,
+}
+
+#[derive(DeriveIden)]
+enum QrCodeAlias {
+    Table,
+    Alias,
+    QrCodeId,
+    CreatedAt,
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(QrCodeRevision::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(QrCodeRevision::Id))
+                    .col(uuid(QrCodeRevision::QrCodeId))
+                    .col(string_len(QrCodeRevision::Link, 512))
+                    .col(string_len_null(QrCodeRevision::Actor, 255))
+                    .col(timestamp(QrCodeRevision::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_qr_code_revision_qr_code")
+                            .from(QrCodeRevision::Table, QrCodeRevision::QrCodeId)
+                            .to(QrCode::Table, QrCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_qr_code_revision_qr_code_created_at")
+                    .table(QrCodeRevision::Table)
+                    .col(QrCodeRevision::QrCodeId)
+                    .col(QrCodeRevision::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(QrCodeRevision::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum QrCodeRevision {
+    Table,
+    Id,
+    QrCodeId,
+    Link,
+    Actor,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    Id,
+}
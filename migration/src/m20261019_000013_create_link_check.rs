@@ -0,0 +1,66 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LinkCheck::Table)
+                    .if_not_exists()
+                    .col(uuid(LinkCheck::QrCodeId).primary_key())
+                    .col(string_len(LinkCheck::Link, 512))
+                    .col(boolean(LinkCheck::Healthy))
+                    .col(integer_null(LinkCheck::StatusCode))
+                    .col(integer(LinkCheck::LatencyMs))
+                    .col(string_len_null(LinkCheck::Error, 255))
+                    .col(timestamp(LinkCheck::CheckedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_link_check_qr_code")
+                            .from(LinkCheck::Table, LinkCheck::QrCodeId)
+                            .to(QrCode::Table, QrCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_link_check_healthy")
+                    .table(LinkCheck::Table)
+                    .col(LinkCheck::Healthy)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LinkCheck::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum LinkCheck {
+    Table,
+    QrCodeId,
+    Link,
+    Healthy,
+    StatusCode,
+    LatencyMs,
+    Error,
+    CheckedAt,
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    Id,
+}
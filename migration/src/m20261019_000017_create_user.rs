@@ -0,0 +1,130 @@
+use sea_orm_migration::{prelude::*, schema::*, sea_orm::DbBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(User::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(User::Id))
+                    .col(string_len_uniq(User::Email, 254))
+                    .col(string_len(User::PasswordHash, 255))
+                    .col(timestamp(User::CreatedAt))
+                    .col(boolean(User::EmailVerified).default(false))
+                    .col(string_len_null(User::VerificationTokenHash, 64))
+                    .col(timestamp_null(User::VerificationExpiresAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserSession::Table)
+                    .if_not_exists()
+                    .col(string_len(UserSession::Id, 64).primary_key())
+                    .col(uuid(UserSession::UserId))
+                    .col(timestamp(UserSession::CreatedAt))
+                    .col(timestamp(UserSession::ExpiresAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_session_user")
+                            .from(UserSession::Table, UserSession::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // SQLite can't add foreign keys to existing tables, but accepts new columns
+        // referencing another one.
+        let mut owner_id = uuid_null(QrCode::OwnerId);
+        let mut alter = Table::alter().table(QrCode::Table).to_owned();
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            owner_id.extra(r#"REFERENCES "user" ("id") ON DELETE SET NULL"#);
+            alter.add_column(owner_id);
+        } else {
+            alter.add_column(owner_id).add_foreign_key(
+                TableForeignKey::new()
+                    .name("fk_qr_code_owner")
+                    .from_tbl(QrCode::Table)
+                    .from_col(QrCode::OwnerId)
+                    .to_tbl(User::Table)
+                    .to_col(User::Id)
+                    .on_delete(ForeignKeyAction::SetNull),
+            );
+        }
+        manager.alter_table(alter).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_qr_code_owner_id")
+                    .table(QrCode::Table)
+                    .col(QrCode::OwnerId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_qr_code_owner_id")
+                    .table(QrCode::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .drop_column(QrCode::OwnerId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UserSession::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(User::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+    Email,
+    PasswordHash,
+    CreatedAt,
+    EmailVerified,
+    VerificationTokenHash,
+    VerificationExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum UserSession {
+    Table,
+    Id,
+    UserId,
+    CreatedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    OwnerId,
+}
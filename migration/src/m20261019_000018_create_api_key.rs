@@ -0,0 +1,60 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKey::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(ApiKey::Id))
+                    .col(uuid(ApiKey::UserId))
+                    .col(string_len(ApiKey::Name, 64))
+                    .col(string_len_uniq(ApiKey::Prefix, 16))
+                    .col(string_len(ApiKey::SecretHash, 64))
+                    .col(string_len(ApiKey::Scopes, 64))
+                    .col(timestamp_null(ApiKey::ExpiresAt))
+                    .col(timestamp_null(ApiKey::LastUsedAt))
+                    .col(timestamp(ApiKey::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_api_key_user")
+                            .from(ApiKey::Table, ApiKey::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ApiKey {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Prefix,
+    SecretHash,
+    Scopes,
+    ExpiresAt,
+    LastUsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
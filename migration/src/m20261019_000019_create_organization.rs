@@ -0,0 +1,143 @@
+use sea_orm_migration::{prelude::*, schema::*, sea_orm::DbBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Organization::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(Organization::Id))
+                    .col(string_len(Organization::Name, 64))
+                    .col(timestamp(Organization::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrganizationMember::Table)
+                    .if_not_exists()
+                    .col(uuid(OrganizationMember::OrganizationId))
+                    .col(uuid(OrganizationMember::UserId))
+                    .col(string_len(OrganizationMember::Role, 16))
+                    .col(timestamp(OrganizationMember::CreatedAt))
+                    .primary_key(
+                        Index::create()
+                            .col(OrganizationMember::OrganizationId)
+                            .col(OrganizationMember::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_organization_member_organization")
+                            .from(
+                                OrganizationMember::Table,
+                                OrganizationMember::OrganizationId,
+                            )
+                            .to(Organization::Table, Organization::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_organization_member_user")
+                            .from(OrganizationMember::Table, OrganizationMember::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Codes of a deleted organization become personal codes again. SQLite can't add
+        // foreign keys to existing tables, but accepts new columns referencing another one.
+        let mut organization_id = uuid_null(QrCode::OrganizationId);
+        let mut alter = Table::alter().table(QrCode::Table).to_owned();
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            organization_id.extra(r#"REFERENCES "organization" ("id") ON DELETE SET NULL"#);
+            alter.add_column(organization_id);
+        } else {
+            alter.add_column(organization_id).add_foreign_key(
+                TableForeignKey::new()
+                    .name("fk_qr_code_organization")
+                    .from_tbl(QrCode::Table)
+                    .from_col(QrCode::OrganizationId)
+                    .to_tbl(Organization::Table)
+                    .to_col(Organization::Id)
+                    .on_delete(ForeignKeyAction::SetNull),
+            );
+        }
+        manager.alter_table(alter).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_qr_code_organization_id")
+                    .table(QrCode::Table)
+                    .col(QrCode::OrganizationId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_qr_code_organization_id")
+                    .table(QrCode::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(QrCode::Table)
+                    .drop_column(QrCode::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(OrganizationMember::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Organization::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Organization {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum OrganizationMember {
+    Table,
+    OrganizationId,
+    UserId,
+    Role,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum QrCode {
+    Table,
+    OrganizationId,
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserIdentity::Table)
+                    .if_not_exists()
+                    .col(string_len(UserIdentity::Issuer, 255))
+                    .col(string_len(UserIdentity::Subject, 255))
+                    .col(uuid(UserIdentity::UserId))
+                    .col(timestamp(UserIdentity::CreatedAt))
+                    .primary_key(
+                        Index::create()
+                            .col(UserIdentity::Issuer)
+                            .col(UserIdentity::Subject),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_identity_user")
+                            .from(UserIdentity::Table, UserIdentity::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserIdentity::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserIdentity {
+    Table,
+    Issuer,
+    Subject,
+    UserId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
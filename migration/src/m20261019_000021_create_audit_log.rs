@@ -0,0 +1,67 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign key to the qr code, entries have to outlive purged codes.
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(AuditLog::Id))
+                    .col(uuid(AuditLog::QrCodeId))
+                    .col(string_len(AuditLog::Action, 32))
+                    .col(string_len(AuditLog::Actor, 64))
+                    .col(text_null(AuditLog::OldValues))
+                    .col(text_null(AuditLog::NewValues))
+                    .col(string_len_null(AuditLog::ClientIpHash, 64))
+                    .col(timestamp(AuditLog::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_qr_code_created_at")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::QrCodeId)
+                    .col(AuditLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_created_at")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    QrCodeId,
+    Action,
+    Actor,
+    OldValues,
+    NewValues,
+    ClientIpHash,
+    CreatedAt,
+}
@@ -0,0 +1,167 @@
+use ::entity::{
+    organization_member::Entity as DbOrganizationMember, qr_code::Model,
+    sea_orm_active_enums::OrganizationRole,
+};
+use sea_orm::{DbConn, DbErr, EntityTrait};
+use uuid::Uuid;
+
+use crate::qrcode::{QrCodeDatabaseError, check_passphrase, find_live};
+
+/// Proves the right to manage a qr code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Credential {
+    /// The passphrase returned once when the code was created.
+    Passphrase(String),
+    /// A logged in user, allowed to manage the codes they own and those of their
+    /// organizations as far as their role permits.
+    User(Uuid),
+}
+
+/// What a caller wants to do, each action includes the ones before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    /// Reading a code, its image and stats.
+    View,
+    /// Changing the destination and settings of a code.
+    Edit,
+    /// Deleting, restoring or handing over a code and managing members.
+    Manage,
+}
+
+/// Owners may do anything, editors change codes and viewers only read them.
+pub fn role_allows(role: OrganizationRole, action: Action) -> bool {
+    match role {
+        OrganizationRole::Owner => true,
+        OrganizationRole::Editor => action <= Action::Edit,
+        OrganizationRole::Viewer => action == Action::View,
+    }
+}
+
+pub(crate) async fn organization_role(
+    db_conn: &DbConn,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<OrganizationRole>, DbErr> {
+    let member = DbOrganizationMember::find_by_id((organization_id, user_id))
+        .one(db_conn)
+        .await?;
+
+    Ok(member.map(|x| x.role))
+}
+
+pub(crate) async fn authorize_organization(
+    db_conn: &DbConn,
+    organization_id: Uuid,
+    user_id: Uuid,
+    action: Action,
+) -> Result<bool, DbErr> {
+    let role = organization_role(db_conn, organization_id, user_id).await?;
+
+    Ok(role.is_some_and(|role| role_allows(role, action)))
+}
+
+/// Finds a live qr code the credential allows `action` on.
+pub(crate) async fn find_authorized(
+    db_conn: &DbConn,
+    id: Uuid,
+    credential: &Credential,
+    action: Action,
+) -> Result<Option<Model>, DbErr> {
+    let Some(qr_code) = find_live(id).one(db_conn).await? else {
+        return Ok(None);
+    };
+
+    authorize(db_conn, qr_code, credential, action).await
+}
+
+/// Like [`find_authorized`], but refuses to change where an immutable code resolves, its
+/// permanent redirect may already be cached by browsers and proxies.
+pub(crate) async fn find_mutable(
+    db_conn: &DbConn,
+    id: Uuid,
+    credential: &Credential,
+    action: Action,
+) -> Result<Option<Model>, QrCodeDatabaseError> {
+    let Some(qr_code) = find_authorized(db_conn, id, credential, action).await? else {
+        return Ok(None);
+    };
+    if qr_code.immutable {
+        return Err(QrCodeDatabaseError::Immutable);
+    }
+
+    Ok(Some(qr_code))
+}
+
+/// The passphrase and the owner may do anything with a code, members of its organization
+/// what their role allows.
+pub(crate) async fn authorize(
+    db_conn: &DbConn,
+    qr_code: Model,
+    credential: &Credential,
+    action: Action,
+) -> Result<Option<Model>, DbErr> {
+    let user_id = match credential {
+        Credential::Passphrase(passphrase) => {
+            return check_passphrase(db_conn, qr_code, passphrase).await;
+        }
+        Credential::User(user_id) => *user_id,
+    };
+
+    if qr_code.owner_id == Some(user_id) {
+        return Ok(Some(qr_code));
+    }
+
+    let Some(organization_id) = qr_code.organization_id else {
+        return Ok(None);
+    };
+    let allowed = authorize_organization(db_conn, organization_id, user_id, action).await?;
+
+    Ok(allowed.then_some(qr_code))
+}
+
+/// Finds a live qr code the viewer may see.
+///
+/// Codes of an organization are only visible to its members, all others stay public like
+/// they were before organizations existed.
+pub(crate) async fn find_visible(
+    db_conn: &DbConn,
+    id: Uuid,
+    viewer: Option<Uuid>,
+) -> Result<Option<Model>, DbErr> {
+    let Some(qr_code) = find_live(id).one(db_conn).await? else {
+        return Ok(None);
+    };
+    if qr_code.organization_id.is_none() {
+        return Ok(Some(qr_code));
+    }
+    let Some(viewer) = viewer else {
+        return Ok(None);
+    };
+
+    authorize(db_conn, qr_code, &Credential::User(viewer), Action::View).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_include_the_actions_below_them() {
+        let allowed = |role| {
+            [Action::View, Action::Edit, Action::Manage]
+                .into_iter()
+                .filter(|action| role_allows(role, *action))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            allowed(OrganizationRole::Owner),
+            [Action::View, Action::Edit, Action::Manage]
+        );
+        assert_eq!(
+            allowed(OrganizationRole::Editor),
+            [Action::View, Action::Edit]
+        );
+        assert_eq!(allowed(OrganizationRole::Viewer), [Action::View]);
+    }
+}
@@ -0,0 +1,173 @@
+use ::entity::{
+    qr_code::{self, Entity as DbQrCode},
+    qr_code_alias::{self, Entity as DbQrCodeAlias},
+};
+use chrono::Utc;
+use entity::qr_code_alias::Model;
+use rand::{Rng, distr::Alphanumeric};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, DbErr, EntityTrait, ModelTrait,
+    PaginatorTrait, QueryFilter, QueryOrder,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::access::{Action, Credential, find_authorized};
+
+const SLUG_LENGTH: usize = 7;
+const SLUG_ATTEMPTS: usize = 10;
+
+/// Paths on the server that must never be shadowed by a short link.
+const RESERVED_ALIASES: [&str; 17] = [
+    "account",
+    "admin",
+    "api",
+    "assets",
+    "delete",
+    "docs",
+    "edit",
+    "favicon.ico",
+    "health",
+    "impressum",
+    "login",
+    "logout",
+    "new",
+    "privacy",
+    "r",
+    "robots.txt",
+    "static",
+];
+
+#[derive(Debug, Error)]
+pub enum AliasError {
+    #[error("the alias is reserved")]
+    Reserved,
+    #[error("the alias is already taken")]
+    Taken,
+    #[error("database operation failed, {0}")]
+    Database(#[from] DbErr),
+}
+
+pub fn is_reserved_alias(alias: &str) -> bool {
+    RESERVED_ALIASES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(alias))
+}
+
+/// Checks whether a slug or alias is already used by any qr code.
+pub(crate) async fn is_taken(db_conn: &DbConn, value: &str) -> Result<bool, DbErr> {
+    let slugs = DbQrCode::find()
+        .filter(qr_code::Column::Slug.eq(value))
+        .count(db_conn)
+        .await?;
+    if slugs > 0 {
+        return Ok(true);
+    }
+
+    Ok(DbQrCodeAlias::find_by_id(value)
+        .one(db_conn)
+        .await?
+        .is_some())
+}
+
+pub(crate) async fn generate_slug(db_conn: &DbConn) -> Result<String, DbErr> {
+    for _ in 0..SLUG_ATTEMPTS {
+        let slug: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(SLUG_LENGTH)
+            .map(char::from)
+            .collect();
+
+        if !is_reserved_alias(&slug) && !is_taken(db_conn, &slug).await? {
+            return Ok(slug);
+        }
+    }
+
+    Err(DbErr::Custom(
+        "could not generate an unused slug".to_string(),
+    ))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AliasDatabase {
+    pub db_conn: DbConn,
+}
+
+impl AliasDatabase {
+    pub async fn list(&self, qr_code_id: Uuid) -> Result<Vec<Model>, DbErr> {
+        DbQrCodeAlias::find()
+            .filter(qr_code_alias::Column::QrCodeId.eq(qr_code_id))
+            .order_by_asc(qr_code_alias::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await
+    }
+
+    pub async fn create(
+        &self,
+        qr_code_id: Uuid,
+        credential: Credential,
+        alias: String,
+    ) -> Result<Option<Model>, AliasError> {
+        if find_authorized(&self.db_conn, qr_code_id, &credential, Action::Edit)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        if is_reserved_alias(&alias) {
+            return Err(AliasError::Reserved);
+        }
+        if is_taken(&self.db_conn, &alias).await? {
+            return Err(AliasError::Taken);
+        }
+
+        let alias = qr_code_alias::ActiveModel {
+            alias: Set(alias),
+            qr_code_id: Set(qr_code_id),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&self.db_conn)
+        .await?;
+
+        Ok(Some(alias))
+    }
+
+    pub async fn delete(
+        &self,
+        qr_code_id: Uuid,
+        credential: Credential,
+        alias: String,
+    ) -> Result<Option<Model>, DbErr> {
+        if find_authorized(&self.db_conn, qr_code_id, &credential, Action::Edit)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let Some(alias) = DbQrCodeAlias::find_by_id(alias)
+            .filter(qr_code_alias::Column::QrCodeId.eq(qr_code_id))
+            .one(&self.db_conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        alias.clone().delete(&self.db_conn).await?;
+
+        Ok(Some(alias))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_aliases_ignore_case() {
+        assert!(is_reserved_alias("API"));
+        assert!(is_reserved_alias("Impressum"));
+        assert!(!is_reserved_alias("summer-sale"));
+    }
+}
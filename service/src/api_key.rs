@@ -0,0 +1,198 @@
+use std::{fmt, str::FromStr};
+
+use ::entity::api_key::{self, Entity as DbApiKey};
+use chrono::{DateTime, Utc};
+use entity::api_key::Model;
+use rand::{Rng, distr::Alphanumeric};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryOrder,
+};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use crate::password::token_hash;
+
+const KEY_PREFIX: &str = "qrk_";
+const PREFIX_LENGTH: usize = 8;
+const SECRET_LENGTH: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiKeyScope {
+    /// Reading qr codes.
+    Read,
+    /// Creating, changing and deleting qr codes.
+    Write,
+    /// Reading scan statistics.
+    Stats,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Stats => "stats",
+        }
+    }
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "stats" => Ok(Self::Stats),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Scopes are stored as a comma separated list, unknown entries are ignored.
+pub fn api_key_scopes(api_key: &Model) -> Vec<ApiKeyScope> {
+    api_key
+        .scopes
+        .split(',')
+        .filter_map(|x| x.parse().ok())
+        .collect()
+}
+
+/// Splits a token of the form `qrk_<prefix>_<secret>` into the stored prefix and the secret.
+fn split_token(token: &str) -> Option<(&str, &str)> {
+    let prefix_end = KEY_PREFIX.len() + PREFIX_LENGTH;
+    let prefix = token.get(..prefix_end)?;
+    let secret = token.get(prefix_end..)?.strip_prefix('_')?;
+
+    (prefix.starts_with(KEY_PREFIX) && !secret.is_empty()).then_some((prefix, secret))
+}
+
+fn random_string(length: usize) -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Keys for programmatic access on behalf of a user.
+///
+/// Only the prefix of a key is stored in plaintext to find it again, the secret is stored as
+/// its hash and shown once on creation.
+#[derive(Clone, Debug, Default)]
+pub struct ApiKeyDatabase {
+    pub db_conn: DbConn,
+}
+
+impl ApiKeyDatabase {
+    /// Creates a key and returns it together with its token.
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        name: String,
+        scopes: &[ApiKeyScope],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(Model, String), DbErr> {
+        let mut scopes = scopes.to_vec();
+        scopes.sort();
+        scopes.dedup();
+
+        let prefix = format!("{KEY_PREFIX}{}", random_string(PREFIX_LENGTH));
+        let secret = random_string(SECRET_LENGTH);
+
+        let api_key = api_key::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            name: Set(name),
+            prefix: Set(prefix.clone()),
+            secret_hash: Set(token_hash(&secret)),
+            scopes: Set(scopes
+                .iter()
+                .map(ApiKeyScope::as_str)
+                .collect::<Vec<_>>()
+                .join(",")),
+            expires_at: Set(expires_at),
+            last_used_at: Set(None),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&self.db_conn)
+        .await?;
+
+        Ok((api_key, format!("{prefix}_{secret}")))
+    }
+
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Model>, DbErr> {
+        DbApiKey::find()
+            .filter(api_key::Column::UserId.eq(user_id))
+            .order_by_desc(api_key::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await
+    }
+
+    /// Deletes a key of the user, returns `None` if the user has no key with this id.
+    pub async fn revoke(&self, user_id: Uuid, id: Uuid) -> Result<Option<Model>, DbErr> {
+        let Some(api_key) = DbApiKey::find_by_id(id)
+            .filter(api_key::Column::UserId.eq(user_id))
+            .one(&self.db_conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        DbApiKey::delete_by_id(id).exec(&self.db_conn).await?;
+
+        Ok(Some(api_key))
+    }
+
+    /// Looks up the key of a token that hasn't expired yet and records its use.
+    pub async fn authenticate(&self, token: &str) -> Result<Option<Model>, DbErr> {
+        let Some((prefix, secret)) = split_token(token) else {
+            return Ok(None);
+        };
+        let Some(api_key) = DbApiKey::find()
+            .filter(api_key::Column::Prefix.eq(prefix))
+            .one(&self.db_conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let matches = api_key
+            .secret_hash
+            .as_bytes()
+            .ct_eq(token_hash(secret).as_bytes());
+        let expired = api_key.expires_at.is_some_and(|x| x <= Utc::now());
+        if !bool::from(matches) || expired {
+            return Ok(None);
+        }
+
+        let mut api_key = api_key.into_active_model();
+        api_key.last_used_at = Set(Some(Utc::now()));
+
+        api_key.update(&self.db_conn).await.map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_tokens_into_prefix_and_secret() {
+        assert_eq!(
+            split_token("qrk_AbCd1234_s3cr3t"),
+            Some(("qrk_AbCd1234", "s3cr3t"))
+        );
+        assert_eq!(split_token("qrk_AbCd1234_"), None);
+        assert_eq!(split_token("qrk_AbCd1234s3cr3t"), None);
+        assert_eq!(split_token("abc_AbCd1234_s3cr3t"), None);
+        assert_eq!(split_token("qrk_Ab"), None);
+    }
+}
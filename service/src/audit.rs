@@ -0,0 +1,252 @@
+use std::{net::IpAddr, sync::Arc};
+
+use ::entity::audit_log::{self, Entity as DbAuditLog};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use entity::{qr_code::Model as QrCodeModel, sea_orm_active_enums::AuditAction};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbConn, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder,
+};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::access::Credential;
+
+/// Only recorded as changed, their values never end up in the log.
+const SECRET_FIELDS: &[&str] = &["passphrase", "access_password_hash"];
+/// Change on their own or along with others, so they would only clutter the log.
+const IGNORED_FIELDS: &[&str] = &[
+    "modified_at",
+    "scan_count",
+    "passphrase_hashed",
+    "link_changed_at",
+];
+const REDACTED: &str = "[redacted]";
+
+/// Fields of a qr code by their name.
+type Values = Map<String, Value>;
+
+/// Where a change came from, stored along with who made it.
+#[derive(Clone, Debug, Default)]
+pub struct AuditContext {
+    pub client_ip_hash: Option<String>,
+}
+
+/// Hashes client ips with a secret, so entries of the same client can be matched without
+/// storing the ip itself.
+#[derive(Clone, Debug)]
+pub struct IpHasher {
+    secret: Arc<[u8]>,
+}
+
+impl IpHasher {
+    /// Without a `secret` a random one is used, hashes then change on restart.
+    pub fn new(secret: Option<&[u8]>) -> Self {
+        let secret = match secret {
+            Some(secret) => Arc::from(secret),
+            None => {
+                let mut secret = [0; 32];
+                rand::rng().fill_bytes(&mut secret);
+                Arc::from(secret.as_slice())
+            }
+        };
+
+        Self { secret }
+    }
+
+    pub fn context(&self, client_ip: Option<IpAddr>) -> AuditContext {
+        let client_ip_hash = client_ip.map(|ip| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+                .expect("hmac accepts keys of any size");
+            mac.update(ip.to_string().as_bytes());
+            BASE64_URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+        });
+
+        AuditContext { client_ip_hash }
+    }
+}
+
+/// Names the actor of a credential, users by their id.
+pub(crate) fn actor(credential: &Credential) -> String {
+    match credential {
+        Credential::Passphrase(_) => "passphrase".to_string(),
+        Credential::User(user_id) => user_actor(*user_id),
+    }
+}
+
+pub(crate) fn user_actor(user_id: Uuid) -> String {
+    format!("user:{user_id}")
+}
+
+fn snapshot(qr_code: &QrCodeModel) -> Values {
+    match serde_json::to_value(qr_code) {
+        Ok(Value::Object(mut values)) => {
+            values.retain(|key, _| !IGNORED_FIELDS.contains(&key.as_str()));
+            values
+        }
+        _ => Map::new(),
+    }
+}
+
+/// Keeps only the fields that differ between both snapshots and redacts the secrets among
+/// them.
+fn changes(old: Option<Values>, new: Option<Values>) -> (Option<Values>, Option<Values>) {
+    let (mut old, mut new) = (old, new);
+
+    if let (Some(old), Some(new)) = (&mut old, &mut new) {
+        let unchanged: Vec<String> = old
+            .iter()
+            .filter(|(key, value)| new.get(*key) == Some(value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in unchanged {
+            old.remove(&key);
+            new.remove(&key);
+        }
+    }
+
+    let redact = |mut values: Values| {
+        for key in SECRET_FIELDS {
+            if let Some(value) = values.get_mut(*key).filter(|x| !x.is_null()) {
+                *value = Value::from(REDACTED);
+            }
+        }
+        values
+    };
+
+    (old.map(redact), new.map(redact))
+}
+
+/// Stores who changed the qr code and how, `old` is `None` for new codes.
+///
+/// Pass the transaction of the change, so it can't be stored without its entry.
+pub(crate) async fn record_audit<C: ConnectionTrait>(
+    db_conn: &C,
+    context: &AuditContext,
+    action: AuditAction,
+    actor: String,
+    old: Option<&QrCodeModel>,
+    new: &QrCodeModel,
+) -> Result<(), DbErr> {
+    let (old_values, new_values) = changes(old.map(snapshot), Some(snapshot(new)));
+    let to_json = |values: Values| Value::Object(values).to_string();
+
+    audit_log::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        qr_code_id: Set(new.id),
+        action: Set(action),
+        actor: Set(actor),
+        old_values: Set(old_values.map(to_json)),
+        new_values: Set(new_values.map(to_json)),
+        client_ip_hash: Set(context.client_ip_hash.clone()),
+        created_at: Set(Utc::now()),
+    }
+    .insert(db_conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Narrows the audit log, fields left at `None` match every entry.
+#[derive(Clone, Debug, Default)]
+pub struct AuditFilter {
+    pub qr_code_id: Option<Uuid>,
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AuditDatabase {
+    pub db_conn: DbConn,
+}
+
+impl AuditDatabase {
+    /// Lists a page of the matching entries, newest first, together with the number of all
+    /// matching entries. Pages start at `0`.
+    pub async fn list(
+        &self,
+        filter: AuditFilter,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<audit_log::Model>, u64), DbErr> {
+        let mut query = DbAuditLog::find();
+        if let Some(qr_code_id) = filter.qr_code_id {
+            query = query.filter(audit_log::Column::QrCodeId.eq(qr_code_id));
+        }
+        if let Some(actor) = filter.actor {
+            query = query.filter(audit_log::Column::Actor.eq(actor));
+        }
+        if let Some(action) = filter.action {
+            query = query.filter(audit_log::Column::Action.eq(action));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(audit_log::Column::CreatedAt.gte(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(audit_log::Column::CreatedAt.lt(until));
+        }
+
+        let paginator = query
+            .order_by_desc(audit_log::Column::CreatedAt)
+            .order_by_desc(audit_log::Column::Id)
+            .paginate(&self.db_conn, page_size);
+        let total = paginator.num_items().await?;
+        let entries = paginator.fetch_page(page).await?;
+
+        Ok((entries, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn values(value: Value) -> Values {
+        match value {
+            Value::Object(values) => values,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn keeps_changed_fields_and_redacts_secrets() {
+        let old = values(json!({
+            "link": "https://example.com/",
+            "active": true,
+            "passphrase": "old hash",
+            "access_password_hash": null,
+        }));
+        let new = values(json!({
+            "link": "https://example.org/",
+            "active": true,
+            "passphrase": "new hash",
+            "access_password_hash": null,
+        }));
+
+        let (old, new) = changes(Some(old), Some(new));
+        assert_eq!(
+            old.map(Value::Object),
+            Some(json!({"link": "https://example.com/", "passphrase": REDACTED}))
+        );
+        assert_eq!(
+            new.map(Value::Object),
+            Some(json!({"link": "https://example.org/", "passphrase": REDACTED}))
+        );
+
+        let created = values(json!({"link": "https://example.com/", "passphrase": "hash"}));
+        let (old, new) = changes(None, Some(created));
+        assert_eq!(old, None);
+        assert_eq!(
+            new.map(Value::Object),
+            Some(json!({"link": "https://example.com/", "passphrase": REDACTED}))
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::Utc;
+use entity::{qr_code::Model, sea_orm_active_enums::RedirectStatus};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sea_orm::{DbErr, prelude::Json};
+use sha2::Sha256;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{ClientInfo, LinkPolicy, ScheduleDatabase, TargetingDatabase, VariantDatabase};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Destination {
+    pub link: String,
+    pub variant_id: Option<Uuid>,
+}
+
+/// Decides where a scan goes: targeting rules first, then weighted variants, then the
+/// scheduled link and finally the link stored on the code itself. Editing that link
+/// supersedes schedule entries that already started.
+#[derive(Clone, Debug, Default)]
+pub struct DestinationResolver {
+    pub schedule: ScheduleDatabase,
+    pub targeting: TargetingDatabase,
+    pub variants: VariantDatabase,
+    pub link_policy: LinkPolicy,
+    pub signer: DestinationSigner,
+}
+
+impl DestinationResolver {
+    pub async fn resolve(
+        &self,
+        qr_code: &Model,
+        client: &ClientInfo,
+        preferred_variant: Option<Uuid>,
+    ) -> Result<Destination, DbErr> {
+        if let Some(link) = self.targeting.matching_link(qr_code.id, client).await? {
+            return Ok(Destination {
+                link,
+                variant_id: None,
+            });
+        }
+
+        if let Some(variant) = self.variants.choose(qr_code.id, preferred_variant).await? {
+            return Ok(Destination {
+                link: variant.link,
+                variant_id: Some(variant.id),
+            });
+        }
+
+        let link = self
+            .schedule
+            .current_link(qr_code.id, Utc::now(), qr_code.link_changed_at)
+            .await?;
+
+        Ok(Destination {
+            link: link.unwrap_or_else(|| qr_code.link.clone()),
+            variant_id: None,
+        })
+    }
+
+    /// Returns whether a scan of the code can end up somewhere else than its stored link,
+    /// or has to reach the server to be counted, unlocked or checked for expiry.
+    pub async fn is_dynamic(&self, qr_code: &Model) -> Result<bool, DbErr> {
+        if qr_code.max_scans.is_some()
+            || qr_code.access_password_hash.is_some()
+            || qr_code.pass_through_query
+            || qr_code.expires_at.is_some()
+            || qr_code.fallback_link.is_some()
+        {
+            return Ok(true);
+        }
+
+        Ok(!self.targeting.list(qr_code.id).await?.is_empty()
+            || !self.variants.list(qr_code.id).await?.is_empty()
+            || !self.schedule.list(qr_code.id).await?.is_empty())
+    }
+}
+
+/// Signs resolved destinations, so the continue link of the preview page leads where the
+/// preview said even if the resolver would pick another variant or rule by then.
+#[derive(Clone, Debug)]
+pub struct DestinationSigner {
+    secret: Arc<[u8]>,
+}
+
+impl DestinationSigner {
+    /// Without a `secret` a random one is used, signed destinations then stop working on
+    /// restart.
+    pub fn new(secret: Option<&[u8]>) -> Self {
+        let secret = match secret {
+            Some(secret) => Arc::from(secret),
+            None => {
+                let mut secret = [0; 32];
+                rand::rng().fill_bytes(&mut secret);
+                Arc::from(secret.as_slice())
+            }
+        };
+
+        Self { secret }
+    }
+
+    fn mac(&self, id: Uuid, variant: &str, link: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("hmac accepts keys of any size");
+        mac.update(b"destination");
+        mac.update(id.as_bytes());
+        mac.update(&expires.to_be_bytes());
+        mac.update(variant.as_bytes());
+        mac.update(b"\n");
+        mac.update(link.as_bytes());
+        mac
+    }
+
+    /// Signs the destination of a scan of the code `id`, valid until `expires`.
+    pub fn sign(&self, id: Uuid, destination: &Destination, expires: i64) -> String {
+        let variant = destination
+            .variant_id
+            .map(|x| x.simple().to_string())
+            .unwrap_or_default();
+        let signature = self
+            .mac(id, &variant, &destination.link, expires)
+            .finalize()
+            .into_bytes();
+
+        format!(
+            "{expires}.{variant}.{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(&destination.link),
+            BASE64_URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    /// Returns the signed destination if the token was issued for the code `id` and hasn't
+    /// expired by `now`.
+    pub fn verify(&self, id: Uuid, token: &str, now: i64) -> Option<Destination> {
+        let mut parts = token.split('.');
+        let (Some(expires), Some(variant), Some(link), Some(signature), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return None;
+        };
+        let expires = expires.parse::<i64>().ok().filter(|x| *x > now)?;
+        let link = String::from_utf8(BASE64_URL_SAFE_NO_PAD.decode(link).ok()?).ok()?;
+        let signature = BASE64_URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(id, variant, &link, expires)
+            .verify_slice(&signature)
+            .ok()?;
+
+        let variant_id = match variant {
+            "" => None,
+            variant => Some(variant.parse().ok()?),
+        };
+
+        Some(Destination { link, variant_id })
+    }
+}
+
+impl Default for DestinationSigner {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Max age of cached permanent redirects without an explicit `cache_max_age`.
+const DEFAULT_PERMANENT_MAX_AGE: i32 = 24 * 60 * 60;
+
+/// The status and `Cache-Control` header a redirect is answered with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    pub status: RedirectStatus,
+    pub cache_control: String,
+}
+
+fn temporary(status: RedirectStatus) -> RedirectStatus {
+    match status {
+        RedirectStatus::MovedPermanently => RedirectStatus::Found,
+        RedirectStatus::PermanentRedirect => RedirectStatus::TemporaryRedirect,
+        status => status,
+    }
+}
+
+impl RedirectPolicy {
+    /// Browsers and shared caches keep permanent redirects and ignore whatever happens to the
+    /// code afterwards, so only immutable codes without dynamic features get them. Others
+    /// fall back to the matching temporary status, dynamic codes uncached.
+    pub fn new(
+        status: RedirectStatus,
+        cache_max_age: Option<i32>,
+        immutable: bool,
+        dynamic: bool,
+    ) -> Self {
+        if dynamic {
+            return Self {
+                status: temporary(status),
+                cache_control: "no-store".to_string(),
+            };
+        }
+
+        let status = match immutable {
+            true => status,
+            false => temporary(status),
+        };
+        let cache_control = match (status, cache_max_age) {
+            (RedirectStatus::MovedPermanently | RedirectStatus::PermanentRedirect, max_age) => {
+                format!(
+                    "public, max-age={}",
+                    max_age.unwrap_or(DEFAULT_PERMANENT_MAX_AGE)
+                )
+            }
+            (_, Some(max_age)) if max_age > 0 => format!("private, max-age={max_age}"),
+            _ => "no-store".to_string(),
+        };
+
+        Self {
+            status,
+            cache_control,
+        }
+    }
+}
+
+/// UTM settings and extra query parameters that get merged into the destination link.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryParams {
+    pub utm_source: Option<String>,
+    pub utm_medium: Option<String>,
+    pub utm_campaign: Option<String>,
+    pub utm_content: Option<String>,
+    pub utm_term: Option<String>,
+    pub extra: BTreeMap<String, String>,
+    pub pass_through: bool,
+}
+
+impl QueryParams {
+    pub fn from_model(qr_code: &Model) -> Self {
+        let extra = qr_code
+            .extra_query
+            .clone()
+            .and_then(|x| serde_json::from_value(x).ok())
+            .unwrap_or_default();
+
+        Self {
+            utm_source: qr_code.utm_source.clone(),
+            utm_medium: qr_code.utm_medium.clone(),
+            utm_campaign: qr_code.utm_campaign.clone(),
+            utm_content: qr_code.utm_content.clone(),
+            utm_term: qr_code.utm_term.clone(),
+            extra,
+            pass_through: qr_code.pass_through_query,
+        }
+    }
+
+    pub(crate) fn extra_json(&self) -> Option<Json> {
+        (!self.extra.is_empty()).then(|| serde_json::json!(self.extra))
+    }
+
+    fn pairs(&self) -> impl Iterator<Item = (String, String)> {
+        [
+            ("utm_source", &self.utm_source),
+            ("utm_medium", &self.utm_medium),
+            ("utm_campaign", &self.utm_campaign),
+            ("utm_content", &self.utm_content),
+            ("utm_term", &self.utm_term),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.clone().map(|value| (key.to_string(), value)))
+        .chain(self.extra.clone())
+    }
+
+    /// Merges the parameters into `url`, replacing keys that are already present.
+    ///
+    /// The link's own parameters are overridden by the configured ones, and those by the
+    /// parameters of the scanned url if pass through is enabled.
+    pub fn apply(&self, url: &mut Url, scanned_query: &[(String, String)]) {
+        let passed_through = scanned_query.iter().filter(|_| self.pass_through).cloned();
+        let params: Vec<(String, String)> = self.pairs().chain(passed_through).collect();
+
+        if params.is_empty() {
+            return;
+        }
+
+        let mut pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        for (key, value) in params {
+            match pairs.iter_mut().find(|(x, _)| *x == key) {
+                Some(pair) => pair.1 = value,
+                None => pairs.push((key, value)),
+            }
+        }
+
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_immutable_static_codes_redirect_permanently() {
+        let policy = RedirectPolicy::new(RedirectStatus::PermanentRedirect, Some(3600), true, true);
+
+        assert_eq!(policy.status, RedirectStatus::TemporaryRedirect);
+        assert_eq!(policy.cache_control, "no-store");
+
+        let policy = RedirectPolicy::new(RedirectStatus::MovedPermanently, None, false, false);
+
+        assert_eq!(policy.status, RedirectStatus::Found);
+        assert_eq!(policy.cache_control, "no-store");
+
+        let policy = RedirectPolicy::new(RedirectStatus::MovedPermanently, Some(60), false, false);
+
+        assert_eq!(policy.status, RedirectStatus::Found);
+        assert_eq!(policy.cache_control, "private, max-age=60");
+
+        let policy = RedirectPolicy::new(RedirectStatus::MovedPermanently, None, true, false);
+
+        assert_eq!(policy.status, RedirectStatus::MovedPermanently);
+        assert_eq!(policy.cache_control, "public, max-age=86400");
+    }
+
+    #[test]
+    fn signed_destinations_are_bound_to_code_and_lifetime() {
+        let signer = DestinationSigner::new(Some(b"secret"));
+        let id = Uuid::new_v4();
+        let destination = Destination {
+            link: "https://example.com/b?x=1".to_string(),
+            variant_id: Some(Uuid::new_v4()),
+        };
+        let token = signer.sign(id, &destination, 2_000);
+
+        assert_eq!(signer.verify(id, &token, 1_000), Some(destination.clone()));
+        assert_eq!(signer.verify(id, &token, 2_000), None);
+        assert_eq!(signer.verify(Uuid::new_v4(), &token, 1_000), None);
+        assert_eq!(
+            signer.verify(id, &token.replace("2000", "3000"), 1_000),
+            None
+        );
+        let other_link = BASE64_URL_SAFE_NO_PAD.encode("https://evil.com");
+        let (head, signature) = token.rsplit_once('.').unwrap();
+        let (head, _) = head.rsplit_once('.').unwrap();
+        assert_eq!(
+            signer.verify(id, &format!("{head}.{other_link}.{signature}"), 1_000),
+            None
+        );
+        assert_eq!(
+            DestinationSigner::new(Some(b"other")).verify(id, &token, 1_000),
+            None
+        );
+
+        let without_variant = Destination {
+            variant_id: None,
+            ..destination
+        };
+        let token = signer.sign(id, &without_variant, 2_000);
+        assert_eq!(signer.verify(id, &token, 1_000), Some(without_variant));
+    }
+
+    #[test]
+    fn merges_params_in_order_of_precedence() {
+        let params = QueryParams {
+            utm_source: Some("poster".to_string()),
+            utm_medium: Some("print".to_string()),
+            extra: BTreeMap::from([("ref".to_string(), "qr".to_string())]),
+            pass_through: true,
+            ..Default::default()
+        };
+        let mut url = Url::parse("https://example.com/landing?utm_source=web&page=1").unwrap();
+
+        params.apply(&mut url, &[("utm_medium".to_string(), "flyer".to_string())]);
+
+        assert_eq!(
+            url.as_str(),
+            "https://example.com/landing?utm_source=poster&page=1&utm_medium=flyer&ref=qr"
+        );
+    }
+
+    #[test]
+    fn leaves_url_untouched_without_params() {
+        let params = QueryParams::default();
+        let mut url = Url::parse("https://example.com/landing").unwrap();
+
+        params.apply(&mut url, &[("foo".to_string(), "bar".to_string())]);
+
+        assert_eq!(url.as_str(), "https://example.com/landing");
+    }
+}
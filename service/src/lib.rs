@@ -1,6 +1,56 @@
+mod access;
+mod alias;
+mod api_key;
+mod audit;
+mod destination;
+mod link_health;
+mod link_policy;
+mod mail;
+mod oidc;
+mod organization;
+mod outbound;
+mod password;
 mod qrcode;
+mod rate_limit;
+mod recovery;
+mod revision;
+mod schedule;
+mod targeting;
+#[cfg(test)]
+mod testing;
+mod throttle;
+mod title;
+mod user;
+mod variant;
 
-pub use qrcode::{QrCodeDatabase, QrCodeGenerator, QrImageType};
+pub use access::{Action, Credential, role_allows};
+pub use alias::{AliasDatabase, AliasError, is_reserved_alias};
+pub use api_key::{ApiKeyDatabase, ApiKeyScope, api_key_scopes};
+pub use audit::{AuditContext, AuditDatabase, AuditFilter, IpHasher};
+pub use destination::{
+    Destination, DestinationResolver, DestinationSigner, QueryParams, RedirectPolicy,
+};
+pub use link_health::{HttpLinkProbe, LinkHealthDatabase, LinkProbe, ProbeResult};
+pub use link_policy::{LinkPolicy, LinkPolicyMode, LinkRejection};
+pub use mail::{
+    ConfiguredMailer, FileMailer, Mail, MailError, Mailer, SmtpConfig, SmtpMailer, SmtpSecurity,
+};
+pub use oidc::{OidcClient, OidcConfig, OidcError, OidcIdentity, OidcLogin};
+pub use organization::{OrganizationDatabase, OrganizationError, OrganizationMember};
+pub use password::{HashError, hash_password, token_hash, verify_password};
+pub use qrcode::{
+    QrCodeDatabase, QrCodeDatabaseError, QrCodeGenerator, QrCodeOptions, QrImageType, ScanOutcome,
+};
+pub use rate_limit::RateLimiter;
+pub use recovery::{PassphraseRecovery, RecoveryError};
+pub use revision::{RevisionDatabase, RevisionError};
+pub use schedule::ScheduleDatabase;
+pub use sea_orm::DbErr;
+pub use targeting::{ClientInfo, TargetingDatabase, TargetingRuleData};
+pub use throttle::{AttemptThrottle, report_passphrase_check};
+pub use title::TitleFetcher;
+pub use user::{UserDatabase, UserError};
+pub use variant::{VariantDatabase, VariantStats};
 
 pub static PACKAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub static PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
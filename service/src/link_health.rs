@@ -0,0 +1,476 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use ::entity::{
+    destination_schedule::{self, Entity as DbDestinationSchedule},
+    destination_variant::{self, Entity as DbDestinationVariant},
+    link_check::{self, Entity as DbLinkCheck},
+    organization_member::{self, Entity as DbOrganizationMember},
+    qr_code::{self, Entity as DbQrCode},
+    targeting_rule::{self, Entity as DbTargetingRule},
+};
+use chrono::Utc;
+use entity::link_check::Model;
+use reqwest::{Client, Method, StatusCode};
+use sea_orm::{
+    ActiveValue::Set,
+    ColumnTrait, Condition, DbConn, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    sea_query::{OnConflict, Query},
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    ScheduleDatabase,
+    outbound::{check_public_host, outbound_client},
+};
+
+/// Outcome of requesting a destination link once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProbeResult {
+    pub status_code: Option<u16>,
+    pub latency: Duration,
+    pub error: Option<String>,
+}
+
+impl ProbeResult {
+    pub fn is_healthy(&self) -> bool {
+        self.status_code
+            .is_some_and(|status| (200..400).contains(&status))
+    }
+}
+
+/// Requests a link and reports how it answered, mocked in tests.
+pub trait LinkProbe: Send + Sync {
+    fn probe(&self, url: &Url) -> impl Future<Output = ProbeResult> + Send;
+}
+
+/// Probes links with a `HEAD` request, falling back to `GET` for servers that don't
+/// support `HEAD`.
+///
+/// Redirects aren't followed, they count as healthy, and private addresses are refused.
+#[derive(Clone, Debug)]
+pub struct HttpLinkProbe {
+    client: Client,
+    public_only: bool,
+}
+
+impl HttpLinkProbe {
+    pub fn new(timeout: Duration) -> reqwest::Result<Self> {
+        Ok(Self {
+            client: outbound_client(timeout)?,
+            public_only: true,
+        })
+    }
+
+    async fn request(&self, method: Method, url: &Url) -> reqwest::Result<StatusCode> {
+        let response = self.client.request(method, url.clone()).send().await?;
+
+        Ok(response.status())
+    }
+}
+
+impl LinkProbe for HttpLinkProbe {
+    async fn probe(&self, url: &Url) -> ProbeResult {
+        let started = Instant::now();
+
+        if self.public_only
+            && let Err(why) = check_public_host(url)
+        {
+            return ProbeResult {
+                status_code: None,
+                latency: Duration::ZERO,
+                error: Some(why),
+            };
+        }
+
+        let result = match self.request(Method::HEAD, url).await {
+            Ok(StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED) => {
+                self.request(Method::GET, url).await
+            }
+            result => result,
+        };
+
+        match result {
+            Ok(status) => ProbeResult {
+                status_code: Some(status.as_u16()),
+                latency: started.elapsed(),
+                error: None,
+            },
+            Err(why) => ProbeResult {
+                status_code: None,
+                latency: started.elapsed(),
+                error: Some(why.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LinkHealthDatabase {
+    pub db_conn: DbConn,
+}
+
+impl LinkHealthDatabase {
+    /// Probes every destination of each qr code that isn't deleted, returns the number of
+    /// checked codes.
+    ///
+    /// A code is only healthy if all of its destinations are, the check records the first
+    /// broken one or otherwise the link of the code.
+    pub async fn check_all<P: LinkProbe>(&self, probe: &P) -> Result<usize, DbErr> {
+        let codes = DbQrCode::find()
+            .filter(qr_code::Column::DeletedAt.is_null())
+            .all(&self.db_conn)
+            .await?;
+
+        for qr_code in &codes {
+            let mut checked = None;
+            for link in self.destinations(qr_code).await? {
+                let result = match Url::parse(&link) {
+                    Ok(url) => probe.probe(&url).await,
+                    Err(why) => ProbeResult {
+                        status_code: None,
+                        latency: Duration::ZERO,
+                        error: Some(format!("invalid url, {why}")),
+                    },
+                };
+
+                let healthy = result.is_healthy();
+                if checked.is_none() || !healthy {
+                    checked = Some((link, result));
+                }
+                if !healthy {
+                    break;
+                }
+            }
+
+            if let Some((link, result)) = checked {
+                self.record(qr_code.id, link, result).await?;
+            }
+        }
+
+        Ok(codes.len())
+    }
+
+    /// Every link the code may redirect to, its own link first. Schedule entries that were
+    /// superseded are left out.
+    async fn destinations(&self, qr_code: &qr_code::Model) -> Result<Vec<String>, DbErr> {
+        let now = Utc::now();
+        let schedule = ScheduleDatabase {
+            db_conn: self.db_conn.clone(),
+        };
+
+        let mut links = vec![qr_code.link.clone()];
+        links.extend(qr_code.fallback_link.clone());
+        links.extend(
+            schedule
+                .current_link(qr_code.id, now, qr_code.link_changed_at)
+                .await?,
+        );
+        links.extend(
+            DbDestinationSchedule::find()
+                .select_only()
+                .column(destination_schedule::Column::Link)
+                .filter(destination_schedule::Column::QrCodeId.eq(qr_code.id))
+                .filter(destination_schedule::Column::StartsAt.gt(now))
+                .into_tuple::<String>()
+                .all(&self.db_conn)
+                .await?,
+        );
+        links.extend(
+            DbTargetingRule::find()
+                .select_only()
+                .column(targeting_rule::Column::Link)
+                .filter(targeting_rule::Column::QrCodeId.eq(qr_code.id))
+                .into_tuple::<String>()
+                .all(&self.db_conn)
+                .await?,
+        );
+        links.extend(
+            DbDestinationVariant::find()
+                .select_only()
+                .column(destination_variant::Column::Link)
+                .filter(destination_variant::Column::QrCodeId.eq(qr_code.id))
+                .into_tuple::<String>()
+                .all(&self.db_conn)
+                .await?,
+        );
+
+        let mut seen = HashSet::new();
+        links.retain(|link| seen.insert(link.clone()));
+
+        Ok(links)
+    }
+
+    pub async fn get(&self, qr_code_id: Uuid) -> Result<Option<Model>, DbErr> {
+        DbLinkCheck::find_by_id(qr_code_id).one(&self.db_conn).await
+    }
+
+    /// Lists the failed checks of the qr codes the user owns or can see through an
+    /// organization, most recent first. Deleted codes are left out.
+    pub async fn broken(&self, user_id: Uuid) -> Result<Vec<(Model, qr_code::Model)>, DbErr> {
+        let organizations = Query::select()
+            .column(organization_member::Column::OrganizationId)
+            .from(DbOrganizationMember)
+            .and_where(organization_member::Column::UserId.eq(user_id))
+            .to_owned();
+
+        let checks = DbLinkCheck::find()
+            .find_also_related(DbQrCode)
+            .filter(link_check::Column::Healthy.eq(false))
+            .filter(qr_code::Column::DeletedAt.is_null())
+            .filter(
+                Condition::any()
+                    .add(qr_code::Column::OwnerId.eq(user_id))
+                    .add(qr_code::Column::OrganizationId.in_subquery(organizations)),
+            )
+            .order_by_desc(link_check::Column::CheckedAt)
+            .all(&self.db_conn)
+            .await?;
+
+        Ok(checks
+            .into_iter()
+            .filter_map(|(check, qr_code)| Some((check, qr_code?)))
+            .collect())
+    }
+
+    async fn record(
+        &self,
+        qr_code_id: Uuid,
+        link: String,
+        result: ProbeResult,
+    ) -> Result<(), DbErr> {
+        let check = link_check::ActiveModel {
+            qr_code_id: Set(qr_code_id),
+            link: Set(link),
+            healthy: Set(result.is_healthy()),
+            status_code: Set(result.status_code.map(i32::from)),
+            latency_ms: Set(i32::try_from(result.latency.as_millis()).unwrap_or(i32::MAX)),
+            error: Set(result.error.map(|error| error.chars().take(255).collect())),
+            checked_at: Set(Utc::now()),
+        };
+
+        DbLinkCheck::insert(check)
+            .on_conflict(
+                OnConflict::column(link_check::Column::QrCodeId)
+                    .update_columns([
+                        link_check::Column::Link,
+                        link_check::Column::Healthy,
+                        link_check::Column::StatusCode,
+                        link_check::Column::LatencyMs,
+                        link_check::Column::Error,
+                        link_check::Column::CheckedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.db_conn)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{method, path},
+    };
+
+    use super::*;
+    use crate::{
+        AuditContext, Credential, QrCodeDatabase, QrCodeOptions, UserDatabase, VariantDatabase,
+        testing::database,
+    };
+
+    #[tokio::test]
+    async fn probes_links_against_a_local_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/ok"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/get-only"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/get-only"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        // The mock server listens on localhost, which the public client refuses.
+        let probe = HttpLinkProbe {
+            client: Client::new(),
+            public_only: false,
+        };
+        let url = |x: &str| Url::parse(&format!("{}{x}", server.uri())).unwrap();
+
+        let ok = probe.probe(&url("/ok")).await;
+        assert_eq!(ok.status_code, Some(200));
+        assert!(ok.is_healthy());
+
+        assert_eq!(probe.probe(&url("/get-only")).await.status_code, Some(204));
+
+        let missing = probe.probe(&url("/missing")).await;
+        assert_eq!(missing.status_code, Some(404));
+        assert!(!missing.is_healthy());
+    }
+
+    #[tokio::test]
+    async fn refuses_links_to_private_addresses() {
+        let probe = HttpLinkProbe::new(Duration::from_secs(5)).unwrap();
+
+        let result = probe
+            .probe(&Url::parse("http://169.254.169.254/latest/meta-data").unwrap())
+            .await;
+        assert_eq!(result.status_code, None);
+        assert!(!result.is_healthy());
+    }
+
+    /// Answers with the status code in the path of the link, e.g. `https://example.com/404`.
+    struct PathProbe;
+
+    impl LinkProbe for PathProbe {
+        async fn probe(&self, url: &Url) -> ProbeResult {
+            ProbeResult {
+                status_code: url.path()[1..].parse().ok(),
+                latency: Duration::from_millis(3),
+                error: None,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_the_broken_links_of_the_codes_of_the_user() {
+        let db_conn = database().await;
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let codes = QrCodeDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let owner = users
+            .register("owner@example.com", "password")
+            .await
+            .unwrap();
+        let other = users
+            .register("other@example.com", "password")
+            .await
+            .unwrap();
+
+        let context = AuditContext::default();
+        let create = |link: &str, owner_id: Option<Uuid>| {
+            let options = QrCodeOptions {
+                owner_id,
+                ..Default::default()
+            };
+            codes.create(Url::parse(link).unwrap(), options, &context)
+        };
+        let (healthy, _) = create("https://example.com/200", Some(owner.id))
+            .await
+            .unwrap();
+        let (broken, _) = create("https://example.com/404", Some(owner.id))
+            .await
+            .unwrap();
+        let (foreign, _) = create("https://example.com/500", Some(other.id))
+            .await
+            .unwrap();
+        create("https://example.com/503", None).await.unwrap();
+
+        let link_health = LinkHealthDatabase { db_conn };
+        assert_eq!(link_health.check_all(&PathProbe).await.unwrap(), 4);
+
+        let check = link_health.get(healthy.id).await.unwrap().unwrap();
+        assert!(check.healthy);
+        assert_eq!(check.status_code, Some(200));
+        assert_eq!(check.latency_ms, 3);
+
+        let broken_ids = |checks: Vec<(Model, qr_code::Model)>| {
+            checks
+                .into_iter()
+                .map(|(x, _)| x.qr_code_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            broken_ids(link_health.broken(owner.id).await.unwrap()),
+            [broken.id]
+        );
+        assert_eq!(
+            broken_ids(link_health.broken(other.id).await.unwrap()),
+            [foreign.id]
+        );
+    }
+
+    #[tokio::test]
+    async fn checks_every_destination_of_a_code() {
+        let db_conn = database().await;
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let codes = QrCodeDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let owner = users.register("a@example.com", "password").await.unwrap();
+        let options = QrCodeOptions {
+            owner_id: Some(owner.id),
+            fallback_link: Some(Url::parse("https://example.com/204").unwrap()),
+            ..Default::default()
+        };
+        let (qr_code, _) = codes
+            .create(
+                Url::parse("https://example.com/200").unwrap(),
+                options,
+                &AuditContext::default(),
+            )
+            .await
+            .unwrap();
+        let credential = || Credential::User(owner.id);
+
+        let link_health = LinkHealthDatabase {
+            db_conn: db_conn.clone(),
+        };
+        link_health.check_all(&PathProbe).await.unwrap();
+        let check = link_health.get(qr_code.id).await.unwrap().unwrap();
+        assert!(check.healthy);
+        assert_eq!(check.link, "https://example.com/200");
+
+        let variants = VariantDatabase {
+            db_conn: db_conn.clone(),
+        };
+        variants
+            .create(
+                qr_code.id,
+                credential(),
+                "b".to_string(),
+                Url::parse("https://example.com/404").unwrap(),
+                1,
+            )
+            .await
+            .unwrap();
+        link_health.check_all(&PathProbe).await.unwrap();
+        let check = link_health.get(qr_code.id).await.unwrap().unwrap();
+        assert!(!check.healthy);
+        assert_eq!(check.link, "https://example.com/404");
+
+        // Upcoming schedule entries are checked too, before they take over.
+        let schedule = ScheduleDatabase { db_conn };
+        schedule
+            .create(
+                qr_code.id,
+                credential(),
+                Url::parse("https://example.com/410").unwrap(),
+                Utc::now() + chrono::Duration::days(1),
+            )
+            .await
+            .unwrap();
+        link_health.check_all(&PathProbe).await.unwrap();
+        let check = link_health.get(qr_code.id).await.unwrap().unwrap();
+        assert_eq!(check.status_code, Some(410));
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use tokio::fs as async_fs;
+
+use thiserror::Error;
+use url::Url;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkPolicyMode {
+    /// Every link is allowed unless it matches an entry of the list.
+    #[default]
+    Blocklist,
+    /// Only links matching an entry of the list are allowed.
+    Allowlist,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LinkRejection {
+    #[error("only http and https links are allowed")]
+    Scheme,
+    #[error("the link is on the blocklist")]
+    Blocked,
+    #[error("the link is not on the allowlist")]
+    NotAllowed,
+}
+
+/// Entries of a list file, one per line, `#` starts a comment.
+///
+/// Entries containing `://` match links starting with them at a path boundary, all others
+/// match the domain and its subdomains.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct LinkRules {
+    domains: Vec<String>,
+    prefixes: Vec<String>,
+}
+
+impl LinkRules {
+    fn parse(content: &str) -> Self {
+        let mut rules = Self::default();
+
+        for entry in content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+        {
+            if entry.contains("://") {
+                rules.prefixes.push(entry.to_ascii_lowercase());
+            } else {
+                let domain = entry.trim_start_matches("*.");
+                rules.domains.push(
+                    domain
+                        .strip_suffix('.')
+                        .unwrap_or(domain)
+                        .to_ascii_lowercase(),
+                );
+            }
+        }
+
+        rules
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        // `evil.com.` is the fully qualified form of `evil.com`, not another host.
+        let mut url = url.clone();
+        if let Some(host) = url.host_str().and_then(|x| x.strip_suffix('.')) {
+            let host = host.to_string();
+            url.set_host(Some(&host)).ok();
+        }
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let link = url.as_str().to_ascii_lowercase();
+
+        self.domains.iter().any(|domain| {
+            host == *domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|x| x.ends_with('.'))
+        }) || self
+            .prefixes
+            .iter()
+            .any(|prefix| starts_at_boundary(&link, prefix))
+    }
+}
+
+/// Whether the link starts with the prefix and the prefix ends a path segment, so
+/// `https://example.com/bad` matches `/bad/page` but not `/badge`.
+fn starts_at_boundary(link: &str, prefix: &str) -> bool {
+    link.strip_prefix(prefix).is_some_and(|rest| {
+        rest.is_empty() || prefix.ends_with('/') || rest.starts_with(['/', '?', '#'])
+    })
+}
+
+#[derive(Debug, Default)]
+struct LoadedRules {
+    modified: Option<SystemTime>,
+    rules: LinkRules,
+}
+
+/// Decides which destination links may be stored and redirected to.
+///
+/// The list file is read again by [`LinkPolicy::reload_if_changed`] whenever it changes, so
+/// edits apply to existing codes without a restart. Checking a link never touches the disk.
+#[derive(Clone, Debug, Default)]
+pub struct LinkPolicy {
+    mode: LinkPolicyMode,
+    path: Option<PathBuf>,
+    loaded: Arc<RwLock<LoadedRules>>,
+}
+
+impl LinkPolicy {
+    pub fn new(mode: LinkPolicyMode, path: Option<PathBuf>) -> io::Result<Self> {
+        let policy = Self {
+            mode,
+            path,
+            loaded: Arc::default(),
+        };
+
+        if let Some(path) = &policy.path {
+            let loaded = LoadedRules {
+                modified: fs::metadata(path)?.modified().ok(),
+                rules: LinkRules::parse(&fs::read_to_string(path)?),
+            };
+            *policy.loaded.write().unwrap() = loaded;
+        }
+
+        Ok(policy)
+    }
+
+    pub fn check(&self, url: &Url) -> Result<(), LinkRejection> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(LinkRejection::Scheme);
+        }
+
+        let matches = self.loaded.read().unwrap().rules.matches(url);
+
+        match (self.mode, matches) {
+            (LinkPolicyMode::Blocklist, true) => Err(LinkRejection::Blocked),
+            (LinkPolicyMode::Allowlist, false) => Err(LinkRejection::NotAllowed),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the rules come from a list file that may change.
+    pub fn has_file(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Keeps the previous rules if the file can't be read, e.g. while it is being replaced.
+    pub async fn reload_if_changed(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let Ok(modified) = async_fs::metadata(path).await.and_then(|x| x.modified()) else {
+            return;
+        };
+        if self.loaded.read().unwrap().modified == Some(modified) {
+            return;
+        }
+
+        if let Ok(content) = async_fs::read_to_string(path).await {
+            *self.loaded.write().unwrap() = LoadedRules {
+                modified: Some(modified),
+                rules: LinkRules::parse(&content),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(mode: LinkPolicyMode, content: &str) -> LinkPolicy {
+        let policy = LinkPolicy::new(mode, None).unwrap();
+        policy.loaded.write().unwrap().rules = LinkRules::parse(content);
+        policy
+    }
+
+    #[test]
+    fn blocks_domains_subdomains_and_prefixes() {
+        let policy = policy(
+            LinkPolicyMode::Blocklist,
+            "# phishing\nevil.com\nhttps://example.com/bad # old campaign\n",
+        );
+        let check = |link: &str| policy.check(&Url::parse(link).unwrap());
+
+        assert_eq!(check("https://evil.com/x"), Err(LinkRejection::Blocked));
+        assert_eq!(check("https://login.evil.com"), Err(LinkRejection::Blocked));
+        assert_eq!(check("https://notevil.com"), Ok(()));
+        assert_eq!(
+            check("https://example.com/bad/page"),
+            Err(LinkRejection::Blocked)
+        );
+        assert_eq!(check("https://example.com/good"), Ok(()));
+        assert_eq!(check("javascript:alert(1)"), Err(LinkRejection::Scheme));
+    }
+
+    #[test]
+    fn matches_fully_qualified_hosts_and_whole_path_segments() {
+        let policy = policy(
+            LinkPolicyMode::Blocklist,
+            "evil.com\nhttps://example.com/bad\n",
+        );
+        let check = |link: &str| policy.check(&Url::parse(link).unwrap());
+
+        assert_eq!(check("https://evil.com./"), Err(LinkRejection::Blocked));
+        assert_eq!(
+            check("https://login.evil.com./x"),
+            Err(LinkRejection::Blocked)
+        );
+        assert_eq!(
+            check("https://example.com./bad"),
+            Err(LinkRejection::Blocked)
+        );
+        assert_eq!(
+            check("https://example.com/bad"),
+            Err(LinkRejection::Blocked)
+        );
+        assert_eq!(
+            check("https://example.com/bad?x=1"),
+            Err(LinkRejection::Blocked)
+        );
+        assert_eq!(check("https://example.com/badge"), Ok(()));
+        assert_eq!(check("https://example.com/bad.html"), Ok(()));
+    }
+
+    #[test]
+    fn allowlist_only_accepts_listed_links() {
+        let policy = policy(LinkPolicyMode::Allowlist, "intranet.local\n");
+        let check = |link: &str| policy.check(&Url::parse(link).unwrap());
+
+        assert_eq!(check("http://wiki.intranet.local/"), Ok(()));
+        assert_eq!(check("https://example.com"), Err(LinkRejection::NotAllowed));
+    }
+}
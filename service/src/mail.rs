@@ -0,0 +1,363 @@
+use std::{io, path::PathBuf, sync::Arc, time::Duration};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::Utc;
+use thiserror::Error;
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    time::timeout,
+};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{ClientConfig, RootCertStore, crypto::ring, pki_types::ServerName},
+};
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("mail could not be transmitted, {0}")]
+    Io(#[from] io::Error),
+    #[error("tls connection failed, {0}")]
+    Tls(String),
+    #[error("mail server did not answer in time")]
+    Timeout,
+    #[error("mail server rejected {command}, {reply}")]
+    Rejected { command: String, reply: String },
+    #[error("mail headers must not contain line breaks")]
+    InvalidHeader,
+}
+
+/// Delivers mails, mocked in tests.
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &Mail) -> impl Future<Output = Result<(), MailError>> + Send;
+}
+
+/// Appends mails to a file instead of sending them, for local testing.
+#[derive(Clone, Debug)]
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Mailer for FileMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        let entry = format!(
+            "Date: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+            Utc::now().to_rfc2822(),
+            mail.to,
+            mail.subject,
+            mail.body
+        );
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(entry.as_bytes()).await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plaintext connection, only meant for relays on the same host or network.
+    None,
+    /// Upgrades the connection with `STARTTLS`, usually on port 587.
+    #[default]
+    StartTls,
+    /// Connects with tls right away, usually on port 465.
+    Tls,
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+}
+
+/// Sends mails to a smtp server, authenticating with `AUTH PLAIN` if credentials are set.
+#[derive(Clone)]
+pub struct SmtpMailer {
+    config: Arc<SmtpConfig>,
+    tls: TlsConnector,
+}
+
+impl SmtpMailer {
+    pub fn new(config: SmtpConfig) -> Self {
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let tls = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        Self {
+            config: Arc::new(config),
+            tls: TlsConnector::from(Arc::new(tls)),
+        }
+    }
+
+    async fn deliver(&self, mail: &Mail) -> Result<(), MailError> {
+        let message = message(&self.config.from, mail)?;
+        let stream = TcpStream::connect((self.config.host.as_str(), self.config.port)).await?;
+
+        match self.config.security {
+            SmtpSecurity::None => {
+                let mut session = SmtpSession::new(stream);
+                session.expect("connect", 220).await?;
+                session.send(&self.config, &mail.to, &message).await
+            }
+            SmtpSecurity::Tls => {
+                let mut session = SmtpSession::new(self.upgrade(stream).await?);
+                session.expect("connect", 220).await?;
+                session.send(&self.config, &mail.to, &message).await
+            }
+            SmtpSecurity::StartTls => {
+                let mut session = SmtpSession::new(stream);
+                session.expect("connect", 220).await?;
+                session.hello(&self.config).await?;
+                session.command("STARTTLS", 220).await?;
+
+                let mut session = SmtpSession::new(self.upgrade(session.into_inner()).await?);
+                session.send(&self.config, &mail.to, &message).await
+            }
+        }
+    }
+
+    async fn upgrade<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        stream: S,
+    ) -> Result<tokio_rustls::client::TlsStream<S>, MailError> {
+        let server_name = ServerName::try_from(self.config.host.clone())
+            .map_err(|why| MailError::Tls(why.to_string()))?;
+
+        Ok(self.tls.connect(server_name, stream).await?)
+    }
+}
+
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        timeout(SMTP_TIMEOUT, self.deliver(mail))
+            .await
+            .map_err(|_| MailError::Timeout)?
+    }
+}
+
+/// Mailer chosen by the configuration of the application.
+#[derive(Clone)]
+pub enum ConfiguredMailer {
+    Smtp(SmtpMailer),
+    File(FileMailer),
+}
+
+impl Mailer for ConfiguredMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        match self {
+            Self::Smtp(mailer) => mailer.send(mail).await,
+            Self::File(mailer) => mailer.send(mail).await,
+        }
+    }
+}
+
+/// Builds a plain text message, lines starting with a dot are escaped for `DATA`.
+fn message(from: &str, mail: &Mail) -> Result<String, MailError> {
+    if [from, &mail.to, &mail.subject]
+        .iter()
+        .any(|x| x.contains(['\r', '\n']))
+    {
+        return Err(MailError::InvalidHeader);
+    }
+
+    let body = mail
+        .body
+        .lines()
+        .map(|line| match line.starts_with('.') {
+            true => format!(".{line}"),
+            false => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n");
+
+    Ok(format!(
+        "From: {from}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{body}",
+        mail.to,
+        mail.subject,
+        Utc::now().to_rfc2822()
+    ))
+}
+
+struct SmtpSession<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SmtpSession<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    async fn send(
+        &mut self,
+        config: &SmtpConfig,
+        to: &str,
+        message: &str,
+    ) -> Result<(), MailError> {
+        self.hello(config).await?;
+
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            let credentials = BASE64_STANDARD.encode(format!("\0{username}\0{password}"));
+            self.command(&format!("AUTH PLAIN {credentials}"), 235)
+                .await?;
+        }
+
+        self.command(&format!("MAIL FROM:<{}>", config.from), 250)
+            .await?;
+        self.command(&format!("RCPT TO:<{to}>"), 250).await?;
+        self.command("DATA", 354).await?;
+        self.write_line(&format!("{message}\r\n.")).await?;
+        self.expect("DATA", 250).await?;
+        self.command("QUIT", 221).await
+    }
+
+    async fn hello(&mut self, config: &SmtpConfig) -> Result<(), MailError> {
+        let domain = config.from.rsplit_once('@').map_or("localhost", |x| x.1);
+
+        self.command(&format!("EHLO {domain}"), 250).await
+    }
+
+    /// Sends a command, errors only name its verb so credentials don't end up in logs.
+    async fn command(&mut self, command: &str, expected: u16) -> Result<(), MailError> {
+        self.write_line(command).await?;
+
+        let verb = command.split([' ', ':']).next().unwrap_or_default();
+        self.expect(verb, expected).await
+    }
+
+    async fn write_line(&mut self, line: &str) -> Result<(), MailError> {
+        let stream = self.stream.get_mut();
+        stream.write_all(format!("{line}\r\n").as_bytes()).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+
+    /// Reads a possibly multiline reply, accepting any code of the same class as `expected`.
+    async fn expect(&mut self, command: &str, expected: u16) -> Result<(), MailError> {
+        let mut reply = String::new();
+
+        loop {
+            let start = reply.len();
+            if self.stream.read_line(&mut reply).await? == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            if reply.as_bytes().get(start + 3) != Some(&b'-') {
+                break;
+            }
+        }
+
+        let code: u16 = reply
+            .get(..3)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_default();
+        if code / 100 != expected / 100 {
+            return Err(MailError::Rejected {
+                command: command.to_string(),
+                reply: reply.trim_end().to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, duplex};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn sends_mails_through_a_smtp_dialog() {
+        let (client, server) = duplex(4096);
+
+        let server = tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            let mut received = String::new();
+
+            for reply in [
+                "250-mail.example.com\r\n250 AUTH PLAIN\r\n",
+                "235 ok\r\n",
+                "250 ok\r\n",
+                "250 ok\r\n",
+                "354 go ahead\r\n",
+            ] {
+                server.read_line(&mut received).await.unwrap();
+                server.get_mut().write_all(reply.as_bytes()).await.unwrap();
+            }
+            while !received.ends_with("\r\n.\r\n") {
+                server.read_line(&mut received).await.unwrap();
+            }
+            server.get_mut().write_all(b"250 queued\r\n").await.unwrap();
+            server.read_line(&mut received).await.unwrap();
+            server.get_mut().write_all(b"221 bye\r\n").await.unwrap();
+            server.read_to_string(&mut received).await.unwrap();
+
+            received
+        });
+
+        let config = SmtpConfig {
+            host: "mail.example.com".to_string(),
+            port: 25,
+            security: SmtpSecurity::None,
+            username: Some("qr".to_string()),
+            password: Some("secret".to_string()),
+            from: "qr@example.com".to_string(),
+        };
+        let mail = Mail {
+            to: "owner@example.com".to_string(),
+            subject: "Recovery".to_string(),
+            body: "first line\n.hidden".to_string(),
+        };
+
+        let mut session = SmtpSession::new(client);
+        session
+            .send(&config, &mail.to, &message(&config.from, &mail).unwrap())
+            .await
+            .unwrap();
+        drop(session);
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("EHLO example.com\r\nAUTH PLAIN AHFyAHNlY3JldA==\r\n"));
+        assert!(received.contains("RCPT TO:<owner@example.com>\r\nDATA\r\n"));
+        assert!(received.contains("Subject: Recovery\r\n"));
+        assert!(received.ends_with("first line\r\n..hidden\r\n.\r\nQUIT\r\n"));
+    }
+}
@@ -0,0 +1,395 @@
+use std::sync::Arc;
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::Utc;
+use rand::{Rng, distr::Alphanumeric};
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::OnceCell;
+use url::Url;
+
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("request to the identity provider failed, {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("the identity provider answered with status {0}")]
+    Status(u16),
+    #[error("the identity provider sent malformed json, {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("the discovery document belongs to issuer {0}")]
+    IssuerMismatch(String),
+    #[error("the id token is invalid, {0}")]
+    InvalidIdToken(&'static str),
+    #[error("the identity provider didn't share an email address")]
+    NoEmail,
+}
+
+#[derive(Clone, Debug)]
+pub struct OidcConfig {
+    /// Issuer identifier, compared exactly with the one in the discovery document and tokens.
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+    pub redirect_url: Url,
+}
+
+/// Login started by [`OidcClient::authorization_url`], kept by the browser until the
+/// provider redirects back.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OidcLogin {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+/// User the identity provider vouched for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OidcIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+    userinfo_endpoint: Option<Url>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    exp: i64,
+    nonce: Option<String>,
+    email: Option<String>,
+    email_verified: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserInfo {
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<bool>,
+}
+
+fn random_string(length: usize) -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+fn code_challenge(code_verifier: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+fn decode_claims(id_token: &str) -> Result<IdTokenClaims, OidcError> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or(OidcError::InvalidIdToken("it is no jwt"))?;
+    let payload = BASE64_URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|_| OidcError::InvalidIdToken("its payload is no base64"))?;
+
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+fn check_claims(
+    claims: &IdTokenClaims,
+    config: &OidcConfig,
+    nonce: &str,
+    now: i64,
+) -> Result<(), OidcError> {
+    let audience_matches = match &claims.aud {
+        Audience::One(aud) => *aud == config.client_id,
+        Audience::Many(aud) => aud.contains(&config.client_id),
+    };
+
+    if claims.iss != config.issuer {
+        return Err(OidcError::InvalidIdToken("it was issued by another issuer"));
+    }
+    if !audience_matches {
+        return Err(OidcError::InvalidIdToken(
+            "it was issued for another client",
+        ));
+    }
+    if claims.exp <= now {
+        return Err(OidcError::InvalidIdToken("it has expired"));
+    }
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(OidcError::InvalidIdToken("its nonce doesn't match"));
+    }
+
+    Ok(())
+}
+
+async fn fetch_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, OidcError> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(OidcError::Status(response.status().as_u16()));
+    }
+
+    Ok(serde_json::from_slice(&response.bytes().await?)?)
+}
+
+/// Logs users in with the authorization code flow of an OpenID Connect provider.
+///
+/// The provider is discovered on first use, so the server still starts while it is down.
+/// Id tokens are received directly from the token endpoint, their issuer is therefore
+/// trusted by the connection instead of their signature as OpenID Connect Core 3.1.3.7
+/// allows.
+#[derive(Clone, Debug)]
+pub struct OidcClient {
+    config: Arc<OidcConfig>,
+    http: Client,
+    metadata: Arc<OnceCell<ProviderMetadata>>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> reqwest::Result<Self> {
+        let http = Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .user_agent(concat!("qr/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        Ok(Self {
+            config: Arc::new(config),
+            http,
+            metadata: Arc::new(OnceCell::new()),
+        })
+    }
+
+    async fn metadata(&self) -> Result<&ProviderMetadata, OidcError> {
+        self.metadata.get_or_try_init(|| self.discover()).await
+    }
+
+    async fn discover(&self) -> Result<ProviderMetadata, OidcError> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = fetch_json(self.http.get(url)).await?;
+        if metadata.issuer != self.config.issuer {
+            return Err(OidcError::IssuerMismatch(metadata.issuer));
+        }
+
+        Ok(metadata)
+    }
+
+    /// Returns the url to send the browser to together with the login to check the
+    /// callback against.
+    pub async fn authorization_url(&self) -> Result<(Url, OidcLogin), OidcError> {
+        let metadata = self.metadata().await?;
+        let login = OidcLogin {
+            state: random_string(32),
+            nonce: random_string(32),
+            code_verifier: random_string(64),
+        };
+
+        let mut url = metadata.authorization_endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", self.config.redirect_url.as_str())
+            .append_pair("scope", &self.config.scopes.join(" "))
+            .append_pair("state", &login.state)
+            .append_pair("nonce", &login.nonce)
+            .append_pair("code_challenge", &code_challenge(&login.code_verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        Ok((url, login))
+    }
+
+    /// Exchanges the code of the callback for the identity of the user, the state must
+    /// already have been compared with the one of `login`.
+    pub async fn exchange(&self, code: &str, login: &OidcLogin) -> Result<OidcIdentity, OidcError> {
+        let metadata = self.metadata().await?;
+
+        let request = self
+            .http
+            .post(metadata.token_endpoint.clone())
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("code_verifier", &login.code_verifier),
+            ]);
+        let tokens: TokenResponse = fetch_json(request).await?;
+
+        let claims = decode_claims(&tokens.id_token)?;
+        check_claims(&claims, &self.config, &login.nonce, Utc::now().timestamp())?;
+
+        let (email, email_verified) = match (claims.email, &metadata.userinfo_endpoint) {
+            (Some(email), _) => (email, claims.email_verified.unwrap_or_default()),
+            (None, Some(userinfo_endpoint)) => {
+                let request = self
+                    .http
+                    .get(userinfo_endpoint.clone())
+                    .bearer_auth(&tokens.access_token);
+                let user_info: UserInfo = fetch_json(request).await?;
+                if user_info.sub != claims.sub {
+                    return Err(OidcError::InvalidIdToken("userinfo is about another user"));
+                }
+
+                (
+                    user_info.email.ok_or(OidcError::NoEmail)?,
+                    user_info.email_verified.unwrap_or_default(),
+                )
+            }
+            (None, None) => return Err(OidcError::NoEmail),
+        };
+
+        Ok(OidcIdentity {
+            issuer: claims.iss,
+            subject: claims.sub,
+            email,
+            email_verified,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_string_contains, method, path},
+    };
+
+    use super::*;
+
+    fn id_token(claims: serde_json::Value) -> String {
+        let encode = |x: serde_json::Value| BASE64_URL_SAFE_NO_PAD.encode(x.to_string());
+
+        format!(
+            "{}.{}.signature",
+            encode(json!({ "alg": "RS256" })),
+            encode(claims)
+        )
+    }
+
+    /// Starts a mock issuer whose token endpoint answers with the claims built from its
+    /// issuer and the nonce of the returned login.
+    async fn mock_issuer(
+        claims: impl Fn(&str, &str) -> serde_json::Value,
+    ) -> (MockServer, OidcClient, OidcLogin) {
+        let server = MockServer::start().await;
+        let issuer = server.uri();
+
+        Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{issuer}/authorize"),
+                "token_endpoint": format!("{issuer}/token"),
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OidcClient::new(OidcConfig {
+            issuer: issuer.clone(),
+            client_id: "qr".to_string(),
+            client_secret: "secret".to_string(),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            redirect_url: Url::parse("http://localhost/api/account/oidc/callback").unwrap(),
+        })
+        .unwrap();
+
+        let (url, login) = client.authorization_url().await.unwrap();
+        assert!(url.as_str().starts_with(&format!("{issuer}/authorize?")));
+        assert!(
+            url.query_pairs()
+                .any(|(key, value)| key == "state" && value == login.state)
+        );
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("code=abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "access",
+                "token_type": "Bearer",
+                "id_token": id_token(claims(&issuer, &login.nonce)),
+            })))
+            .mount(&server)
+            .await;
+
+        (server, client, login)
+    }
+
+    #[tokio::test]
+    async fn logs_in_against_a_mock_issuer() {
+        let exp = Utc::now().timestamp() + 60;
+        let (server, client, login) = mock_issuer(|iss, nonce| {
+            json!({
+                "iss": iss, "sub": "u-1", "aud": ["qr"], "exp": exp, "nonce": nonce,
+                "email": "Jane@example.com", "email_verified": true,
+            })
+        })
+        .await;
+
+        assert_eq!(
+            client.exchange("abc", &login).await.unwrap(),
+            OidcIdentity {
+                issuer: server.uri(),
+                subject: "u-1".to_string(),
+                email: "Jane@example.com".to_string(),
+                email_verified: true,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_id_tokens_that_were_not_meant_for_this_login() {
+        let exp = Utc::now().timestamp() + 60;
+        let (_server, client, login) = mock_issuer(|iss, _| {
+            json!({ "iss": iss, "sub": "u-1", "aud": "qr", "exp": exp, "nonce": "replayed" })
+        })
+        .await;
+        assert!(matches!(
+            client.exchange("abc", &login).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+
+        let (_server, client, login) = mock_issuer(|iss, nonce| {
+            json!({ "iss": iss, "sub": "u-1", "aud": "other", "exp": exp, "nonce": nonce })
+        })
+        .await;
+        assert!(matches!(
+            client.exchange("abc", &login).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+
+        let (_server, client, login) = mock_issuer(
+            |iss, nonce| json!({ "iss": iss, "sub": "u-1", "aud": "qr", "exp": 0, "nonce": nonce }),
+        )
+        .await;
+        assert!(matches!(
+            client.exchange("abc", &login).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+    }
+}
@@ -0,0 +1,237 @@
+use ::entity::{
+    organization::{self, Entity as DbOrganization},
+    organization_member::{self, Entity as DbOrganizationMember},
+    qr_code::{self, Entity as DbQrCode},
+    user::{self, Entity as DbUser},
+};
+use chrono::Utc;
+use entity::sea_orm_active_enums::OrganizationRole;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::access::{Action, organization_role, role_allows};
+
+#[derive(Debug, Error)]
+pub enum OrganizationError {
+    #[error("the organization does not exist or the user is no member")]
+    NotFound,
+    #[error("the role of the user does not allow this")]
+    Forbidden,
+    #[error("no user is registered with this email")]
+    UnknownUser,
+    #[error("the last owner can't be removed or demoted")]
+    LastOwner,
+    #[error("database operation failed, {0}")]
+    Database(#[from] DbErr),
+}
+
+/// Member of an organization together with the email of the user.
+#[derive(Clone, Debug)]
+pub struct OrganizationMember {
+    pub user_id: Uuid,
+    pub email: String,
+    pub role: OrganizationRole,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OrganizationDatabase {
+    pub db_conn: DbConn,
+}
+
+impl OrganizationDatabase {
+    /// Creates an organization with the user as its first owner.
+    pub async fn create(&self, user_id: Uuid, name: String) -> Result<organization::Model, DbErr> {
+        let txn = self.db_conn.begin().await?;
+
+        let organization = organization::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            name: Set(name),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&txn)
+        .await?;
+
+        organization_member::ActiveModel {
+            organization_id: Set(organization.id),
+            user_id: Set(user_id),
+            role: Set(OrganizationRole::Owner),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(organization)
+    }
+
+    /// Lists the organizations of the user with the role they have in each.
+    pub async fn list(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<(organization::Model, OrganizationRole)>, DbErr> {
+        let memberships = DbOrganizationMember::find()
+            .filter(organization_member::Column::UserId.eq(user_id))
+            .find_also_related(DbOrganization)
+            .order_by_asc(organization_member::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await?;
+
+        Ok(memberships
+            .into_iter()
+            .filter_map(|(member, organization)| organization.map(|x| (x, member.role)))
+            .collect())
+    }
+
+    pub async fn members(
+        &self,
+        organization_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<OrganizationMember>, OrganizationError> {
+        self.require(organization_id, user_id, Action::View).await?;
+
+        let members = DbOrganizationMember::find()
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .find_also_related(DbUser)
+            .order_by_asc(organization_member::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await?;
+
+        Ok(members
+            .into_iter()
+            .filter_map(|(member, user)| {
+                user.map(|user| OrganizationMember {
+                    user_id: member.user_id,
+                    email: user.email,
+                    role: member.role,
+                })
+            })
+            .collect())
+    }
+
+    /// Adds the user registered with `email` or changes their role, only owners may do this.
+    pub async fn set_member(
+        &self,
+        organization_id: Uuid,
+        user_id: Uuid,
+        email: &str,
+        role: OrganizationRole,
+    ) -> Result<OrganizationMember, OrganizationError> {
+        self.require(organization_id, user_id, Action::Manage)
+            .await?;
+
+        let Some(member_user) = DbUser::find()
+            .filter(user::Column::Email.eq(email.trim().to_lowercase()))
+            .one(&self.db_conn)
+            .await?
+        else {
+            return Err(OrganizationError::UnknownUser);
+        };
+
+        match organization_role(&self.db_conn, organization_id, member_user.id).await? {
+            Some(current) => {
+                if current == OrganizationRole::Owner && role != OrganizationRole::Owner {
+                    self.keep_an_owner(organization_id).await?;
+                }
+
+                organization_member::ActiveModel {
+                    organization_id: Set(organization_id),
+                    user_id: Set(member_user.id),
+                    role: Set(role),
+                    ..Default::default()
+                }
+                .update(&self.db_conn)
+                .await?;
+            }
+            None => {
+                organization_member::ActiveModel {
+                    organization_id: Set(organization_id),
+                    user_id: Set(member_user.id),
+                    role: Set(role),
+                    created_at: Set(Utc::now()),
+                }
+                .insert(&self.db_conn)
+                .await?;
+            }
+        }
+
+        Ok(OrganizationMember {
+            user_id: member_user.id,
+            email: member_user.email,
+            role,
+        })
+    }
+
+    /// Removes a member, owners may remove anyone and every member may leave on their own.
+    pub async fn remove_member(
+        &self,
+        organization_id: Uuid,
+        user_id: Uuid,
+        member_id: Uuid,
+    ) -> Result<(), OrganizationError> {
+        let action = match user_id == member_id {
+            true => Action::View,
+            false => Action::Manage,
+        };
+        self.require(organization_id, user_id, action).await?;
+
+        match organization_role(&self.db_conn, organization_id, member_id).await? {
+            Some(OrganizationRole::Owner) => self.keep_an_owner(organization_id).await?,
+            Some(_) => {}
+            None => return Err(OrganizationError::NotFound),
+        }
+
+        DbOrganizationMember::delete_by_id((organization_id, member_id))
+            .exec(&self.db_conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Lists the live qr codes of the organization, newest first.
+    pub async fn codes(
+        &self,
+        organization_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<qr_code::Model>, OrganizationError> {
+        self.require(organization_id, user_id, Action::View).await?;
+
+        Ok(DbQrCode::find()
+            .filter(qr_code::Column::OrganizationId.eq(organization_id))
+            .filter(qr_code::Column::DeletedAt.is_null())
+            .order_by_desc(qr_code::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await?)
+    }
+
+    async fn require(
+        &self,
+        organization_id: Uuid,
+        user_id: Uuid,
+        action: Action,
+    ) -> Result<(), OrganizationError> {
+        match organization_role(&self.db_conn, organization_id, user_id).await? {
+            Some(role) if role_allows(role, action) => Ok(()),
+            Some(_) => Err(OrganizationError::Forbidden),
+            None => Err(OrganizationError::NotFound),
+        }
+    }
+
+    /// Fails if the organization would be left without an owner once one is removed.
+    async fn keep_an_owner(&self, organization_id: Uuid) -> Result<(), OrganizationError> {
+        let owners = DbOrganizationMember::find()
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .filter(organization_member::Column::Role.eq(OrganizationRole::Owner))
+            .count(&self.db_conn)
+            .await?;
+
+        match owners > 1 {
+            true => Ok(()),
+            false => Err(OrganizationError::LastOwner),
+        }
+    }
+}
@@ -1,19 +1,44 @@
 use std::path::PathBuf;
 
-use ::entity::qr_code::{self, Entity as DbQrCode};
-use chrono::Utc;
-use entity::qr_code::{ActiveModel, Model};
+use ::entity::{
+    qr_code::{self, Entity as DbQrCode},
+    qr_code_alias::Entity as DbQrCodeAlias,
+    scan_event,
+};
+use chrono::{DateTime, Duration, Utc};
+use entity::{
+    qr_code::{ActiveModel, Model},
+    sea_orm_active_enums::{AuditAction, RedirectStatus},
+};
 use image::{
     ImageEncoder, ImageError, Luma,
     codecs::{jpeg::JpegEncoder, png::PngEncoder},
 };
 use qrcode::{QrCode, render::svg, types::QrError};
-use rand::{distr::Alphanumeric, Rng};
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, DbConn, DbErr, EntityTrait};
+use rand::{Rng, distr::Alphanumeric};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, ConnectionTrait, DbConn, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, Select, TransactionTrait, sea_query::Expr,
+};
+use subtle::ConstantTimeEq;
 use thiserror::Error;
 use url::Url;
 use uuid::Uuid;
 
+use crate::{
+    QueryParams,
+    access::{
+        Action, Credential, authorize, authorize_organization, find_authorized, find_mutable,
+        find_visible,
+    },
+    alias::generate_slug,
+    audit::{AuditContext, actor, record_audit, user_actor},
+    password::{HashError, hash_password, verify_password},
+    revision::record_revision,
+    throttle::passphrase_checked,
+};
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Error)]
 pub enum QrGeneratorError {
     #[error("error during qr code generation, {0}")]
@@ -24,6 +49,53 @@ pub enum QrGeneratorError {
     DataBaseError(#[from] DbErr),
 }
 
+#[derive(Debug, Error)]
+pub enum QrCodeDatabaseError {
+    #[error("database operation failed, {0}")]
+    Database(#[from] DbErr),
+    #[error("password hashing failed, {0}")]
+    Hash(#[from] HashError),
+    #[error("not allowed to add codes to this organization")]
+    Forbidden,
+    #[error("immutable codes can't be edited")]
+    Immutable,
+}
+
+/// Optional per code settings, fields left at `None` keep their default or current value.
+#[derive(Clone, Debug, Default)]
+pub struct QrCodeOptions {
+    pub max_scans: Option<i32>,
+    /// Lets an existing code be scanned without a limit again.
+    pub clear_max_scans: bool,
+    pub access_password: Option<String>,
+    /// Removes the access password of an existing code.
+    pub clear_access_password: bool,
+    pub sticky_variants: Option<bool>,
+    pub query_params: Option<QueryParams>,
+    pub redirect_status: Option<RedirectStatus>,
+    pub cache_max_age: Option<i32>,
+    /// Removes the cache lifetime of an existing code.
+    pub clear_cache_max_age: bool,
+    pub preview: Option<bool>,
+    pub preview_title: Option<String>,
+    pub fallback_link: Option<Url>,
+    /// Removes the fallback link of an existing code.
+    pub clear_fallback_link: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Removes the expiry of an existing code.
+    pub clear_expires_at: bool,
+    pub owner_email: Option<String>,
+    /// Removes the owner email of an existing code, which disables recovering its passphrase.
+    pub clear_owner_email: bool,
+    pub owner_id: Option<Uuid>,
+    /// Requires the editor role in the organization, moving an existing code also requires
+    /// managing it.
+    pub organization_id: Option<Uuid>,
+    /// Only set on new codes, immutable codes may be redirected permanently but can't be
+    /// edited afterwards.
+    pub immutable: Option<bool>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum QrImageType {
     Png,
@@ -39,6 +111,75 @@ fn generate_passphrase(len: usize) -> String {
         .collect()
 }
 
+fn set_query_params(active: &mut ActiveModel, query_params: QueryParams) {
+    active.extra_query = Set(query_params.extra_json());
+    active.utm_source = Set(query_params.utm_source);
+    active.utm_medium = Set(query_params.utm_medium);
+    active.utm_campaign = Set(query_params.utm_campaign);
+    active.utm_content = Set(query_params.utm_content);
+    active.utm_term = Set(query_params.utm_term);
+    active.pass_through_query = Set(query_params.pass_through);
+}
+
+/// Selects a qr code by id, ignoring codes that were moved to the trash.
+pub(crate) fn find_live(id: Uuid) -> Select<DbQrCode> {
+    DbQrCode::find_by_id(id).filter(qr_code::Column::DeletedAt.is_null())
+}
+
+/// Checks the passphrase of the qr code in constant time.
+///
+/// Codes created before passphrases were hashed still store them in plaintext, those are
+/// replaced by their hash on the first successful check.
+pub(crate) async fn check_passphrase(
+    db_conn: &DbConn,
+    qr_code: Model,
+    passphrase: &str,
+) -> Result<Option<Model>, DbErr> {
+    if qr_code.passphrase_hashed {
+        let matches = verify_password(passphrase, &qr_code.passphrase);
+        passphrase_checked(matches);
+
+        return Ok(matches.then_some(qr_code));
+    }
+
+    let matches = qr_code
+        .passphrase
+        .as_bytes()
+        .ct_eq(passphrase.as_bytes())
+        .into();
+    passphrase_checked(matches);
+    if !matches {
+        return Ok(None);
+    }
+
+    let Ok(hash) = hash_password(passphrase) else {
+        return Ok(Some(qr_code));
+    };
+
+    let mut active: ActiveModel = qr_code.into();
+    active.passphrase = Set(hash);
+    active.passphrase_hashed = Set(true);
+    let qr_code = active.update(db_conn).await?;
+
+    Ok(Some(qr_code))
+}
+
+/// Replaces the passphrase of the qr code with a new one, returned in plaintext.
+pub(crate) async fn replace_passphrase<C: ConnectionTrait>(
+    db_conn: &C,
+    qr_code: Model,
+) -> Result<(Model, String), QrCodeDatabaseError> {
+    let passphrase = generate_passphrase(32);
+
+    let mut active: ActiveModel = qr_code.into();
+    active.passphrase = Set(hash_password(&passphrase)?);
+    active.passphrase_hashed = Set(true);
+    active.modified_at = Set(Some(Utc::now()));
+    let qr_code = active.update(db_conn).await?;
+
+    Ok((qr_code, passphrase))
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct QrCodeGenerator {
     pub db_conn: DbConn,
@@ -48,7 +189,7 @@ pub struct QrCodeGenerator {
 
 impl QrCodeGenerator {
     pub async fn generate_and_save(&self, id: Uuid) -> Result<Option<String>, QrGeneratorError> {
-        let Some(qr_code) = DbQrCode::find_by_id(id).one(&self.db_conn).await? else {
+        let Some(qr_code) = find_live(id).one(&self.db_conn).await? else {
             return Ok(None);
         };
 
@@ -67,12 +208,25 @@ impl QrCodeGenerator {
         &self,
         id: Uuid,
         image_type: QrImageType,
+        viewer: Option<Uuid>,
     ) -> Result<Option<Vec<u8>>, QrGeneratorError> {
-        let Some(qr_code) = DbQrCode::find_by_id(id).one(&self.db_conn).await? else {
+        let Some(qr_code) = find_visible(&self.db_conn, id, viewer).await? else {
             return Ok(None);
         };
-        
-        let code = QrCode::new(&format!("{}/api/redirect?id={}", self.server_url, qr_code.id))?;
+
+        let slug = match qr_code.slug {
+            Some(slug) => slug,
+            None => {
+                // Codes created before short links existed get their slug on first render.
+                let slug = generate_slug(&self.db_conn).await?;
+                let mut active: ActiveModel = qr_code.into();
+                active.slug = Set(Some(slug.clone()));
+                active.update(&self.db_conn).await?;
+                slug
+            }
+        };
+
+        let code = QrCode::new(format!("{}/r/{}", self.server_url, slug))?;
 
         let image = code.render::<Luma<u8>>().build();
         let height = image.height();
@@ -105,69 +259,845 @@ impl QrCodeGenerator {
     }
 }
 
+/// What became of a scan, see [`QrCodeDatabase::register_scan`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanOutcome {
+    Counted,
+    /// The scan limit of the code is already reached.
+    Exhausted,
+    /// The code was deleted since it was looked up.
+    NotFound,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct QrCodeDatabase {
     pub db_conn: DbConn,
 }
 
 impl QrCodeDatabase {
-    pub async fn create(&self, link: Url) -> Result<Model, DbErr> {
+    /// Creates a qr code, returns it together with its plaintext passphrase which is only
+    /// stored as a hash.
+    pub async fn create(
+        &self,
+        link: Url,
+        options: QrCodeOptions,
+        context: &AuditContext,
+    ) -> Result<(Model, String), QrCodeDatabaseError> {
+        if let Some(organization_id) = options.organization_id {
+            let credential = options.owner_id.map(Credential::User);
+            self.check_organization(organization_id, credential.as_ref())
+                .await?;
+        }
+
         let passphrase = generate_passphrase(32);
+        let passphrase_hash = hash_password(&passphrase)?;
+        let access_password_hash = options
+            .access_password
+            .map(|password| hash_password(&password))
+            .transpose()?;
 
-        let qr_code = qr_code::ActiveModel {
+        let mut active = qr_code::ActiveModel {
             id: Set(uuid::Uuid::new_v4()),
             link: Set(link.to_string()),
-            passphrase: Set(passphrase),
+            passphrase: Set(passphrase_hash),
+            passphrase_hashed: Set(true),
             created_at: Set(Utc::now()),
+            scan_count: Set(0),
+            max_scans: Set(options.max_scans),
+            access_password_hash: Set(access_password_hash),
+            sticky_variants: Set(options.sticky_variants.unwrap_or_default()),
+            slug: Set(Some(generate_slug(&self.db_conn).await?)),
+            redirect_status: Set(options.redirect_status.unwrap_or(RedirectStatus::Found)),
+            cache_max_age: Set(options.cache_max_age),
+            active: Set(true),
+            preview: Set(options.preview.unwrap_or_default()),
+            preview_title: Set(options.preview_title),
+            fallback_link: Set(options.fallback_link.map(|x| x.to_string())),
+            expires_at: Set(options.expires_at),
+            owner_email: Set(options.owner_email),
+            owner_id: Set(options.owner_id),
+            organization_id: Set(options.organization_id),
+            immutable: Set(options.immutable.unwrap_or_default()),
+            ..Default::default()
+        };
+        set_query_params(&mut active, options.query_params.unwrap_or_default());
+        let txn = self.db_conn.begin().await?;
+        let qr_code = active.insert(&txn).await?;
+
+        let actor = options
+            .owner_id
+            .map_or_else(|| "anonymous".to_string(), user_actor);
+        record_audit(&txn, context, AuditAction::Create, actor, None, &qr_code).await?;
+        txn.commit().await?;
+
+        Ok((qr_code, passphrase))
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<Model>, DbErr> {
+        let Some(qr_code) = find_live(id).one(&self.db_conn).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(qr_code))
+    }
+
+    /// Like [`Self::get`], but hides codes of organizations the viewer isn't a member of.
+    pub async fn get_visible(
+        &self,
+        id: Uuid,
+        viewer: Option<Uuid>,
+    ) -> Result<Option<Model>, DbErr> {
+        find_visible(&self.db_conn, id, viewer).await
+    }
+
+    /// Like [`Self::get`], but only finds codes the credential allows `action` on.
+    pub async fn get_authorized(
+        &self,
+        id: Uuid,
+        credential: &Credential,
+        action: Action,
+    ) -> Result<Option<Model>, DbErr> {
+        find_authorized(&self.db_conn, id, credential, action).await
+    }
+
+    /// Whether the credential allows viewing the qr code, which includes reading the links
+    /// of codes with an access password.
+    pub async fn may_view(&self, qr_code: &Model, credential: &Credential) -> Result<bool, DbErr> {
+        let authorized =
+            authorize(&self.db_conn, qr_code.clone(), credential, Action::View).await?;
+
+        Ok(authorized.is_some())
+    }
+
+    /// Looks up a qr code by its generated slug or one of its aliases.
+    pub async fn find_by_slug(&self, slug: &str) -> Result<Option<Model>, DbErr> {
+        if let Some(qr_code) = DbQrCode::find()
+            .filter(qr_code::Column::Slug.eq(slug))
+            .one(&self.db_conn)
+            .await?
+        {
+            return Ok(qr_code.deleted_at.is_none().then_some(qr_code));
+        }
+
+        let Some(alias) = DbQrCodeAlias::find_by_id(slug).one(&self.db_conn).await? else {
+            return Ok(None);
+        };
+
+        self.get(alias.qr_code_id).await
+    }
+
+    /// Counts a scan of the qr code, unless its scan limit is already reached or it was
+    /// deleted.
+    ///
+    /// The limit check and the increment happen in a single `UPDATE`, so concurrent scans
+    /// can never push the counter past `max_scans`.
+    pub async fn register_scan(
+        &self,
+        id: Uuid,
+        variant_id: Option<Uuid>,
+    ) -> Result<ScanOutcome, DbErr> {
+        let result = DbQrCode::update_many()
+            .col_expr(
+                qr_code::Column::ScanCount,
+                Expr::col(qr_code::Column::ScanCount).add(1),
+            )
+            .filter(qr_code::Column::Id.eq(id))
+            .filter(qr_code::Column::DeletedAt.is_null())
+            .filter(
+                Condition::any()
+                    .add(qr_code::Column::MaxScans.is_null())
+                    .add(
+                        Expr::col(qr_code::Column::ScanCount)
+                            .lt(Expr::col(qr_code::Column::MaxScans)),
+                    ),
+            )
+            .exec(&self.db_conn)
+            .await?;
+
+        if result.rows_affected == 0 {
+            return match find_live(id).one(&self.db_conn).await? {
+                Some(_) => Ok(ScanOutcome::Exhausted),
+                None => Ok(ScanOutcome::NotFound),
+            };
+        }
+
+        scan_event::ActiveModel {
+            qr_code_id: Set(id),
+            variant_id: Set(variant_id),
+            scanned_at: Set(Utc::now()),
             ..Default::default()
         }
         .insert(&self.db_conn)
         .await?;
 
-        Ok(qr_code)
+        Ok(ScanOutcome::Counted)
     }
 
-    pub async fn get(&self, id: Uuid) -> Result<Option<Model>, DbErr> {
-        let Some(qr_code) = DbQrCode::find_by_id(id).one(&self.db_conn).await? else {
+    pub async fn update(
+        &self,
+        id: Uuid,
+        credential: Credential,
+        link: Url,
+        options: QrCodeOptions,
+        context: &AuditContext,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let action = match options.organization_id {
+            Some(_) => Action::Manage,
+            None => Action::Edit,
+        };
+        let Some(qr_code) = find_mutable(&self.db_conn, id, &credential, action).await? else {
             return Ok(None);
         };
+        if let Some(organization_id) = options.organization_id {
+            self.check_organization(organization_id, Some(&credential))
+                .await?;
+        }
+
+        let link = link.to_string();
+        let link_changed = link != qr_code.link;
+        let txn = self.db_conn.begin().await?;
+        if link_changed {
+            record_revision(&txn, id, qr_code.link.clone(), actor(&credential)).await?;
+        }
+
+        let old = qr_code.clone();
+        let mut active: ActiveModel = qr_code.into();
+        active.link = Set(link);
+        if link_changed {
+            active.link_changed_at = Set(Some(Utc::now()));
+        }
+        if let Some(max_scans) = options.max_scans {
+            active.max_scans = Set(Some(max_scans));
+        } else if options.clear_max_scans {
+            active.max_scans = Set(None);
+        }
+        if let Some(access_password) = options.access_password {
+            active.access_password_hash = Set(Some(hash_password(&access_password)?));
+        } else if options.clear_access_password {
+            active.access_password_hash = Set(None);
+        }
+        if let Some(sticky_variants) = options.sticky_variants {
+            active.sticky_variants = Set(sticky_variants);
+        }
+        if let Some(query_params) = options.query_params {
+            set_query_params(&mut active, query_params);
+        }
+        if let Some(redirect_status) = options.redirect_status {
+            active.redirect_status = Set(redirect_status);
+        }
+        if let Some(cache_max_age) = options.cache_max_age {
+            active.cache_max_age = Set(Some(cache_max_age));
+        } else if options.clear_cache_max_age {
+            active.cache_max_age = Set(None);
+        }
+        if let Some(preview) = options.preview {
+            active.preview = Set(preview);
+        }
+        if let Some(preview_title) = options.preview_title {
+            active.preview_title = Set(Some(preview_title));
+        }
+        if let Some(fallback_link) = options.fallback_link {
+            active.fallback_link = Set(Some(fallback_link.to_string()));
+        } else if options.clear_fallback_link {
+            active.fallback_link = Set(None);
+        }
+        if let Some(expires_at) = options.expires_at {
+            active.expires_at = Set(Some(expires_at));
+        } else if options.clear_expires_at {
+            active.expires_at = Set(None);
+        }
+        if let Some(owner_email) = options.owner_email {
+            active.owner_email = Set(Some(owner_email));
+        } else if options.clear_owner_email {
+            active.owner_email = Set(None);
+        }
+        if let Some(owner_id) = options.owner_id {
+            active.owner_id = Set(Some(owner_id));
+        }
+        if let Some(organization_id) = options.organization_id {
+            active.organization_id = Set(Some(organization_id));
+        }
+        active.modified_at = Set(Some(Utc::now()));
+        let qr_code = active.update(&txn).await?;
+
+        record_audit(
+            &txn,
+            context,
+            AuditAction::Update,
+            actor(&credential),
+            Some(&old),
+            &qr_code,
+        )
+        .await?;
+        txn.commit().await?;
 
         Ok(Some(qr_code))
     }
 
-    pub async fn update(
+    /// Pauses or resumes the redirect of the qr code.
+    pub async fn set_active(
+        &self,
+        id: Uuid,
+        credential: Credential,
+        active: bool,
+        context: &AuditContext,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let Some(qr_code) = find_mutable(&self.db_conn, id, &credential, Action::Edit).await?
+        else {
+            return Ok(None);
+        };
+
+        let txn = self.db_conn.begin().await?;
+        let old = qr_code.clone();
+        let mut active_model: ActiveModel = qr_code.into();
+        active_model.active = Set(active);
+        active_model.modified_at = Set(Some(Utc::now()));
+        let qr_code = active_model.update(&txn).await?;
+
+        let action = match active {
+            true => AuditAction::Resume,
+            false => AuditAction::Pause,
+        };
+        record_audit(
+            &txn,
+            context,
+            action,
+            actor(&credential),
+            Some(&old),
+            &qr_code,
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(Some(qr_code))
+    }
+
+    /// Lists the qr codes owned by the user that aren't deleted, newest first.
+    pub async fn list_owned(&self, user_id: Uuid) -> Result<Vec<Model>, DbErr> {
+        DbQrCode::find()
+            .filter(qr_code::Column::OwnerId.eq(user_id))
+            .filter(qr_code::Column::DeletedAt.is_null())
+            .order_by_desc(qr_code::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await
+    }
+
+    /// Transfers an anonymous qr code to the user, the passphrase keeps working.
+    pub async fn claim(
         &self,
         id: Uuid,
         passphrase: String,
-        link: Url,
+        user_id: Uuid,
+        context: &AuditContext,
     ) -> Result<Option<Model>, DbErr> {
-        let Some(qr_code) = DbQrCode::find_by_id(id).one(&self.db_conn).await? else {
+        let credential = Credential::Passphrase(passphrase);
+        let Some(qr_code) = find_authorized(&self.db_conn, id, &credential, Action::Manage).await?
+        else {
             return Ok(None);
         };
 
-        if qr_code.passphrase != passphrase {
+        let txn = self.db_conn.begin().await?;
+        let old = qr_code.clone();
+        let mut active: ActiveModel = qr_code.into();
+        active.owner_id = Set(Some(user_id));
+        active.modified_at = Set(Some(Utc::now()));
+        let qr_code = active.update(&txn).await?;
+
+        record_audit(
+            &txn,
+            context,
+            AuditAction::Claim,
+            user_actor(user_id),
+            Some(&old),
+            &qr_code,
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(Some(qr_code))
+    }
+
+    /// Swaps the passphrase for a new one, the old one stops working immediately.
+    pub async fn rotate_passphrase(
+        &self,
+        id: Uuid,
+        credential: Credential,
+        context: &AuditContext,
+    ) -> Result<Option<(Model, String)>, QrCodeDatabaseError> {
+        let Some(qr_code) = find_authorized(&self.db_conn, id, &credential, Action::Manage).await?
+        else {
             return Ok(None);
-        }
+        };
+
+        let txn = self.db_conn.begin().await?;
+        let old = qr_code.clone();
+        let (qr_code, passphrase) = replace_passphrase(&txn, qr_code).await?;
 
+        record_audit(
+            &txn,
+            context,
+            AuditAction::RotatePassphrase,
+            actor(&credential),
+            Some(&old),
+            &qr_code,
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(Some((qr_code, passphrase)))
+    }
+
+    pub async fn delete(
+        &self,
+        id: Uuid,
+        credential: Credential,
+        context: &AuditContext,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let Some(qr_code) = find_mutable(&self.db_conn, id, &credential, Action::Manage).await?
+        else {
+            return Ok(None);
+        };
+
+        let txn = self.db_conn.begin().await?;
+        let old = qr_code.clone();
         let mut active: ActiveModel = qr_code.into();
-        active.link = Set(link.to_string());
-        active.modified_at = Set(Some(Utc::now()));
-        let qr_code = active.update(&self.db_conn).await?;
+        active.deleted_at = Set(Some(Utc::now()));
+        let qr_code = active.update(&txn).await?;
+
+        record_audit(
+            &txn,
+            context,
+            AuditAction::Delete,
+            actor(&credential),
+            Some(&old),
+            &qr_code,
+        )
+        .await?;
+        txn.commit().await?;
 
         Ok(Some(qr_code))
     }
 
-    pub async fn delete(&self, id: Uuid, passphrase: String) -> Result<Option<Model>, DbErr> {
-        let Some(qr_code) = DbQrCode::find_by_id(id).one(&self.db_conn).await? else {
+    /// Takes a qr code back out of the trash, as long as it was deleted within `retention`.
+    pub async fn restore(
+        &self,
+        id: Uuid,
+        credential: Credential,
+        retention: Duration,
+        context: &AuditContext,
+    ) -> Result<Option<Model>, DbErr> {
+        let Some(qr_code) = DbQrCode::find_by_id(id)
+            .filter(qr_code::Column::DeletedAt.gt(Utc::now() - retention))
+            .one(&self.db_conn)
+            .await?
+        else {
             return Ok(None);
         };
 
-        if qr_code.passphrase != passphrase {
+        let Some(qr_code) = authorize(&self.db_conn, qr_code, &credential, Action::Manage).await?
+        else {
             return Ok(None);
-        }
+        };
 
-        DbQrCode::delete_by_id(id).exec(&self.db_conn).await?;
+        let txn = self.db_conn.begin().await?;
+        let old = qr_code.clone();
+        let mut active: ActiveModel = qr_code.into();
+        active.deleted_at = Set(None);
+        active.modified_at = Set(Some(Utc::now()));
+        let qr_code = active.update(&txn).await?;
+
+        record_audit(
+            &txn,
+            context,
+            AuditAction::Restore,
+            actor(&credential),
+            Some(&old),
+            &qr_code,
+        )
+        .await?;
+        txn.commit().await?;
 
         Ok(Some(qr_code))
     }
+
+    async fn check_organization(
+        &self,
+        organization_id: Uuid,
+        credential: Option<&Credential>,
+    ) -> Result<(), QrCodeDatabaseError> {
+        let allowed = match credential {
+            Some(Credential::User(user_id)) => {
+                authorize_organization(&self.db_conn, organization_id, *user_id, Action::Edit)
+                    .await?
+            }
+            _ => false,
+        };
+
+        match allowed {
+            true => Ok(()),
+            false => Err(QrCodeDatabaseError::Forbidden),
+        }
+    }
+
+    /// Permanently removes qr codes that are in the trash for longer than `retention`.
+    pub async fn purge_deleted(&self, retention: Duration) -> Result<u64, DbErr> {
+        let result = DbQrCode::delete_many()
+            .filter(qr_code::Column::DeletedAt.lte(Utc::now() - retention))
+            .exec(&self.db_conn)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::task::JoinSet;
+
+    use super::*;
+    use crate::{
+        LinkPolicy, RevisionDatabase, RevisionError, ScheduleDatabase, TargetingDatabase,
+        TargetingRuleData, UserDatabase, VariantDatabase, report_passphrase_check,
+        testing::database,
+    };
+
+    fn link(x: &str) -> Url {
+        Url::parse(x).unwrap()
+    }
+
+    fn immutable<T>(result: Result<Option<T>, QrCodeDatabaseError>) -> bool {
+        matches!(result, Err(QrCodeDatabaseError::Immutable))
+    }
+
+    #[tokio::test]
+    async fn refuses_to_change_where_immutable_codes_resolve() {
+        let db_conn = database().await;
+        let codes = QrCodeDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let owner = users.register("a@example.com", "password").await.unwrap();
+        let context = AuditContext::default();
+        let options = QrCodeOptions {
+            redirect_status: Some(RedirectStatus::MovedPermanently),
+            owner_id: Some(owner.id),
+            immutable: Some(true),
+            ..Default::default()
+        };
+        let (qr_code, _) = codes
+            .create(link("https://example.com"), options, &context)
+            .await
+            .unwrap();
+        let id = qr_code.id;
+        let credential = || Credential::User(owner.id);
+
+        assert!(immutable(
+            codes
+                .update(
+                    id,
+                    credential(),
+                    link("https://example.org"),
+                    QrCodeOptions::default(),
+                    &context,
+                )
+                .await
+        ));
+        assert!(immutable(
+            codes.set_active(id, credential(), false, &context).await
+        ));
+        assert!(immutable(codes.delete(id, credential(), &context).await));
+
+        let revisions = RevisionDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let rollback = revisions
+            .rollback(
+                id,
+                Uuid::new_v4(),
+                credential(),
+                &LinkPolicy::default(),
+                &context,
+            )
+            .await;
+        assert!(matches!(rollback, Err(RevisionError::Immutable)));
+
+        let schedule = ScheduleDatabase {
+            db_conn: db_conn.clone(),
+        };
+        assert!(immutable(
+            schedule
+                .create(id, credential(), link("https://example.org"), Utc::now())
+                .await
+        ));
+        assert!(immutable(
+            schedule
+                .update(
+                    id,
+                    Uuid::new_v4(),
+                    credential(),
+                    link("https://example.org"),
+                    Utc::now(),
+                )
+                .await
+        ));
+        assert!(immutable(
+            schedule.delete(id, Uuid::new_v4(), credential()).await
+        ));
+
+        let targeting = TargetingDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let rule = TargetingRuleData {
+            position: 0,
+            os: None,
+            device: None,
+            language: Some("de".to_string()),
+            country: None,
+            link: link("https://example.org"),
+        };
+        assert!(immutable(
+            targeting.create(id, credential(), rule.clone()).await
+        ));
+        assert!(immutable(
+            targeting
+                .update(id, Uuid::new_v4(), credential(), rule)
+                .await
+        ));
+        assert!(immutable(
+            targeting.delete(id, Uuid::new_v4(), credential()).await
+        ));
+
+        let variants = VariantDatabase { db_conn };
+        assert!(immutable(
+            variants
+                .create(
+                    id,
+                    credential(),
+                    "b".to_string(),
+                    link("https://example.org"),
+                    1,
+                )
+                .await
+        ));
+        assert!(immutable(
+            variants
+                .update(
+                    id,
+                    Uuid::new_v4(),
+                    credential(),
+                    "b".to_string(),
+                    link("https://example.org"),
+                    1,
+                )
+                .await
+        ));
+        assert!(immutable(
+            variants.delete(id, Uuid::new_v4(), credential()).await
+        ));
+
+        let qr_code = codes.get(id).await.unwrap().unwrap();
+        assert_eq!(qr_code.link, "https://example.com/");
+        assert!(qr_code.active);
+    }
+
+    #[tokio::test]
+    async fn reports_whether_the_passphrase_matched() {
+        let codes = QrCodeDatabase {
+            db_conn: database().await,
+        };
+        let context = AuditContext::default();
+        let (qr_code, passphrase) = codes
+            .create(
+                link("https://example.com"),
+                QrCodeOptions::default(),
+                &context,
+            )
+            .await
+            .unwrap();
+        let set_active = |credential| codes.set_active(qr_code.id, credential, false, &context);
+
+        let (result, matches) =
+            report_passphrase_check(set_active(Credential::Passphrase("wrong".to_string()))).await;
+        assert!(matches!(result, Ok(None)));
+        assert_eq!(matches, Some(false));
+
+        let (result, matches) =
+            report_passphrase_check(set_active(Credential::User(Uuid::new_v4()))).await;
+        assert!(matches!(result, Ok(None)));
+        assert_eq!(matches, None);
+
+        let (result, matches) =
+            report_passphrase_check(set_active(Credential::Passphrase(passphrase))).await;
+        assert!(matches!(result, Ok(Some(_))));
+        assert_eq!(matches, Some(true));
+    }
+
+    #[tokio::test]
+    async fn never_counts_scans_past_the_limit() {
+        let codes = QrCodeDatabase {
+            db_conn: database().await,
+        };
+        let context = AuditContext::default();
+        let options = QrCodeOptions {
+            max_scans: Some(2),
+            ..Default::default()
+        };
+        let (qr_code, passphrase) = codes
+            .create(link("https://example.com"), options, &context)
+            .await
+            .unwrap();
+        let id = qr_code.id;
+
+        let mut scans = JoinSet::new();
+        for _ in 0..5 {
+            let codes = codes.clone();
+            scans.spawn(async move { codes.register_scan(id, None).await.unwrap() });
+        }
+        let outcomes = scans.join_all().await;
+        let count = |outcome| outcomes.iter().filter(|x| **x == outcome).count();
+        assert_eq!(count(ScanOutcome::Counted), 2);
+        assert_eq!(count(ScanOutcome::Exhausted), 3);
+        assert_eq!(codes.get(id).await.unwrap().unwrap().scan_count, 2);
+
+        codes
+            .delete(id, Credential::Passphrase(passphrase), &context)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            codes.register_scan(id, None).await.unwrap(),
+            ScanOutcome::NotFound
+        );
+        assert_eq!(
+            codes.register_scan(Uuid::new_v4(), None).await.unwrap(),
+            ScanOutcome::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn restores_deleted_codes_until_they_are_purged() {
+        let db_conn = database().await;
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let codes = QrCodeDatabase { db_conn };
+        let owner = users.register("a@example.com", "password").await.unwrap();
+        let context = AuditContext::default();
+        let create = || {
+            let options = QrCodeOptions {
+                owner_id: Some(owner.id),
+                ..Default::default()
+            };
+            codes.create(link("https://example.com"), options, &context)
+        };
+        let (qr_code, _) = create().await.unwrap();
+        let (kept, _) = create().await.unwrap();
+        let id = qr_code.id;
+        let credential = || Credential::User(owner.id);
+        let retention = Duration::days(30);
+
+        let stranger = Credential::User(Uuid::new_v4());
+        assert!(
+            codes
+                .delete(id, stranger, &context)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let deleted = codes
+            .delete(id, credential(), &context)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(deleted.deleted_at.is_some());
+        assert!(codes.get(id).await.unwrap().is_none());
+        assert_eq!(codes.list_owned(owner.id).await.unwrap().len(), 1);
+        assert!(
+            codes
+                .delete(id, credential(), &context)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let restored = codes
+            .restore(id, credential(), retention, &context)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert!(codes.get(id).await.unwrap().is_some());
+
+        // Only codes in the trash for longer than the retention are purged.
+        codes
+            .delete(id, credential(), &context)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(codes.purge_deleted(retention).await.unwrap(), 0);
+        assert_eq!(codes.purge_deleted(Duration::zero()).await.unwrap(), 1);
+        assert!(
+            codes
+                .restore(id, credential(), retention, &context)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(codes.get(kept.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn hashes_passphrases_and_upgrades_plaintext_ones() {
+        let codes = QrCodeDatabase {
+            db_conn: database().await,
+        };
+        let options = QrCodeOptions {
+            access_password: Some("1234".to_string()),
+            ..Default::default()
+        };
+        let (qr_code, passphrase) = codes
+            .create(
+                link("https://example.com"),
+                options,
+                &AuditContext::default(),
+            )
+            .await
+            .unwrap();
+        assert!(qr_code.passphrase_hashed);
+        assert!(qr_code.passphrase.starts_with("$argon2"));
+        assert!(verify_password(&passphrase, &qr_code.passphrase));
+        let access_password_hash = qr_code.access_password_hash.clone().unwrap();
+        assert!(access_password_hash.starts_with("$argon2"));
+        assert!(verify_password("1234", &access_password_hash));
+
+        // Codes from before passphrases were hashed.
+        let mut active: ActiveModel = qr_code.into();
+        active.passphrase = Set(passphrase.clone());
+        active.passphrase_hashed = Set(false);
+        let qr_code = active.update(&codes.db_conn).await.unwrap();
+        let credential = |x: &str| Credential::Passphrase(x.to_string());
+        let id = qr_code.id;
+
+        let wrong = codes
+            .get_authorized(id, &credential("wrong"), Action::Edit)
+            .await
+            .unwrap();
+        assert!(wrong.is_none());
+        assert!(!codes.get(id).await.unwrap().unwrap().passphrase_hashed);
+
+        let upgraded = codes
+            .get_authorized(id, &credential(&passphrase), Action::Edit)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(upgraded.passphrase_hashed);
+        assert!(verify_password(&passphrase, &upgraded.passphrase));
+        let right = codes
+            .get_authorized(id, &credential(&passphrase), Action::Edit)
+            .await
+            .unwrap();
+        assert!(right.is_some());
+    }
 }
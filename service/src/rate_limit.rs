@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Buckets are only pruned once there are this many, to keep the common path cheap.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket per key, each holding up to `per_minute` tokens that refill evenly over a
+/// minute, so bursts up to the limit are allowed.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+    per_minute: u32,
+}
+
+impl RateLimiter {
+    /// A limit of `0` lets every request through.
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+            per_minute,
+        }
+    }
+
+    /// Takes a token of the key, returns how long to wait for the next one if there is none.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+
+        let capacity = f64::from(self.per_minute);
+        let refill_per_second = capacity / 60.0;
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * refill_per_second
+                    < capacity
+            });
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_second).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / refill_per_second,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_bursts_and_refills_over_time() {
+        let limiter = RateLimiter::new(2);
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_at("a", start), Ok(()));
+        assert_eq!(limiter.check_at("a", start), Ok(()));
+        assert_eq!(limiter.check_at("a", start), Err(Duration::from_secs(30)));
+        assert_eq!(limiter.check_at("b", start), Ok(()));
+
+        assert_eq!(
+            limiter.check_at("a", start + Duration::from_secs(30)),
+            Ok(())
+        );
+        assert!(
+            limiter
+                .check_at("a", start + Duration::from_secs(30))
+                .is_err()
+        );
+
+        let unlimited = RateLimiter::new(0);
+        assert!((0..100).all(|_| unlimited.check_at("a", start).is_ok()));
+    }
+}
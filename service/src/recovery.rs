@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use entity::{qr_code::Model, sea_orm_active_enums::AuditAction};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sea_orm::{DbConn, DbErr, TransactionTrait};
+use sha2::Sha256;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    audit::{AuditContext, record_audit},
+    mail::{Mail, MailError, Mailer},
+    qrcode::{QrCodeDatabaseError, find_live, replace_passphrase},
+};
+
+#[derive(Debug, Error)]
+pub enum RecoveryError {
+    #[error("database operation failed, {0}")]
+    Database(#[from] DbErr),
+    #[error("recovery mail could not be sent, {0}")]
+    Mail(#[from] MailError),
+}
+
+/// Mails recovery tokens to the owner email of a qr code and exchanges them for a new
+/// passphrase.
+///
+/// Tokens are signed over the current passphrase hash, so each one stops working as soon as
+/// the passphrase was replaced.
+#[derive(Clone, Debug)]
+pub struct PassphraseRecovery {
+    pub db_conn: DbConn,
+    secret: Arc<[u8]>,
+    token_lifetime: Duration,
+    server_url: String,
+}
+
+impl PassphraseRecovery {
+    /// Without a `secret` a random one is used, issued tokens then stop working on restart.
+    pub fn new(
+        db_conn: DbConn,
+        secret: Option<&[u8]>,
+        token_lifetime: Duration,
+        server_url: String,
+    ) -> Self {
+        let secret = match secret {
+            Some(secret) => Arc::from(secret),
+            None => {
+                let mut secret = [0; 32];
+                rand::rng().fill_bytes(&mut secret);
+                Arc::from(secret.as_slice())
+            }
+        };
+
+        Self {
+            db_conn,
+            secret,
+            token_lifetime,
+            server_url,
+        }
+    }
+
+    /// Mails a recovery token if `email` is the owner email of the qr code, silently does
+    /// nothing otherwise so callers can't probe for owners.
+    pub async fn request<M: Mailer>(
+        &self,
+        id: Uuid,
+        email: &str,
+        mailer: &M,
+    ) -> Result<(), RecoveryError> {
+        let Some(qr_code) = find_live(id).one(&self.db_conn).await? else {
+            return Ok(());
+        };
+        let Some(owner_email) = qr_code.owner_email.as_deref() else {
+            return Ok(());
+        };
+        if !owner_email.eq_ignore_ascii_case(email.trim()) {
+            return Ok(());
+        }
+
+        let expires = (Utc::now() + self.token_lifetime).timestamp();
+        let token = self.issue(qr_code.id, &qr_code.passphrase, expires);
+        let mail = Mail {
+            to: owner_email.to_string(),
+            subject: "Recover the passphrase of your qr code".to_string(),
+            body: format!(
+                "A new passphrase was requested for the qr code {id}.\n\n\
+                 Send the token below within {} minutes to {}/api/qr/{id}/recovery/redeem \
+                 to receive it, the current passphrase stops working then.\n\n{token}\n\n\
+                 If you didn't request this, you can ignore this mail.",
+                self.token_lifetime.num_minutes(),
+                self.server_url
+            ),
+        };
+        mailer.send(&mail).await?;
+
+        Ok(())
+    }
+
+    /// Replaces the passphrase if the token is valid, returns the new one in plaintext.
+    pub async fn redeem(
+        &self,
+        id: Uuid,
+        token: &str,
+        context: &AuditContext,
+    ) -> Result<Option<(Model, String)>, QrCodeDatabaseError> {
+        let Some(qr_code) = find_live(id).one(&self.db_conn).await? else {
+            return Ok(None);
+        };
+        if !self.verify(
+            qr_code.id,
+            &qr_code.passphrase,
+            token,
+            Utc::now().timestamp(),
+        ) {
+            return Ok(None);
+        }
+
+        let txn = self.db_conn.begin().await?;
+        let old = qr_code.clone();
+        let (qr_code, passphrase) = replace_passphrase(&txn, qr_code).await?;
+
+        record_audit(
+            &txn,
+            context,
+            AuditAction::RecoverPassphrase,
+            "recovery_token".to_string(),
+            Some(&old),
+            &qr_code,
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(Some((qr_code, passphrase)))
+    }
+
+    fn mac(&self, id: Uuid, passphrase: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("hmac accepts keys of any size");
+        mac.update(id.as_bytes());
+        mac.update(&expires.to_be_bytes());
+        mac.update(passphrase.as_bytes());
+        mac
+    }
+
+    fn issue(&self, id: Uuid, passphrase: &str, expires: i64) -> String {
+        let signature = self.mac(id, passphrase, expires).finalize().into_bytes();
+
+        format!("{expires}.{}", BASE64_URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    fn verify(&self, id: Uuid, passphrase: &str, token: &str, now: i64) -> bool {
+        let Some((expires, signature)) = token.split_once('.') else {
+            return false;
+        };
+        let (Ok(expires), Ok(signature)) = (
+            expires.parse::<i64>(),
+            BASE64_URL_SAFE_NO_PAD.decode(signature),
+        ) else {
+            return false;
+        };
+
+        expires > now
+            && self
+                .mac(id, passphrase, expires)
+                .verify_slice(&signature)
+                .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_bound_to_code_passphrase_and_lifetime() {
+        let recovery = PassphraseRecovery::new(
+            DbConn::default(),
+            Some(b"secret"),
+            Duration::minutes(30),
+            String::new(),
+        );
+        let id = Uuid::new_v4();
+        let token = recovery.issue(id, "$argon2id$old", 2_000);
+
+        assert!(recovery.verify(id, "$argon2id$old", &token, 1_000));
+        assert!(!recovery.verify(id, "$argon2id$old", &token, 2_000));
+        assert!(!recovery.verify(id, "$argon2id$new", &token, 1_000));
+        assert!(!recovery.verify(Uuid::new_v4(), "$argon2id$old", &token, 1_000));
+        assert!(!recovery.verify(id, "$argon2id$old", &token.replace("2000", "3000"), 1_000));
+
+        let other = PassphraseRecovery::new(
+            DbConn::default(),
+            Some(b"other"),
+            Duration::minutes(30),
+            String::new(),
+        );
+        assert!(!other.verify(id, "$argon2id$old", &token, 1_000));
+    }
+}
@@ -0,0 +1,124 @@
+use ::entity::qr_code_revision::{self, Entity as DbQrCodeRevision};
+use chrono::Utc;
+use entity::{
+    qr_code::{self, Model as QrCodeModel},
+    qr_code_revision::Model,
+    sea_orm_active_enums::AuditAction,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbConn, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use thiserror::Error;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    LinkPolicy, LinkRejection,
+    access::{Action, Credential, find_authorized},
+    audit::{AuditContext, actor, record_audit},
+};
+
+#[derive(Debug, Error)]
+pub enum RevisionError {
+    #[error("database operation failed, {0}")]
+    Database(#[from] DbErr),
+    #[error("the link of the revision was rejected, {0}")]
+    Rejected(LinkRejection),
+    #[error("immutable codes can't be edited")]
+    Immutable,
+}
+
+/// Stores the link a qr code pointed to before `actor` changed it.
+pub(crate) async fn record_revision<C: ConnectionTrait>(
+    db_conn: &C,
+    qr_code_id: Uuid,
+    link: String,
+    actor: String,
+) -> Result<Model, DbErr> {
+    qr_code_revision::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        qr_code_id: Set(qr_code_id),
+        link: Set(link),
+        actor: Set(Some(actor)),
+        created_at: Set(Utc::now()),
+    }
+    .insert(db_conn)
+    .await
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RevisionDatabase {
+    pub db_conn: DbConn,
+}
+
+impl RevisionDatabase {
+    /// Lists the previous links of the qr code, newest first.
+    pub async fn list(&self, qr_code_id: Uuid) -> Result<Vec<Model>, DbErr> {
+        DbQrCodeRevision::find()
+            .filter(qr_code_revision::Column::QrCodeId.eq(qr_code_id))
+            .order_by_desc(qr_code_revision::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await
+    }
+
+    /// Restores the link of a revision, the current link is kept as a new revision.
+    ///
+    /// The link has to pass the current link policy, it may have been blocked since.
+    pub async fn rollback(
+        &self,
+        qr_code_id: Uuid,
+        revision_id: Uuid,
+        credential: Credential,
+        link_policy: &LinkPolicy,
+        context: &AuditContext,
+    ) -> Result<Option<QrCodeModel>, RevisionError> {
+        let Some(qr_code) =
+            find_authorized(&self.db_conn, qr_code_id, &credential, Action::Edit).await?
+        else {
+            return Ok(None);
+        };
+        if qr_code.immutable {
+            return Err(RevisionError::Immutable);
+        }
+
+        let Some(revision) = DbQrCodeRevision::find_by_id(revision_id)
+            .filter(qr_code_revision::Column::QrCodeId.eq(qr_code_id))
+            .one(&self.db_conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        if revision.link == qr_code.link {
+            return Ok(Some(qr_code));
+        }
+
+        let link = Url::parse(&revision.link)
+            .map_err(|_| RevisionError::Rejected(LinkRejection::Scheme))?;
+        link_policy.check(&link).map_err(RevisionError::Rejected)?;
+
+        let txn = self.db_conn.begin().await?;
+        record_revision(&txn, qr_code_id, qr_code.link.clone(), actor(&credential)).await?;
+
+        let old = qr_code.clone();
+        let mut active: qr_code::ActiveModel = qr_code.into();
+        active.link = Set(revision.link);
+        active.link_changed_at = Set(Some(Utc::now()));
+        active.modified_at = Set(Some(Utc::now()));
+        let qr_code = active.update(&txn).await?;
+
+        record_audit(
+            &txn,
+            context,
+            AuditAction::Rollback,
+            actor(&credential),
+            Some(&old),
+            &qr_code,
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(Some(qr_code))
+    }
+}
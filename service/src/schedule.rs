@@ -0,0 +1,220 @@
+use ::entity::destination_schedule::{self, Entity as DbDestinationSchedule};
+use chrono::{DateTime, Utc};
+use entity::destination_schedule::{ActiveModel, Model};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, DbErr, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder,
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    QrCodeDatabaseError,
+    access::{Action, Credential, find_mutable},
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct ScheduleDatabase {
+    pub db_conn: DbConn,
+}
+
+impl ScheduleDatabase {
+    pub async fn list(&self, qr_code_id: Uuid) -> Result<Vec<Model>, DbErr> {
+        DbDestinationSchedule::find()
+            .filter(destination_schedule::Column::QrCodeId.eq(qr_code_id))
+            .order_by_asc(destination_schedule::Column::StartsAt)
+            .all(&self.db_conn)
+            .await
+    }
+
+    pub async fn create(
+        &self,
+        qr_code_id: Uuid,
+        credential: Credential,
+        link: Url,
+        starts_at: DateTime<Utc>,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        if find_mutable(&self.db_conn, qr_code_id, &credential, Action::Edit)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let entry = destination_schedule::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            qr_code_id: Set(qr_code_id),
+            link: Set(link.to_string()),
+            starts_at: Set(starts_at),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&self.db_conn)
+        .await?;
+
+        Ok(Some(entry))
+    }
+
+    pub async fn update(
+        &self,
+        qr_code_id: Uuid,
+        entry_id: Uuid,
+        credential: Credential,
+        link: Url,
+        starts_at: DateTime<Utc>,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let Some(entry) = self
+            .find_authorized_entry(qr_code_id, entry_id, &credential)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut active: ActiveModel = entry.into();
+        active.link = Set(link.to_string());
+        active.starts_at = Set(starts_at);
+        let entry = active.update(&self.db_conn).await?;
+
+        Ok(Some(entry))
+    }
+
+    pub async fn delete(
+        &self,
+        qr_code_id: Uuid,
+        entry_id: Uuid,
+        credential: Credential,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let Some(entry) = self
+            .find_authorized_entry(qr_code_id, entry_id, &credential)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        entry.clone().delete(&self.db_conn).await?;
+
+        Ok(Some(entry))
+    }
+
+    /// Finds the link of the latest entry that has started by `now`.
+    ///
+    /// Entries that started before `link_changed_at`, when the link of the code was last
+    /// changed directly, are superseded by that change.
+    pub async fn current_link(
+        &self,
+        qr_code_id: Uuid,
+        now: DateTime<Utc>,
+        link_changed_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<String>, DbErr> {
+        let mut query = DbDestinationSchedule::find()
+            .filter(destination_schedule::Column::QrCodeId.eq(qr_code_id))
+            .filter(destination_schedule::Column::StartsAt.lte(now));
+        if let Some(link_changed_at) = link_changed_at {
+            query = query.filter(destination_schedule::Column::StartsAt.gt(link_changed_at));
+        }
+
+        let entry = query
+            .order_by_desc(destination_schedule::Column::StartsAt)
+            .one(&self.db_conn)
+            .await?;
+
+        Ok(entry.map(|x| x.link))
+    }
+
+    async fn find_authorized_entry(
+        &self,
+        qr_code_id: Uuid,
+        entry_id: Uuid,
+        credential: &Credential,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        if find_mutable(&self.db_conn, qr_code_id, credential, Action::Edit)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let entry = DbDestinationSchedule::find_by_id(entry_id)
+            .filter(destination_schedule::Column::QrCodeId.eq(qr_code_id))
+            .one(&self.db_conn)
+            .await?;
+
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::{AuditContext, QrCodeDatabase, QrCodeOptions, UserDatabase, testing::database};
+
+    #[tokio::test]
+    async fn follows_started_entries_until_the_link_is_changed() {
+        let db_conn = database().await;
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let codes = QrCodeDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let schedule = ScheduleDatabase { db_conn };
+        let owner = users.register("a@example.com", "password").await.unwrap();
+        let context = AuditContext::default();
+        let link = |x: &str| Url::parse(x).unwrap();
+        let options = QrCodeOptions {
+            owner_id: Some(owner.id),
+            ..Default::default()
+        };
+        let (qr_code, _) = codes
+            .create(link("https://example.com"), options, &context)
+            .await
+            .unwrap();
+        let id = qr_code.id;
+
+        let now = Utc::now();
+        for (path, starts_at) in [
+            ("started", now - Duration::hours(2)),
+            ("latest", now - Duration::hours(1)),
+            ("upcoming", now + Duration::hours(1)),
+        ] {
+            schedule
+                .create(
+                    id,
+                    Credential::User(owner.id),
+                    link(&format!("https://example.org/{path}")),
+                    starts_at,
+                )
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        let current = |at, link_changed_at| schedule.current_link(id, at, link_changed_at);
+        assert_eq!(
+            current(now, None).await.unwrap().as_deref(),
+            Some("https://example.org/latest")
+        );
+        assert_eq!(current(now - Duration::hours(3), None).await.unwrap(), None);
+
+        let qr_code = codes
+            .update(
+                id,
+                Credential::User(owner.id),
+                link("https://example.net"),
+                QrCodeOptions::default(),
+                &context,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(current(now, qr_code.link_changed_at).await.unwrap(), None);
+        assert_eq!(
+            current(now + Duration::hours(2), qr_code.link_changed_at)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("https://example.org/upcoming")
+        );
+    }
+}
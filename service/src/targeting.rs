@@ -0,0 +1,297 @@
+use ::entity::targeting_rule::{self, Entity as DbTargetingRule};
+use chrono::Utc;
+use entity::{
+    sea_orm_active_enums::{DeviceClass, TargetOs},
+    targeting_rule::{ActiveModel, Model},
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, DbErr, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder,
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    QrCodeDatabaseError,
+    access::{Action, Credential, find_mutable},
+};
+
+/// What we know about the scanning client, derived from its request headers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub os: Option<TargetOs>,
+    pub device: Option<DeviceClass>,
+    pub language: Option<String>,
+    pub country: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_headers(
+        user_agent: Option<&str>,
+        accept_language: Option<&str>,
+        country: Option<&str>,
+    ) -> Self {
+        let user_agent = user_agent.unwrap_or_default();
+        let preferred_language = accept_language.and_then(preferred_language);
+
+        let country = country
+            .map(str::trim)
+            .filter(|x| x.len() == 2)
+            .map(str::to_ascii_uppercase)
+            .or_else(|| {
+                preferred_language
+                    .as_deref()
+                    .and_then(|x| x.split('-').nth(1))
+                    .filter(|x| x.len() == 2)
+                    .map(str::to_ascii_uppercase)
+            });
+
+        Self {
+            os: parse_os(user_agent),
+            device: parse_device(user_agent),
+            language: preferred_language
+                .map(|x| x.split('-').next().unwrap_or_default().to_string()),
+            country,
+        }
+    }
+
+    fn matches(&self, rule: &Model) -> bool {
+        rule.os.is_none_or(|os| self.os == Some(os))
+            && rule.device.is_none_or(|device| self.device == Some(device))
+            && rule.language.as_deref().is_none_or(|language| {
+                self.language
+                    .as_deref()
+                    .is_some_and(|x| x.eq_ignore_ascii_case(language))
+            })
+            && rule.country.as_deref().is_none_or(|country| {
+                self.country
+                    .as_deref()
+                    .is_some_and(|x| x.eq_ignore_ascii_case(country))
+            })
+    }
+}
+
+fn parse_os(user_agent: &str) -> Option<TargetOs> {
+    if ["iPhone", "iPad", "iPod"]
+        .iter()
+        .any(|x| user_agent.contains(x))
+    {
+        Some(TargetOs::Ios)
+    } else if user_agent.contains("Android") {
+        Some(TargetOs::Android)
+    } else if user_agent.contains("CrOS") {
+        Some(TargetOs::ChromeOs)
+    } else if user_agent.contains("Windows") {
+        Some(TargetOs::Windows)
+    } else if user_agent.contains("Macintosh") || user_agent.contains("Mac OS X") {
+        Some(TargetOs::MacOs)
+    } else if user_agent.contains("Linux") {
+        Some(TargetOs::Linux)
+    } else {
+        None
+    }
+}
+
+fn parse_device(user_agent: &str) -> Option<DeviceClass> {
+    if user_agent.is_empty() {
+        None
+    } else if user_agent.contains("iPad")
+        || user_agent.contains("Tablet")
+        || (user_agent.contains("Android") && !user_agent.contains("Mobile"))
+    {
+        Some(DeviceClass::Tablet)
+    } else if user_agent.contains("Mobi") || user_agent.contains("iPhone") {
+        Some(DeviceClass::Mobile)
+    } else {
+        Some(DeviceClass::Desktop)
+    }
+}
+
+fn preferred_language(accept_language: &str) -> Option<String> {
+    accept_language
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|x| x.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |x| x.parse::<f32>().ok())?;
+
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+        })
+        .fold(None, |best: Option<(&str, f32)>, current| match best {
+            Some(best) if best.1 >= current.1 => Some(best),
+            _ => Some(current),
+        })
+        .map(|(tag, _)| tag.to_string())
+}
+
+#[derive(Clone, Debug)]
+pub struct TargetingRuleData {
+    pub position: i32,
+    pub os: Option<TargetOs>,
+    pub device: Option<DeviceClass>,
+    pub language: Option<String>,
+    pub country: Option<String>,
+    pub link: Url,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TargetingDatabase {
+    pub db_conn: DbConn,
+}
+
+impl TargetingDatabase {
+    pub async fn list(&self, qr_code_id: Uuid) -> Result<Vec<Model>, DbErr> {
+        DbTargetingRule::find()
+            .filter(targeting_rule::Column::QrCodeId.eq(qr_code_id))
+            .order_by_asc(targeting_rule::Column::Position)
+            .order_by_asc(targeting_rule::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await
+    }
+
+    pub async fn create(
+        &self,
+        qr_code_id: Uuid,
+        credential: Credential,
+        data: TargetingRuleData,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        if find_mutable(&self.db_conn, qr_code_id, &credential, Action::Edit)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let rule = targeting_rule::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            qr_code_id: Set(qr_code_id),
+            position: Set(data.position),
+            os: Set(data.os),
+            device: Set(data.device),
+            language: Set(data.language),
+            country: Set(data.country),
+            link: Set(data.link.to_string()),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&self.db_conn)
+        .await?;
+
+        Ok(Some(rule))
+    }
+
+    pub async fn update(
+        &self,
+        qr_code_id: Uuid,
+        rule_id: Uuid,
+        credential: Credential,
+        data: TargetingRuleData,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let Some(rule) = self
+            .find_authorized_rule(qr_code_id, rule_id, &credential)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut active: ActiveModel = rule.into();
+        active.position = Set(data.position);
+        active.os = Set(data.os);
+        active.device = Set(data.device);
+        active.language = Set(data.language);
+        active.country = Set(data.country);
+        active.link = Set(data.link.to_string());
+        let rule = active.update(&self.db_conn).await?;
+
+        Ok(Some(rule))
+    }
+
+    pub async fn delete(
+        &self,
+        qr_code_id: Uuid,
+        rule_id: Uuid,
+        credential: Credential,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let Some(rule) = self
+            .find_authorized_rule(qr_code_id, rule_id, &credential)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        rule.clone().delete(&self.db_conn).await?;
+
+        Ok(Some(rule))
+    }
+
+    /// Returns the link of the first rule, in position order, that matches the client.
+    pub async fn matching_link(
+        &self,
+        qr_code_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<Option<String>, DbErr> {
+        let rules = self.list(qr_code_id).await?;
+
+        Ok(rules
+            .into_iter()
+            .find(|rule| client.matches(rule))
+            .map(|rule| rule.link))
+    }
+
+    async fn find_authorized_rule(
+        &self,
+        qr_code_id: Uuid,
+        rule_id: Uuid,
+        credential: &Credential,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        if find_mutable(&self.db_conn, qr_code_id, credential, Action::Edit)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let rule = DbTargetingRule::find_by_id(rule_id)
+            .filter(targeting_rule::Column::QrCodeId.eq(qr_code_id))
+            .one(&self.db_conn)
+            .await?;
+
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_iphone() {
+        let client = ClientInfo::from_headers(
+            Some(
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 Mobile/15E148",
+            ),
+            Some("de-AT,de;q=0.9,en;q=0.8"),
+            None,
+        );
+
+        assert_eq!(client.os, Some(TargetOs::Ios));
+        assert_eq!(client.device, Some(DeviceClass::Mobile));
+        assert_eq!(client.language.as_deref(), Some("de"));
+        assert_eq!(client.country.as_deref(), Some("AT"));
+    }
+
+    #[test]
+    fn detects_android_tablet_and_prefers_country_header() {
+        let client = ClientInfo::from_headers(
+            Some("Mozilla/5.0 (Linux; Android 14; SM-X910) AppleWebKit/537.36 Chrome/120.0"),
+            Some("en;q=0.5, fr"),
+            Some("ch"),
+        );
+
+        assert_eq!(client.os, Some(TargetOs::Android));
+        assert_eq!(client.device, Some(DeviceClass::Tablet));
+        assert_eq!(client.language.as_deref(), Some("fr"));
+        assert_eq!(client.country.as_deref(), Some("CH"));
+    }
+}
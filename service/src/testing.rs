@@ -0,0 +1,13 @@
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ConnectOptions, Database, DbConn};
+
+/// Empty in memory database with all migrations applied.
+pub(crate) async fn database() -> DbConn {
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    // Every connection would open its own empty in memory database.
+    options.max_connections(1).sqlx_logging(false);
+    let db_conn = Database::connect(options).await.unwrap();
+    Migrator::up(&db_conn, None).await.unwrap();
+
+    db_conn
+}
@@ -0,0 +1,99 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const MAX_LOCKOUT: Duration = Duration::from_secs(60 * 60);
+
+tokio::task_local! {
+    static PASSPHRASE_CHECK: Cell<Option<bool>>;
+}
+
+#[derive(Clone, Copy, Debug)]
+struct AttemptState {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Tracks failed attempts per key and locks the key out with an exponentially growing delay.
+#[derive(Clone, Debug)]
+pub struct AttemptThrottle {
+    attempts: Arc<Mutex<HashMap<String, AttemptState>>>,
+    max_attempts: u32,
+    base_lockout: Duration,
+}
+
+impl AttemptThrottle {
+    pub fn new(max_attempts: u32, base_lockout: Duration) -> Self {
+        Self {
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+            max_attempts,
+            base_lockout,
+        }
+    }
+
+    /// Returns the remaining lockout time if the key is currently locked.
+    pub fn check(&self, key: &str) -> Option<Duration> {
+        let attempts = self.attempts.lock().unwrap();
+        let locked_until = attempts.get(key)?.locked_until?;
+
+        locked_until.checked_duration_since(Instant::now())
+    }
+
+    pub fn record_failure(&self, key: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().unwrap();
+
+        attempts.retain(|_, state| now.duration_since(state.last_failure) < MAX_LOCKOUT);
+
+        let state = attempts.entry(key.to_string()).or_insert(AttemptState {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        state.failures += 1;
+        state.last_failure = now;
+
+        if state.failures < self.max_attempts {
+            return None;
+        }
+
+        let exponent = (state.failures - self.max_attempts).min(16);
+        let lockout = self
+            .base_lockout
+            .saturating_mul(2u32.pow(exponent))
+            .min(MAX_LOCKOUT);
+        state.locked_until = Some(now + lockout);
+
+        Some(lockout)
+    }
+
+    pub fn reset(&self, key: &str) {
+        self.attempts.lock().unwrap().remove(key);
+    }
+}
+
+impl Default for AttemptThrottle {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(30))
+    }
+}
+
+/// Runs `future` and reports whether the passphrase it checked was right, `None` if it
+/// didn't check one, so callers can throttle wrong ones without hashing them again.
+pub async fn report_passphrase_check<F: Future>(future: F) -> (F::Output, Option<bool>) {
+    PASSPHRASE_CHECK
+        .scope(Cell::new(None), async move {
+            let output = future.await;
+            (output, PASSPHRASE_CHECK.with(Cell::get))
+        })
+        .await
+}
+
+/// Records the outcome of a passphrase check for [`report_passphrase_check`].
+pub(crate) fn passphrase_checked(matches: bool) {
+    let _ = PASSPHRASE_CHECK.try_with(|x| x.set(Some(matches)));
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::Client;
+use url::Url;
+
+use crate::outbound::{check_public_host, outbound_client};
+
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+/// Titles belong into the head, so the rest of the page isn't downloaded.
+const MAX_BODY: usize = 64 * 1024;
+const MAX_TITLE_CHARS: usize = 255;
+
+/// Titles by link, with the time they were looked up.
+type TitleCache = HashMap<String, (Instant, Option<String>)>;
+
+/// Looks up the titles of destination pages for the preview page.
+///
+/// Titles are cached for a while, every scan of a code with a preview asks for them. They
+/// are fetched in the background, so scans never wait for the destination.
+#[derive(Clone, Debug)]
+pub struct TitleFetcher {
+    client: Client,
+    titles: Arc<Mutex<TitleCache>>,
+}
+
+impl TitleFetcher {
+    pub fn new(timeout: Duration) -> reqwest::Result<Self> {
+        Ok(Self {
+            client: outbound_client(timeout)?,
+            titles: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Returns the cached title of the page, `None` if it has none, couldn't be loaded or
+    /// is still being looked up.
+    ///
+    /// Only pass stored links, not ones with parameters of the scanned url, so scanners
+    /// can't make the server fetch arbitrary urls or fill the cache.
+    pub fn title(&self, url: &Url) -> Option<String> {
+        let mut titles = self.titles.lock().unwrap();
+        if let Some((fetched_at, title)) = titles.get(url.as_str())
+            && fetched_at.elapsed() < CACHE_TTL
+        {
+            return title.clone();
+        }
+
+        titles.retain(|_, (fetched_at, _)| fetched_at.elapsed() < CACHE_TTL);
+        // Other scans meanwhile find this entry instead of fetching the page again.
+        titles.insert(url.to_string(), (Instant::now(), None));
+        drop(titles);
+
+        let fetcher = self.clone();
+        let url = url.clone();
+        tokio::spawn(async move {
+            let title = fetcher.fetch(&url).await;
+            fetcher
+                .titles
+                .lock()
+                .unwrap()
+                .insert(url.to_string(), (Instant::now(), title));
+        });
+
+        None
+    }
+
+    async fn fetch(&self, url: &Url) -> Option<String> {
+        check_public_host(url).ok()?;
+
+        let mut response = self.client.get(url.clone()).send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.ok()? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY {
+                break;
+            }
+        }
+
+        parse_title(&String::from_utf8_lossy(&body))
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn parse_title(html: &str) -> Option<String> {
+    // Lowercasing ascii keeps every byte offset, so they apply to the original as well.
+    let lowercase = html.to_ascii_lowercase();
+    let tag = lowercase.find("<title")?;
+    let start = tag + lowercase[tag..].find('>')? + 1;
+    let end = start + lowercase[start..].find("</title")?;
+
+    let title = html[start..end].split_whitespace().collect::<Vec<_>>();
+    let title = decode_entities(&title.join(" "));
+
+    (!title.is_empty()).then(|| title.chars().take(MAX_TITLE_CHARS).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_title_of_a_page() {
+        let html = "<html><head><meta charset=\"utf-8\">\n<TITLE lang=\"en\">\n  Fish &amp; Chips\n  Menu </TITLE></head></html>";
+
+        assert_eq!(parse_title(html), Some("Fish & Chips Menu".to_string()));
+        assert_eq!(parse_title("<title> </title>"), None);
+        assert_eq!(parse_title("<title>unterminated"), None);
+        assert_eq!(parse_title("<p>no title</p>"), None);
+    }
+}
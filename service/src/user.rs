@@ -0,0 +1,378 @@
+use ::entity::{
+    api_key::{self, Entity as DbApiKey},
+    user::{self, Entity as DbUser},
+    user_identity::{self, Entity as DbUserIdentity},
+    user_session::{self, Entity as DbUserSession},
+};
+use chrono::{Duration, Utc};
+use entity::user::Model;
+use rand::{Rng, distr::Alphanumeric};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, TransactionTrait,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    mail::{Mail, MailError, Mailer},
+    oidc::OidcIdentity,
+    password::{HashError, hash_password, token_hash, verify_password},
+};
+
+const SESSION_TOKEN_LENGTH: usize = 48;
+const VERIFICATION_LIFETIME_HOURS: i64 = 48;
+
+#[derive(Debug, Error)]
+pub enum UserError {
+    #[error("the email is already registered")]
+    Taken,
+    #[error("the identity provider didn't verify the email")]
+    Unverified,
+    #[error("database operation failed, {0}")]
+    Database(#[from] DbErr),
+    #[error("password hashing failed, {0}")]
+    Hash(#[from] HashError),
+    #[error("verification mail could not be sent, {0}")]
+    Mail(#[from] MailError),
+}
+
+/// Session tokens are only stored as their hash, so a leaked database can't be used to log in.
+fn session_id(token: &str) -> String {
+    token_hash(token)
+}
+
+fn random_token() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(SESSION_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct UserDatabase {
+    pub db_conn: DbConn,
+}
+
+impl UserDatabase {
+    /// Creates an account whose email stays unverified until the token of
+    /// [`UserDatabase::request_verification`] is redeemed.
+    pub async fn register(&self, email: &str, password: &str) -> Result<Model, UserError> {
+        let email = email.trim().to_lowercase();
+
+        let existing = DbUser::find()
+            .filter(user::Column::Email.eq(&email))
+            .count(&self.db_conn)
+            .await?;
+        if existing > 0 {
+            return Err(UserError::Taken);
+        }
+
+        let user = user::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            email: Set(email),
+            password_hash: Set(hash_password(password)?),
+            created_at: Set(Utc::now()),
+            email_verified: Set(false),
+            ..Default::default()
+        }
+        .insert(&self.db_conn)
+        .await?;
+
+        Ok(user)
+    }
+
+    /// Returns the user if the password matches.
+    pub async fn login(&self, email: &str, password: &str) -> Result<Option<Model>, DbErr> {
+        let user = DbUser::find()
+            .filter(user::Column::Email.eq(email.trim().to_lowercase()))
+            .one(&self.db_conn)
+            .await?;
+
+        Ok(user.filter(|user| verify_password(password, &user.password_hash)))
+    }
+
+    /// Finds the user linked to an identity of the identity provider.
+    ///
+    /// Only emails the provider verified are accepted. Unknown identities are linked to the
+    /// user with the same email, otherwise a user is created whose password nobody knows.
+    ///
+    /// Anyone could have registered the email without verifying it, so such an account is
+    /// taken over: its password is replaced, and its sessions and api keys are revoked.
+    pub async fn login_with_identity(&self, identity: &OidcIdentity) -> Result<Model, UserError> {
+        let linked =
+            DbUserIdentity::find_by_id((identity.issuer.clone(), identity.subject.clone()))
+                .find_also_related(DbUser)
+                .one(&self.db_conn)
+                .await?;
+        if let Some((_, Some(user))) = linked {
+            return Ok(user);
+        }
+        if !identity.email_verified {
+            return Err(UserError::Unverified);
+        }
+
+        let email = identity.email.trim().to_lowercase();
+        let existing = DbUser::find()
+            .filter(user::Column::Email.eq(&email))
+            .one(&self.db_conn)
+            .await?;
+
+        let txn = self.db_conn.begin().await?;
+        let user = match existing {
+            Some(user) if user.email_verified => user,
+            Some(user) => {
+                DbUserSession::delete_many()
+                    .filter(user_session::Column::UserId.eq(user.id))
+                    .exec(&txn)
+                    .await?;
+                DbApiKey::delete_many()
+                    .filter(api_key::Column::UserId.eq(user.id))
+                    .exec(&txn)
+                    .await?;
+
+                let mut active: user::ActiveModel = user.into();
+                active.password_hash = Set(hash_password(&random_token())?);
+                active.email_verified = Set(true);
+                active.verification_token_hash = Set(None);
+                active.verification_expires_at = Set(None);
+                active.update(&txn).await?
+            }
+            None => {
+                user::ActiveModel {
+                    id: Set(Uuid::new_v4()),
+                    email: Set(email),
+                    password_hash: Set(hash_password(&random_token())?),
+                    created_at: Set(Utc::now()),
+                    email_verified: Set(true),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?
+            }
+        };
+
+        user_identity::ActiveModel {
+            issuer: Set(identity.issuer.clone()),
+            subject: Set(identity.subject.clone()),
+            user_id: Set(user.id),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+
+        Ok(user)
+    }
+
+    /// Mails a token proving that the user owns their email, replacing earlier ones. Does
+    /// nothing for verified emails.
+    pub async fn request_verification<M: Mailer>(
+        &self,
+        user: Model,
+        server_url: &str,
+        mailer: &M,
+    ) -> Result<(), UserError> {
+        if user.email_verified {
+            return Ok(());
+        }
+
+        let token = random_token();
+        let email = user.email.clone();
+        let mut active: user::ActiveModel = user.into();
+        active.verification_token_hash = Set(Some(token_hash(&token)));
+        active.verification_expires_at = Set(Some(
+            Utc::now() + Duration::hours(VERIFICATION_LIFETIME_HOURS),
+        ));
+        active.update(&self.db_conn).await?;
+
+        let mail = Mail {
+            to: email,
+            subject: "Verify the email of your account".to_string(),
+            body: format!(
+                "Send the token below within {VERIFICATION_LIFETIME_HOURS} hours to \
+                 {server_url}/api/account/verify to confirm that this email belongs to \
+                 your account.\n\n{token}\n\n\
+                 If you didn't create an account, you can ignore this mail."
+            ),
+        };
+        mailer.send(&mail).await?;
+
+        Ok(())
+    }
+
+    /// Marks the email of the user with this token as verified.
+    pub async fn verify_email(&self, token: &str) -> Result<Option<Model>, DbErr> {
+        let Some(user) = DbUser::find()
+            .filter(user::Column::VerificationTokenHash.eq(token_hash(token)))
+            .filter(user::Column::VerificationExpiresAt.gt(Utc::now()))
+            .one(&self.db_conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut active: user::ActiveModel = user.into();
+        active.email_verified = Set(true);
+        active.verification_token_hash = Set(None);
+        active.verification_expires_at = Set(None);
+
+        active.update(&self.db_conn).await.map(Some)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<Model>, DbErr> {
+        DbUser::find_by_id(id).one(&self.db_conn).await
+    }
+
+    /// Starts a session for the user and returns its token, expired sessions of the user are
+    /// removed on the way.
+    pub async fn create_session(&self, user_id: Uuid, ttl: Duration) -> Result<String, DbErr> {
+        DbUserSession::delete_many()
+            .filter(user_session::Column::UserId.eq(user_id))
+            .filter(user_session::Column::ExpiresAt.lte(Utc::now()))
+            .exec(&self.db_conn)
+            .await?;
+
+        let token = random_token();
+
+        user_session::ActiveModel {
+            id: Set(session_id(&token)),
+            user_id: Set(user_id),
+            created_at: Set(Utc::now()),
+            expires_at: Set(Utc::now() + ttl),
+        }
+        .insert(&self.db_conn)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Looks up the user of a session that hasn't expired yet.
+    pub async fn find_session(&self, token: &str) -> Result<Option<Model>, DbErr> {
+        let Some(session) = DbUserSession::find_by_id(session_id(token))
+            .filter(user_session::Column::ExpiresAt.gt(Utc::now()))
+            .one(&self.db_conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        self.get(session.user_id).await
+    }
+
+    pub async fn end_session(&self, token: &str) -> Result<(), DbErr> {
+        DbUserSession::delete_by_id(session_id(token))
+            .exec(&self.db_conn)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::testing::database;
+
+    /// Keeps the bodies of sent mails.
+    #[derive(Default)]
+    struct Outbox(Mutex<Vec<String>>);
+
+    impl Mailer for Outbox {
+        async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+            self.0.lock().unwrap().push(mail.body.clone());
+            Ok(())
+        }
+    }
+
+    fn identity(subject: &str, email: &str, email_verified: bool) -> OidcIdentity {
+        OidcIdentity {
+            issuer: "https://idp.example.com".to_string(),
+            subject: subject.to_string(),
+            email: email.to_string(),
+            email_verified,
+        }
+    }
+
+    #[tokio::test]
+    async fn links_identities_only_to_verified_emails() {
+        let users = UserDatabase {
+            db_conn: database().await,
+        };
+        let local = users.register("a@example.com", "password").await.unwrap();
+
+        let unverified = users
+            .login_with_identity(&identity("1", "b@example.com", false))
+            .await;
+        assert!(matches!(unverified, Err(UserError::Unverified)));
+
+        let outbox = Outbox::default();
+        users
+            .request_verification(local.clone(), "http://localhost", &outbox)
+            .await
+            .unwrap();
+        let body = outbox.0.lock().unwrap().pop().unwrap();
+        let token = body
+            .lines()
+            .find(|line| line.len() == SESSION_TOKEN_LENGTH)
+            .unwrap();
+        assert!(users.verify_email("wrong").await.unwrap().is_none());
+        assert!(
+            users
+                .verify_email(token)
+                .await
+                .unwrap()
+                .unwrap()
+                .email_verified
+        );
+
+        let linked = users
+            .login_with_identity(&identity("2", "a@example.com", true))
+            .await
+            .unwrap();
+        assert_eq!(linked.id, local.id);
+        assert!(
+            users
+                .login("a@example.com", "password")
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let created = users
+            .login_with_identity(&identity("3", "c@example.com", true))
+            .await
+            .unwrap();
+        assert!(created.email_verified);
+    }
+
+    #[tokio::test]
+    async fn identities_take_over_accounts_squatting_their_email() {
+        let users = UserDatabase {
+            db_conn: database().await,
+        };
+        let squatter = users.register("a@example.com", "password").await.unwrap();
+        let session = users
+            .create_session(squatter.id, Duration::days(1))
+            .await
+            .unwrap();
+
+        let user = users
+            .login_with_identity(&identity("1", "a@example.com", true))
+            .await
+            .unwrap();
+        assert_eq!(user.id, squatter.id);
+        assert!(user.email_verified);
+        assert!(
+            users
+                .login("a@example.com", "password")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(users.find_session(&session).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use ::entity::{
+    destination_variant::{self, Entity as DbDestinationVariant},
+    scan_event::{self, Entity as DbScanEvent},
+};
+use chrono::Utc;
+use entity::destination_variant::{ActiveModel, Model};
+use rand::Rng;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, DbErr, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    QrCodeDatabaseError,
+    access::{Action, Credential, find_mutable},
+};
+
+#[derive(Clone, Debug)]
+pub struct VariantStats {
+    pub variant: Model,
+    pub scans: i64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VariantDatabase {
+    pub db_conn: DbConn,
+}
+
+impl VariantDatabase {
+    pub async fn list(&self, qr_code_id: Uuid) -> Result<Vec<Model>, DbErr> {
+        DbDestinationVariant::find()
+            .filter(destination_variant::Column::QrCodeId.eq(qr_code_id))
+            .order_by_asc(destination_variant::Column::CreatedAt)
+            .all(&self.db_conn)
+            .await
+    }
+
+    pub async fn create(
+        &self,
+        qr_code_id: Uuid,
+        credential: Credential,
+        name: String,
+        link: Url,
+        weight: i32,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        if find_mutable(&self.db_conn, qr_code_id, &credential, Action::Edit)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let variant = destination_variant::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            qr_code_id: Set(qr_code_id),
+            name: Set(name),
+            link: Set(link.to_string()),
+            weight: Set(weight),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&self.db_conn)
+        .await?;
+
+        Ok(Some(variant))
+    }
+
+    pub async fn update(
+        &self,
+        qr_code_id: Uuid,
+        variant_id: Uuid,
+        credential: Credential,
+        name: String,
+        link: Url,
+        weight: i32,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let Some(variant) = self
+            .find_authorized_variant(qr_code_id, variant_id, &credential)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut active: ActiveModel = variant.into();
+        active.name = Set(name);
+        active.link = Set(link.to_string());
+        active.weight = Set(weight);
+        let variant = active.update(&self.db_conn).await?;
+
+        Ok(Some(variant))
+    }
+
+    pub async fn delete(
+        &self,
+        qr_code_id: Uuid,
+        variant_id: Uuid,
+        credential: Credential,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        let Some(variant) = self
+            .find_authorized_variant(qr_code_id, variant_id, &credential)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        variant.clone().delete(&self.db_conn).await?;
+
+        Ok(Some(variant))
+    }
+
+    /// Picks a variant for a scan, keeping `preferred` if it still belongs to the code.
+    pub async fn choose(
+        &self,
+        qr_code_id: Uuid,
+        preferred: Option<Uuid>,
+    ) -> Result<Option<Model>, DbErr> {
+        let variants = self.list(qr_code_id).await?;
+
+        if let Some(variant) = preferred.and_then(|id| variants.iter().find(|x| x.id == id)) {
+            return Ok(Some(variant.clone()));
+        }
+
+        let total: i64 = variants.iter().map(|x| i64::from(x.weight.max(0))).sum();
+        if total == 0 {
+            return Ok(None);
+        }
+
+        let mut pick = rand::rng().random_range(0..total);
+        for variant in variants {
+            let weight = i64::from(variant.weight.max(0));
+            if pick < weight {
+                return Ok(Some(variant));
+            }
+            pick -= weight;
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the scans per variant and the number of scans without a variant.
+    pub async fn stats(&self, qr_code_id: Uuid) -> Result<(Vec<VariantStats>, i64), DbErr> {
+        let counts: HashMap<Option<Uuid>, i64> = DbScanEvent::find()
+            .select_only()
+            .column(scan_event::Column::VariantId)
+            .column_as(scan_event::Column::Id.count(), "scans")
+            .filter(scan_event::Column::QrCodeId.eq(qr_code_id))
+            .group_by(scan_event::Column::VariantId)
+            .into_tuple::<(Option<Uuid>, i64)>()
+            .all(&self.db_conn)
+            .await?
+            .into_iter()
+            .collect();
+
+        let variants = self
+            .list(qr_code_id)
+            .await?
+            .into_iter()
+            .map(|variant| VariantStats {
+                scans: counts.get(&Some(variant.id)).copied().unwrap_or_default(),
+                variant,
+            })
+            .collect();
+
+        Ok((variants, counts.get(&None).copied().unwrap_or_default()))
+    }
+
+    async fn find_authorized_variant(
+        &self,
+        qr_code_id: Uuid,
+        variant_id: Uuid,
+        credential: &Credential,
+    ) -> Result<Option<Model>, QrCodeDatabaseError> {
+        if find_mutable(&self.db_conn, qr_code_id, credential, Action::Edit)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let variant = DbDestinationVariant::find_by_id(variant_id)
+            .filter(destination_variant::Column::QrCodeId.eq(qr_code_id))
+            .one(&self.db_conn)
+            .await?;
+
+        Ok(variant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{AuditContext, QrCodeDatabase, QrCodeOptions, UserDatabase, testing::database};
+
+    #[tokio::test]
+    async fn splits_scans_by_weight_and_counts_them_per_variant() {
+        let db_conn = database().await;
+        let users = UserDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let codes = QrCodeDatabase {
+            db_conn: db_conn.clone(),
+        };
+        let variants = VariantDatabase { db_conn };
+        let owner = users.register("a@example.com", "password").await.unwrap();
+        let options = QrCodeOptions {
+            owner_id: Some(owner.id),
+            ..Default::default()
+        };
+        let (qr_code, _) = codes
+            .create(
+                Url::parse("https://example.com").unwrap(),
+                options,
+                &AuditContext::default(),
+            )
+            .await
+            .unwrap();
+        let id = qr_code.id;
+        assert!(variants.choose(id, None).await.unwrap().is_none());
+
+        let create = |name: &str, weight| {
+            let link = Url::parse(&format!("https://example.com/{name}")).unwrap();
+            variants.create(
+                id,
+                Credential::User(owner.id),
+                name.to_string(),
+                link,
+                weight,
+            )
+        };
+        let a = create("a", 1).await.unwrap().unwrap();
+        let off = create("off", 0).await.unwrap().unwrap();
+        let c = create("c", 3).await.unwrap().unwrap();
+
+        let mut chosen = HashSet::new();
+        for _ in 0..200 {
+            chosen.insert(variants.choose(id, None).await.unwrap().unwrap().id);
+        }
+        assert_eq!(chosen, HashSet::from([a.id, c.id]));
+
+        // Returning visitors keep their variant, even one that no longer gets new ones.
+        let kept = variants.choose(id, Some(off.id)).await.unwrap().unwrap();
+        assert_eq!(kept.id, off.id);
+        let foreign = variants.choose(id, Some(Uuid::new_v4())).await.unwrap();
+        assert_ne!(foreign.unwrap().id, off.id);
+
+        for variant_id in [Some(a.id), Some(a.id), Some(c.id), None] {
+            codes.register_scan(id, variant_id).await.unwrap();
+        }
+        let (stats, without_variant) = variants.stats(id).await.unwrap();
+        let scans: Vec<_> = stats.iter().map(|x| (x.variant.id, x.scans)).collect();
+        assert_eq!(scans, [(a.id, 2), (off.id, 0), (c.id, 1)]);
+        assert_eq!(without_variant, 1);
+    }
+}